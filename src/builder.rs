@@ -0,0 +1,369 @@
+use std::fs::File;
+use std::fmt;
+use std::io::{Error as IoError, ErrorKind, IsTerminal, Result as IoResult};
+use std::os::fd::OwnedFd;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::Terminal;
+use crate::streams::{detached, is_terminal_closed, set_speed, Streams};
+
+/// How a Ctrl+C keypress is reported while reading keys.
+/// Other than with [`CtrlC::Signal`], the terminal stops sending `SIGINT` for Ctrl+C while keys are read,
+/// leaving Ctrl+Z and Ctrl+\\ to send their signals.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CtrlC {
+    /// Reads fail with an [`std::io::ErrorKind::Interrupted`] error. As callers commonly retry reads
+    /// failing this way, programs choosing this should make sure Ctrl+C still lets the user quit.
+    Error,
+    /// Ctrl+C is delivered as a regular key, `Key::Ctrl('c')`.
+    Key,
+    /// The kernel delivers `SIGINT` to the program, as it does outside of key reads, which ends the
+    /// program unless it handles the signal. Where the terminal passes Ctrl+C on as input instead,
+    /// such as for reads with [`ReadOptions::passthrough`], reads fail as with [`CtrlC::Error`].
+    ///
+    /// [`ReadOptions::passthrough`]: crate::streams::ReadOptions::passthrough
+    #[default]
+    Signal,
+}
+
+/// The line ending written by the `println` family of methods.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Newline {
+    /// A lone line feed, `\n`.
+    #[default]
+    Lf,
+    /// A carriage return followed by a line feed, `\r\n`.
+    /// Useful when output post-processing has been turned off on the terminal.
+    CrLf,
+}
+
+/// Whether styled output should be produced.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Colors are used when the stream is a terminal and `NO_COLOR` is not set.
+    #[default]
+    Auto,
+    /// Colors are always used.
+    Always,
+    /// Colors are never used.
+    Never,
+}
+
+/// Where prompts and widgets are drawn, see [`Terminal::lock_prompt`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PromptOutput {
+    /// The standard output stream.
+    #[default]
+    Stdout,
+    /// The standard error stream, leaving standard output free for the program's data,
+    /// such as when it is piped into another program.
+    Stderr,
+    /// The controlling terminal, `/dev/tty`, which is drawn on even when both standard output
+    /// and error are redirected. Falls back to the standard error stream if it cannot be opened.
+    Tty,
+}
+
+/// How a [`Terminal`] waits for input, for environments where some of the system calls are not allowed,
+/// such as seccomp sandboxes. Background threads shared by terminals, such as the one following resizes,
+/// wait the way chosen for the terminal that started them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PollBackend {
+    /// `poll(2)`.
+    #[default]
+    Poll,
+    /// `select(2)`, which can only wait on descriptors below `FD_SETSIZE` (1024), and cannot tell a
+    /// terminal hanging up apart from the end of input.
+    Select,
+    /// `epoll(7)`, with an instance made for each wait. Only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    Epoll,
+}
+
+/// What the [`Terminal`] facade methods do when reading or writing fails.
+/// Locked streams always return their errors instead.
+#[derive(Clone, Default)]
+pub enum ErrorPolicy {
+    /// The method panics.
+    #[default]
+    Panic,
+    /// Errors from a closed stream, such as when output is piped into `head` (`EPIPE`)
+    /// or the terminal is hung up (see [`is_terminal_closed`]), are ignored; other errors panic.
+    IgnoreClosed,
+    /// Every error is handed to the given function instead.
+    Handler(Arc<dyn Fn(&IoError) + Send + Sync>),
+}
+
+impl fmt::Debug for ErrorPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorPolicy::Panic => f.write_str("Panic"),
+            ErrorPolicy::IgnoreClosed => f.write_str("IgnoreClosed"),
+            ErrorPolicy::Handler(_) => f.write_str("Handler(..)"),
+        }
+    }
+}
+
+// Whether an error comes from the other side of a stream having gone away.
+pub(crate) fn is_closed(error: &IoError) -> bool {
+    error.kind() == ErrorKind::BrokenPipe || is_terminal_closed(error)
+}
+
+// The settings shared by a `Terminal` and every lock taken from it.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Settings {
+    // How long to wait for the rest of an escape sequence after an escape byte.
+    pub(crate) escape_timeout: Duration,
+    // How Ctrl+C is reported.
+    pub(crate) ctrl_c: CtrlC,
+    // The line ending used when printing lines.
+    pub(crate) newline: Newline,
+    // Whether styled output should be produced.
+    pub(crate) color: ColorChoice,
+    // Whether the terminal is dumb, understanding neither raw input nor escape sequences.
+    pub(crate) dumb: bool,
+    // Whether queued identical navigation keys are merged into one event.
+    pub(crate) coalesce_navigation: bool,
+    // Whether the cursor position is followed through output.
+    pub(crate) track_cursor: bool,
+    // How close together identical key presses must be to be reported as repeats.
+    pub(crate) repeat_threshold: Option<Duration>,
+    // How close together identical key presses must be for the later ones to be dropped.
+    pub(crate) debounce: Option<Duration>,
+    // Whether Ctrl+A and Ctrl+E are read as `Key::Ctrl` rather than as Home and End.
+    pub(crate) ctrl_home_end: bool,
+    // The system call waiting for input.
+    pub(crate) poll_backend: PollBackend,
+    // Whether printing leaves flushing the output to the caller.
+    pub(crate) manual_flush: bool,
+}
+
+impl Settings {
+    // Gives the escape timeout in milliseconds, clamped to what `poll` accepts.
+    pub(crate) fn escape_timeout_millis(&self) -> i32 {
+        self.escape_timeout.as_millis().min(i32::MAX as u128) as i32
+    }
+}
+
+/// A builder for configuring a [`Terminal`] before it is created.
+///
+/// ```rust,ignore
+/// use in_keys::Terminal;
+/// use in_keys::builder::CtrlC;
+/// use std::time::Duration;
+///
+/// let terminal = Terminal::builder()
+///     .escape_timeout(Duration::from_millis(25))
+///     .ctrl_c(CtrlC::Key)
+///     .tty_fallback(true)
+///     .build();
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct TerminalBuilder {
+    settings: Settings,
+    tty_fallback: bool,
+    no_input: bool,
+    prompt_output: PromptOutput,
+    errors: ErrorPolicy,
+}
+
+impl TerminalBuilder {
+    /// Creates a builder with the default configuration, matching [`Terminal::new`].
+    pub fn new() -> Self {
+        TerminalBuilder::default()
+    }
+
+    /// Sets how long to wait for the remainder of an escape sequence once an escape byte is read.
+    /// A lone escape byte followed by nothing within this time is reported as the Escape key.
+    /// Defaults to zero, only accepting bytes that are already available.
+    pub fn escape_timeout(mut self, timeout: Duration) -> Self {
+        self.settings.escape_timeout = timeout;
+        self
+    }
+
+    /// Sets how a Ctrl+C keypress is reported while reading keys. Defaults to [`CtrlC::Signal`].
+    pub fn ctrl_c(mut self, ctrl_c: CtrlC) -> Self {
+        self.settings.ctrl_c = ctrl_c;
+        self
+    }
+
+    /// Sets the line ending written by the `println` family of methods.
+    pub fn newline(mut self, newline: Newline) -> Self {
+        self.settings.newline = newline;
+        self
+    }
+
+    /// Overrides whether styled output should be produced.
+    pub fn color(mut self, color: ColorChoice) -> Self {
+        self.settings.color = color;
+        self
+    }
+
+    /// Sets whether identical arrow, Page Up and Page Down presses that are already queued
+    /// when an event is read are merged into a single [`KeyEvent`] with a `repeat` count,
+    /// so that holding a key scrolls proportionally instead of lagging behind.
+    /// Only applies to [`StdinLock::read_event`]. Defaults to off.
+    ///
+    /// [`KeyEvent`]: crate::keys::KeyEvent
+    /// [`StdinLock::read_event`]: crate::streams::StdinLock::read_event
+    pub fn coalesce_navigation(mut self, enabled: bool) -> Self {
+        self.settings.coalesce_navigation = enabled;
+        self
+    }
+
+    /// Sets the system call used to wait for input. Defaults to [`PollBackend::Poll`].
+    pub fn poll_backend(mut self, backend: PollBackend) -> Self {
+        self.settings.poll_backend = backend;
+        self
+    }
+
+    /// Sets whether Ctrl+A and Ctrl+E are read as [`Key::Home`] and [`Key::End`], as line editors
+    /// such as readline bind them, rather than as [`Key::Ctrl`] like the other control characters.
+    /// Defaults to on.
+    ///
+    /// [`Key::Home`]: crate::keys::Key::Home
+    /// [`Key::End`]: crate::keys::Key::End
+    /// [`Key::Ctrl`]: crate::keys::Key::Ctrl
+    pub fn legacy_home_end(mut self, enabled: bool) -> Self {
+        self.settings.ctrl_home_end = !enabled;
+        self
+    }
+
+    /// Sets how close together identical key presses must arrive for the later ones to be reported as
+    /// [`KeyKind::Repeat`], letting interfaces accelerate while a key is held down on terminals that
+    /// do not report key releases. Auto-repeat usually sends a key every 30 to 50 milliseconds,
+    /// after an initial delay. Only applies to [`StdinLock::read_event`]. Defaults to off.
+    ///
+    /// [`KeyKind::Repeat`]: crate::keys::KeyKind::Repeat
+    /// [`StdinLock::read_event`]: crate::streams::StdinLock::read_event
+    pub fn repeat_threshold(mut self, threshold: Option<Duration>) -> Self {
+        self.settings.repeat_threshold = threshold;
+        self
+    }
+
+    /// Sets how close together identical key presses must arrive for the later ones to be dropped,
+    /// filtering out switch bounce from flaky hardware and events some terminal emulators send twice.
+    /// The window starts at the last press kept, so it should stay below the auto-repeat interval
+    /// for held keys to keep repeating. Applies to presses read from the terminal, not replayed ones.
+    /// Defaults to off.
+    pub fn debounce(mut self, window: Option<Duration>) -> Self {
+        self.settings.debounce = window;
+        self
+    }
+
+    /// Sets whether the cursor position is followed through the moves and prints made on the
+    /// standard output stream, so it can be read back with [`StdoutLock::cursor_position_hint`]
+    /// instead of querying the terminal. Defaults to off.
+    ///
+    /// [`StdoutLock::cursor_position_hint`]: crate::streams::StdoutLock::cursor_position_hint
+    pub fn track_cursor(mut self, enabled: bool) -> Self {
+        self.settings.track_cursor = enabled;
+        self
+    }
+
+    /// Sets whether input should be read from `/dev/tty` when the standard input
+    /// stream is not a terminal, such as when data is piped into the program.
+    pub fn tty_fallback(mut self, enabled: bool) -> Self {
+        self.tty_fallback = enabled;
+        self
+    }
+
+    /// Sets whether an input stream is attached at all. Without one, as for programs that only
+    /// draw output, every read fails as if the standard input stream were not a terminal.
+    /// Only applies to terminals using the standard streams. Defaults to on.
+    pub fn attach_input(mut self, enabled: bool) -> Self {
+        self.no_input = !enabled;
+        self
+    }
+
+    /// Sets whether printing flushes the output right away. With it off, output written to the
+    /// standard streams is held in their buffers until [`Write::flush`] is called or a line is ended,
+    /// so a frame drawn from many prints reaches the terminal at once. Defaults to on.
+    ///
+    /// [`Write::flush`]: std::io::Write::flush
+    pub fn auto_flush(mut self, enabled: bool) -> Self {
+        self.settings.manual_flush = !enabled;
+        self
+    }
+
+    /// Sets where prompts, widgets and other interactive output are drawn, see [`Terminal::lock_prompt`].
+    /// Only applies to terminals using the standard streams. Defaults to the standard output stream.
+    pub fn prompt_output(mut self, output: PromptOutput) -> Self {
+        self.prompt_output = output;
+        self
+    }
+
+    /// Sets what the [`Terminal`] facade methods do when reading or writing fails. Defaults to panicking.
+    pub fn errors(mut self, policy: ErrorPolicy) -> Self {
+        self.errors = policy;
+        self
+    }
+
+    /// Creates the [`Terminal`] with the configured options.
+    pub fn build(self) -> Terminal {
+        let input = !self.no_input;
+        let streams = Arc::new(Streams::with(self.settings, input, self.tty_fallback, self.prompt_output));
+        Terminal { streams, errors: self.errors }
+    }
+
+    /// Creates the [`Terminal`] like [`TerminalBuilder::build`], failing instead if no terminal could
+    /// be found to read from, for programs that cannot go on without one.
+    pub fn try_build(self) -> IoResult<Terminal> {
+        let terminal = self.build();
+        match terminal.streams.has_input() {
+            true => Ok(terminal),
+            false => Err(detached()),
+        }
+    }
+
+    /// Creates the [`Terminal`] writing all output, errors included, to an opened device such as `/dev/tty`
+    /// or a pseudo-terminal, so the cursor, clearing and other escape sequence helpers of [`StdoutLock`]
+    /// reach the screen while the standard output stream is redirected to a file.
+    /// Any writer owning a descriptor can be given, such as a [`File`], a [`UnixStream`] or an [`OwnedFd`];
+    /// the descriptor is written to directly, which lets every lock share it and query the terminal size through it.
+    /// Input is read as with [`TerminalBuilder::build`].
+    ///
+    /// [`StdoutLock`]: crate::streams::StdoutLock
+    /// [`UnixStream`]: std::os::unix::net::UnixStream
+    pub fn build_output(self, output: impl Into<OwnedFd>) -> Terminal {
+        let input = !self.no_input;
+        let output = File::from(output.into());
+        let streams = Streams::with(self.settings, input, self.tty_fallback, self.prompt_output).with_output(output);
+        Terminal { streams: Arc::new(streams), errors: self.errors }
+    }
+
+    /// Creates the [`Terminal`] reading input from an opened terminal device, such as `/dev/tty`
+    /// or the slave side of a pseudo-terminal, while output still goes to the standard streams.
+    /// This lets programs whose standard input is a pipe read keys from the user.
+    /// Any descriptor can be given by converting it first, as in `File::from(fd)`.
+    /// Fails if `input` is not a terminal.
+    pub fn build_input(self, input: File) -> IoResult<Terminal> {
+        if !input.is_terminal() {
+            return Err(IoError::new(ErrorKind::InvalidInput, "input is not a terminal"));
+        }
+
+        let streams = Arc::new(Streams::from_input(self.settings, input, self.prompt_output));
+        Ok(Terminal { streams, errors: self.errors })
+    }
+
+    /// Creates the [`Terminal`] over an opened serial device, such as `/dev/ttyUSB0`,
+    /// setting its line speed to `baud`. Input is read from and all output is written to the device.
+    /// Fails if the baud rate is not supported or the device is not a terminal.
+    pub fn build_serial(self, device: File, baud: u32) -> IoResult<Terminal> {
+        set_speed(&device, baud)?;
+
+        let streams = Arc::new(Streams::from_tty(self.settings, device));
+        Ok(Terminal { streams, errors: self.errors })
+    }
+
+    /// Creates the [`Terminal`] over the master side of a pseudo-terminal, as held by SSH servers,
+    /// terminal multiplexers and test harnesses. Input is read from `input` and all output is
+    /// written to `output`, which may be two handles to the same master (see [`File::try_clone`]).
+    /// The size seen by the other side can be changed with [`StdoutLock::set_size`].
+    ///
+    /// [`StdoutLock::set_size`]: crate::streams::StdoutLock::set_size
+    pub fn build_pty(self, input: File, output: File) -> Terminal {
+        let streams = Arc::new(Streams::from_ttys(self.settings, input, output));
+        Terminal { streams, errors: self.errors }
+    }
+}
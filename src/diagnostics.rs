@@ -0,0 +1,120 @@
+//! Diagnostics for the state of the terminal, meant to be attached to bug reports.
+//!
+//! ```rust,ignore
+//! use in_keys::{diagnostics, Terminal};
+//!
+//! eprintln!("{}", diagnostics::report());
+//!
+//! // Echoes the bytes sent for each key until `q` is pressed
+//! diagnostics::echo_keys(&Terminal::new())?;
+//! ```
+
+use std::env;
+use std::fmt::{self, Write as _};
+use std::io::{self, IsTerminal, Result as IoResult};
+
+use crate::streams::{attributes, detached};
+use crate::{Target, Terminal};
+
+// The environment variables that influence how terminals behave.
+const VARIABLES: &[&str] = &[
+    "TERM", "COLORTERM", "TERM_PROGRAM", "TERM_PROGRAM_VERSION",
+    "NO_COLOR", "LANG", "LC_ALL", "LC_CTYPE", "TMUX", "STY", "SSH_TTY",
+];
+
+// The `termios` local, input and output flags worth reporting, by name.
+const LOCAL_FLAGS: &[(&str, libc::tcflag_t)] = &[
+    ("ICANON", libc::ICANON), ("ECHO", libc::ECHO), ("ECHOE", libc::ECHOE),
+    ("ECHOK", libc::ECHOK), ("ECHONL", libc::ECHONL), ("ISIG", libc::ISIG),
+    ("IEXTEN", libc::IEXTEN), ("TOSTOP", libc::TOSTOP),
+];
+const INPUT_FLAGS: &[(&str, libc::tcflag_t)] = &[
+    ("ICRNL", libc::ICRNL), ("INLCR", libc::INLCR), ("IGNCR", libc::IGNCR),
+    ("IXON", libc::IXON), ("IXOFF", libc::IXOFF), ("ISTRIP", libc::ISTRIP),
+    ("IUTF8", libc::IUTF8), ("BRKINT", libc::BRKINT),
+];
+const OUTPUT_FLAGS: &[(&str, libc::tcflag_t)] = &[
+    ("OPOST", libc::OPOST), ("ONLCR", libc::ONLCR),
+];
+
+// Lists which of the named flags are set, prefixing unset ones with `-` as `stty` does.
+fn flags(set: libc::tcflag_t, names: &[(&str, libc::tcflag_t)]) -> String {
+    names
+        .iter()
+        .map(|(name, flag)| match set & flag == *flag {
+            true => name.to_string(),
+            false => format!("-{}", name),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Gives a report on the current state of the terminal: the `termios` flags of the input stream,
+/// the window size, detected capabilities, and the relevant environment variables.
+pub fn report() -> String {
+    let mut report = String::new();
+    // Writing to a `String` cannot fail
+    write_report(&mut report).expect("failed to write report");
+
+    report
+}
+
+// Writes the sections of the report.
+fn write_report(report: &mut String) -> fmt::Result {
+    let terminal = Terminal::new();
+    writeln!(report, "in-keys {}", env!("CARGO_PKG_VERSION"))?;
+
+    writeln!(report, "\nstreams:")?;
+    writeln!(report, "  stdin is a terminal: {}", io::stdin().is_terminal())?;
+    writeln!(report, "  stdout is a terminal: {}", io::stdout().is_terminal())?;
+    writeln!(report, "  stderr is a terminal: {}", io::stderr().is_terminal())?;
+
+    writeln!(report, "\ntermios:")?;
+    match attributes(&io::stdin()) {
+        Ok(termios) => {
+            writeln!(report, "  local: {}", flags(termios.c_lflag, LOCAL_FLAGS))?;
+            writeln!(report, "  input: {}", flags(termios.c_iflag, INPUT_FLAGS))?;
+            writeln!(report, "  output: {}", flags(termios.c_oflag, OUTPUT_FLAGS))?;
+            writeln!(report, "  VMIN: {}, VTIME: {}", termios.c_cc[libc::VMIN], termios.c_cc[libc::VTIME])?;
+        },
+        Err(error) => writeln!(report, "  unavailable: {}", error)?,
+    }
+
+    let stdout = terminal.lock_stdout();
+    writeln!(report, "\ncapabilities:")?;
+    match stdout.size() {
+        Some((rows, columns)) => writeln!(report, "  size: {} rows, {} columns", rows, columns)?,
+        None => writeln!(report, "  size: unknown")?,
+    }
+    writeln!(report, "  colors: {}", stdout.colors_enabled())?;
+    writeln!(report, "  input attached: {}", terminal.lock_stdin().is_some())?;
+
+    writeln!(report, "\nenvironment:")?;
+    for variable in VARIABLES {
+        match env::var_os(variable) {
+            Some(value) => writeln!(report, "  {}={}", variable, value.to_string_lossy())?,
+            None => writeln!(report, "  {} is unset", variable)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Echoes the raw bytes the terminal sends for each key, until `q` is pressed.
+/// Useful for finding out which sequences a terminal uses for keys that are not recognized.
+pub fn echo_keys(terminal: &Terminal) -> IoResult<()> {
+    let Some(mut stdin) = terminal.lock_stdin() else {
+        return Err(detached());
+    };
+
+    terminal.println(Target::Stdout, "Press keys to see their sequences, or `q` to quit.");
+
+    loop {
+        let bytes = stdin.read_raw()?;
+        if bytes == b"q" { return Ok(()); }
+
+        let hex = bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(" ");
+        let escaped = bytes.escape_ascii().to_string();
+        terminal.println(Target::Stdout, &format!("{:<24} \"{}\"", hex, escaped));
+    }
+}
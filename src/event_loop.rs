@@ -0,0 +1,166 @@
+//! A minimal event loop, for applications wanting more than raw events but less than a full framework.
+//!
+//! [`run_loop`] sets the terminal up, hands every event to a handler along with regular ticks and
+//! draw requests, and puts the terminal back as it was once the handler breaks out of the loop.
+//!
+//! ```rust,ignore
+//! use std::ops::ControlFlow;
+//! use std::time::Duration;
+//! use in_keys::Terminal;
+//! use in_keys::event_loop::{run_loop, LoopEvent, LoopOptions};
+//! use in_keys::events::Event;
+//! use in_keys::keys::Key;
+//!
+//! let terminal = Terminal::new();
+//! let options = LoopOptions { tick: Some(Duration::from_secs(1)), ..LoopOptions::default() };
+//! let mut seconds = 0;
+//!
+//! let total = run_loop(&terminal, options, |event, context| {
+//!     match event {
+//!         LoopEvent::Event(Event::Key(event)) if event.key == Key::Char('q') => return ControlFlow::Break(seconds),
+//!         LoopEvent::Tick => {
+//!             seconds += 1;
+//!             context.redraw();
+//!         },
+//!         LoopEvent::Draw => context.terminal().lock_stdout().print(&format!("\r{} seconds", seconds)).unwrap(),
+//!         _ => {},
+//!     }
+//!
+//!     ControlFlow::Continue(())
+//! })?;
+//! ```
+
+use std::io::{Result as IoResult, Write};
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+use crate::events::{Event, MouseMode};
+use crate::streams::detached;
+use crate::Terminal;
+
+/// How [`run_loop`] sets the terminal up for the duration of the loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopOptions {
+    /// How often to give [`LoopEvent::Tick`], if at all.
+    pub tick: Option<Duration>,
+    /// Whether to hide the cursor.
+    pub hide_cursor: bool,
+    /// The mode to capture the mouse in, if at all.
+    pub mouse: Option<MouseMode>,
+    /// Whether to report the window gaining and losing focus.
+    pub focus: bool,
+    /// Whether to read pasted text whole, as [`Event::Paste`].
+    pub paste: bool,
+}
+
+impl Default for LoopOptions {
+    fn default() -> Self {
+        LoopOptions { tick: None, hide_cursor: true, mouse: None, focus: false, paste: false }
+    }
+}
+
+/// What the handler of [`run_loop`] is given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoopEvent {
+    /// An event was read from the terminal.
+    Event(Event),
+    /// The tick interval elapsed.
+    Tick,
+    /// The screen should be drawn, as asked for with [`LoopContext::redraw`], first thing, or after a resize.
+    Draw,
+}
+
+/// What the handler of [`run_loop`] can act on.
+#[derive(Debug)]
+pub struct LoopContext {
+    terminal: Terminal,
+    redraw: bool,
+}
+
+impl LoopContext {
+    /// Gives the terminal the loop runs on, for drawing to.
+    pub fn terminal(&self) -> &Terminal {
+        &self.terminal
+    }
+
+    /// Asks for a [`LoopEvent::Draw`]. Requests made while input is queued are merged
+    /// into a single draw once it has all been handled.
+    pub fn redraw(&mut self) {
+        self.redraw = true;
+    }
+}
+
+// Undoes the setup of the loop once dropped, even if the handler panics.
+struct Teardown<'a> {
+    terminal: &'a Terminal,
+    options: LoopOptions,
+}
+
+impl Drop for Teardown<'_> {
+    fn drop(&mut self) {
+        // Errors cannot be reported from here, and leave nothing worse than the setup in place
+        let mut output = self.terminal.lock_stdout();
+        if self.options.paste { let _ = output.disable_bracketed_paste(); }
+        if self.options.focus { let _ = output.disable_focus_events(); }
+        if self.options.mouse.is_some() { let _ = output.disable_mouse_capture(); }
+        if self.options.hide_cursor { let _ = output.show(); }
+        let _ = output.flush();
+    }
+}
+
+/// Runs `handler` on every event read from `terminal`, along with ticks and draws, until it gives
+/// [`ControlFlow::Break`], whose value is then given back. Resizes are read as events and followed by a draw.
+/// Input is read raw and unechoed meanwhile, and the terminal is set back up as it was afterwards.
+/// Fails if the input is not attached to a terminal, if resize events cannot be enabled, or if reading or writing fails.
+pub fn run_loop<T>(
+    terminal: &Terminal,
+    options: LoopOptions,
+    mut handler: impl FnMut(LoopEvent, &mut LoopContext) -> ControlFlow<T>,
+) -> IoResult<T> {
+    let mut stdin = terminal.lock_stdin().ok_or_else(detached)?;
+    terminal.enable_resize_events()?;
+
+    let teardown = Teardown { terminal, options };
+    {
+        let mut output = terminal.lock_stdout();
+        if options.hide_cursor { output.hide()?; }
+        if let Some(mode) = options.mouse { output.enable_mouse_capture(mode)?; }
+        if options.focus { output.enable_focus_events()?; }
+        if options.paste { output.enable_bracketed_paste()?; }
+        output.flush()?;
+    }
+
+    let mut stdin = stdin.echo_off()?;
+    let mut context = LoopContext { terminal: terminal.clone(), redraw: true };
+    let mut next_tick = options.tick.map(|tick| Instant::now() + tick);
+
+    let result = loop {
+        // Draws wait until the input queued so far has been handled
+        if context.redraw && !stdin.has_input(Some(Duration::ZERO))? {
+            context.redraw = false;
+            if let ControlFlow::Break(value) = handler(LoopEvent::Draw, &mut context) { break value; }
+        }
+
+        let event = match next_tick {
+            Some(tick) => stdin.read_event_or_timeout(tick.saturating_duration_since(Instant::now()))?.value(),
+            None => Some(stdin.read_event()?),
+        };
+
+        let event = match event {
+            Some(event) => {
+                if let Event::Resize(..) = event { context.redraw = true; }
+                LoopEvent::Event(event)
+            },
+            None => {
+                // Ticks missed while handling slow events are not made up for
+                next_tick = options.tick.map(|tick| Instant::now() + tick);
+                LoopEvent::Tick
+            },
+        };
+
+        if let ControlFlow::Break(value) = handler(event, &mut context) { break value; }
+    };
+
+    drop(teardown);
+    Ok(result)
+}
@@ -0,0 +1,221 @@
+use std::str;
+
+use crate::keys::{Key, KeyEvent};
+
+/// Represents an input event read from the terminal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A key was pressed
+    Key(KeyEvent),
+    /// The terminal was resized, to (`rows`, `columns`), while resize events were enabled
+    Resize(u16, u16),
+    /// The mouse was used while mouse capture was enabled
+    Mouse(MouseEvent),
+    /// Text was pasted, given whole
+    Paste(String),
+    /// The terminal window gained focus while focus events were enabled
+    FocusGained,
+    /// The terminal window lost focus while focus events were enabled
+    FocusLost,
+}
+
+impl From<Key> for Event {
+    fn from(key: Key) -> Self {
+        Event::Key(key.into())
+    }
+}
+
+/// How mouse positions are reported once mouse capture is enabled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MouseMode {
+    /// Positions are reported in cells (SGR, mode 1006).
+    #[default]
+    Cells,
+    /// Positions are reported in pixels (SGR-Pixels, mode 1016), with the cell computed from them.
+    /// Terminals without support for the mode keep reporting cells.
+    Pixels,
+}
+
+/// The mouse button involved in a [`MouseEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    /// Left button
+    Left,
+    /// Middle button (or wheel click)
+    Middle,
+    /// Right button
+    Right,
+    /// No button, such as when the mouse is moved or the wheel is scrolled
+    None,
+}
+
+/// What happened in a [`MouseEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseKind {
+    /// A button was pressed
+    Press,
+    /// A button was released
+    Release,
+    /// The mouse was moved while a button was held
+    Drag,
+    /// The mouse was moved with no button held
+    Move,
+    /// The wheel was scrolled up
+    ScrollUp,
+    /// The wheel was scrolled down
+    ScrollDown,
+    /// The wheel was scrolled left
+    ScrollLeft,
+    /// The wheel was scrolled right
+    ScrollRight,
+}
+
+/// A mouse report, with positions in (`row`, `column`) notation starting from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    /// What happened
+    pub kind: MouseKind,
+    /// The button involved
+    pub button: MouseButton,
+    /// The row of the cell under the mouse. In [`MouseMode::Pixels`], 0 if [`MouseEvent::cell`] is unknown.
+    pub row: usize,
+    /// The column of the cell under the mouse. In [`MouseMode::Pixels`], 0 if [`MouseEvent::cell`] is unknown.
+    pub column: usize,
+    /// The position under the mouse in pixels, (`row`, `column`), when reported in [`MouseMode::Pixels`].
+    pub pixel: Option<(usize, usize)>,
+    /// The cell under the mouse, (`row`, `column`), as computed from [`MouseEvent::pixel`] in [`MouseMode::Pixels`].
+    /// [`None`] in [`MouseMode::Cells`], or if the terminal does not report its size in pixels, leaving the cell unknown.
+    pub cell: Option<(usize, usize)>,
+}
+
+impl MouseEvent {
+    // Decodes the body of an SGR mouse report, `b;x;y` followed by `M` (press) or `m` (release).
+    // In pixel mode, `cell_size` holds the size of a cell in pixels, (`height`, `width`), if it is known;
+    // otherwise the cell position is left out.
+    pub(crate) fn from_sgr(body: &[u8], mode: MouseMode, cell_size: Option<(usize, usize)>) -> Option<Self> {
+        let (&last, params) = body.split_last()?;
+        let released = match last {
+            b'M' => false,
+            b'm' => true,
+            _ => return None,
+        };
+
+        // Parse the three semicolon-separated parameters
+        let mut params = str::from_utf8(params).ok()?.split(';').map(str::parse::<usize>);
+        let (Some(Ok(code)), Some(Ok(x)), Some(Ok(y)), None) =
+            (params.next(), params.next(), params.next(), params.next())
+        else {
+            return None;
+        };
+
+        // The low bits hold the button, 32 marks motion and 64 marks the wheel
+        let button = match code & 0b11 {
+            0 => MouseButton::Left,
+            1 => MouseButton::Middle,
+            2 => MouseButton::Right,
+            _ => MouseButton::None,
+        };
+
+        let (kind, button) = match (code & 0b1100000, released) {
+            (64, _) => match code & 0b11 {
+                0 => (MouseKind::ScrollUp, MouseButton::None),
+                1 => (MouseKind::ScrollDown, MouseButton::None),
+                2 => (MouseKind::ScrollLeft, MouseButton::None),
+                _ => (MouseKind::ScrollRight, MouseButton::None),
+            },
+            (32, _) if button == MouseButton::None => (MouseKind::Move, button),
+            (32, _) => (MouseKind::Drag, button),
+            (_, true) => (MouseKind::Release, button),
+            (_, false) => (MouseKind::Press, button),
+        };
+
+        let (pixel, cell) = match mode {
+            MouseMode::Cells => (None, None),
+            MouseMode::Pixels => match cell_size {
+                Some((height, width)) if height > 0 && width > 0 => {
+                    (Some((y, x)), Some((y.saturating_sub(1) / height + 1, x.saturating_sub(1) / width + 1)))
+                },
+                _ => (Some((y, x)), None),
+            },
+        };
+
+        let (row, column) = match (mode, cell) {
+            (MouseMode::Cells, _) => (y, x),
+            (MouseMode::Pixels, cell) => cell.unwrap_or((0, 0)),
+        };
+
+        Some(MouseEvent { kind, button, row, column, pixel, cell })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Parses a report body in cells.
+    fn cells(body: &str) -> Option<MouseEvent> {
+        MouseEvent::from_sgr(body.as_bytes(), MouseMode::Cells, None)
+    }
+
+    #[test]
+    fn parses_presses_and_releases() {
+        assert_eq!(cells("0;10;5M"), Some(MouseEvent {
+            kind: MouseKind::Press,
+            button: MouseButton::Left,
+            row: 5,
+            column: 10,
+            pixel: None,
+            cell: None,
+        }));
+
+        let release = cells("2;3;4m").unwrap();
+        assert_eq!((release.kind, release.button), (MouseKind::Release, MouseButton::Right));
+        assert_eq!((release.row, release.column), (4, 3));
+
+        let middle = cells("1;1;1M").unwrap();
+        assert_eq!((middle.kind, middle.button), (MouseKind::Press, MouseButton::Middle));
+    }
+
+    #[test]
+    fn parses_motion_and_wheel() {
+        let drag = cells("32;7;2M").unwrap();
+        assert_eq!((drag.kind, drag.button), (MouseKind::Drag, MouseButton::Left));
+
+        let moved = cells("35;7;2M").unwrap();
+        assert_eq!((moved.kind, moved.button), (MouseKind::Move, MouseButton::None));
+
+        let scrolls = [(64, MouseKind::ScrollUp), (65, MouseKind::ScrollDown), (66, MouseKind::ScrollLeft), (67, MouseKind::ScrollRight)];
+        for (code, kind) in scrolls {
+            let scroll = cells(&format!("{};1;1M", code)).unwrap();
+            assert_eq!((scroll.kind, scroll.button), (kind, MouseButton::None));
+        }
+
+        // Modifier bits are ignored
+        assert_eq!(cells("4;1;1M").unwrap().kind, MouseKind::Press);
+    }
+
+    #[test]
+    fn computes_cells_from_pixels() {
+        // With cells 16 pixels high and 8 wide, pixel 1 is in the first cell and pixel 17 in the second
+        let event = MouseEvent::from_sgr(b"0;17;16M", MouseMode::Pixels, Some((16, 8))).unwrap();
+        assert_eq!((event.row, event.column, event.pixel, event.cell), (1, 3, Some((16, 17)), Some((1, 3))));
+
+        let event = MouseEvent::from_sgr(b"0;1;17m", MouseMode::Pixels, Some((16, 8))).unwrap();
+        assert_eq!((event.row, event.column, event.pixel, event.cell), (2, 1, Some((17, 1)), Some((2, 1))));
+    }
+
+    #[test]
+    fn leaves_cells_unknown_without_a_cell_size() {
+        for cell_size in [None, Some((0, 8)), Some((16, 0))] {
+            let event = MouseEvent::from_sgr(b"0;40;20M", MouseMode::Pixels, cell_size).unwrap();
+            assert_eq!((event.row, event.column, event.pixel, event.cell), (0, 0, Some((20, 40)), None));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_reports() {
+        for body in ["", "M", "0;1M", "0;1;2;3M", "0;1;2", "0;1;2X", "a;1;2M", "0;-1;2M", "0;;2M"] {
+            assert_eq!(cells(body), None, "{:?}", body);
+        }
+    }
+}
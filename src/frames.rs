@@ -0,0 +1,219 @@
+//! Pacing animated output, so quick redraws do not saturate slow links such as SSH sessions.
+//!
+//! A [`FrameScheduler`] keeps only the latest frame queued, writes it no more often than the
+//! target rate allows, and skips frames identical to the one already on screen. When writing
+//! falls behind, because frames take longer to write than the rate allows or the output would
+//! block, frames are spaced further apart until the output keeps up again.
+//!
+//! ```rust,ignore
+//! use in_keys::Terminal;
+//! use in_keys::frames::FrameScheduler;
+//!
+//! let terminal = Terminal::new();
+//! let mut frames = FrameScheduler::new(30);
+//! let mut stdin = terminal.lock_stdin().expect("Failed to connect with terminal");
+//!
+//! for percent in 0..=100 {
+//!     frames.queue(format!("\r{}%", percent));
+//!
+//!     // Frames queued before the next one is due replace each other
+//!     frames.flush(&mut terminal.lock_stdout())?;
+//!     if let Some(due) = frames.due() { stdin.has_input(Some(due))?; }
+//! }
+//!
+//! frames.finish(&mut terminal.lock_stdout())?;
+//! ```
+
+use std::fmt;
+use std::io::{ErrorKind, Result as IoResult, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// The furthest apart frames are spaced when the output falls behind.
+const MAX_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A change in how often a [`FrameScheduler`] writes, as given to the hook set with [`FrameScheduler::on_throttle`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Throttle {
+    /// Writing fell behind, so frames are now spaced `interval` apart.
+    Slowed {
+        /// The time now left between frames.
+        interval: Duration,
+    },
+    /// Frames are written at the target rate again.
+    Recovered,
+}
+
+/// Writes frames of output at most at a target rate, dropping the ones queued in between.
+#[derive(Clone)]
+pub struct FrameScheduler {
+    // The time between frames at the target rate.
+    interval: Duration,
+    // The time between frames currently kept, longer than `interval` while throttled.
+    current: Duration,
+    // How many queued frames were replaced before being written.
+    dropped: u64,
+    // Called as the output is throttled or stops being throttled.
+    hook: Option<Arc<dyn Fn(Throttle) + Send + Sync>>,
+    // When the last frame was written.
+    written_at: Option<Instant>,
+    // The last frame written, or the one being written if the output would have blocked partway.
+    shown: String,
+    // How many bytes of `shown` are written.
+    written: usize,
+    // Whether `shown` is flushed once written.
+    flushed: bool,
+    // The latest frame queued and not written yet.
+    pending: Option<String>,
+}
+
+impl FrameScheduler {
+    /// Creates a scheduler writing at most `fps` frames a second, with 0 taken as 1.
+    pub fn new(fps: u32) -> Self {
+        FrameScheduler::with_interval(Duration::from_secs(1) / fps.max(1))
+    }
+
+    /// Creates a scheduler leaving at least `interval` between the frames it writes.
+    pub fn with_interval(interval: Duration) -> Self {
+        FrameScheduler {
+            interval,
+            current: interval,
+            dropped: 0,
+            hook: None,
+            written_at: None,
+            shown: String::new(),
+            written: 0,
+            flushed: true,
+            pending: None,
+        }
+    }
+
+    /// Sets a function to be called whenever frames are slowed down or back at the target rate.
+    pub fn on_throttle(mut self, hook: impl Fn(Throttle) + Send + Sync + 'static) -> Self {
+        self.hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Queues `frame`, the whole of the output drawing it, replacing any frame queued before that was not written.
+    /// A frame identical to the last one written is dropped.
+    pub fn queue(&mut self, frame: String) {
+        if self.pending.is_some() { self.dropped += 1; }
+        self.pending = (frame != self.shown).then_some(frame);
+    }
+
+    /// Whether a frame is waiting to be written, or to be finished being written.
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some() || !self.is_shown()
+    }
+
+    // Whether the last frame is fully written and flushed.
+    fn is_shown(&self) -> bool {
+        self.written == self.shown.len() && self.flushed
+    }
+
+    /// Whether frames are spaced further apart than the target rate, as writing fell behind.
+    pub fn is_throttled(&self) -> bool {
+        self.current > self.interval
+    }
+
+    /// Gives how many queued frames were replaced by later ones before being written.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Gives how long until the queued frame may be written, which is zero if it is due already,
+    /// or `None` if no frame is queued. Suited as a timeout for reading input in between frames.
+    pub fn due(&self) -> Option<Duration> {
+        if !self.is_pending() { return None; }
+
+        let since = self.written_at.map_or(self.current, |written_at| written_at.elapsed());
+        Some(self.current.saturating_sub(since))
+    }
+
+    /// Writes the queued frame to `output` and flushes it, if one is queued and due.
+    /// Gives whether a frame was written.
+    pub fn flush(&mut self, output: &mut impl Write) -> IoResult<bool> {
+        match self.due() {
+            Some(Duration::ZERO) => self.finish(output),
+            _ => Ok(false),
+        }
+    }
+
+    /// Writes the queued frame to `output` and flushes it right away, if one is queued, such as
+    /// for the last frame of an animation. Gives whether a frame was written.
+    /// If the output would block, frames are slowed down, and the rest of the frame is written on the
+    /// next call, before any frame queued since, so no part of it is written twice.
+    pub fn finish(&mut self, output: &mut impl Write) -> IoResult<bool> {
+        if self.is_shown() {
+            let Some(frame) = self.pending.take() else { return Ok(false) };
+            (self.shown, self.written, self.flushed) = (frame, 0, false);
+        }
+
+        let start = Instant::now();
+        let written = self.write_rest(output);
+        self.written_at = Some(Instant::now());
+
+        match written {
+            Err(error) if error.kind() == ErrorKind::WouldBlock => {
+                self.pace(self.current * 2);
+                Ok(false)
+            },
+            Err(error) => Err(error),
+            Ok(()) => {
+                // A frame taking longer to write than the rate allows leaves the link time to catch up,
+                // while quick ones bring the rate back up step by step
+                let took = start.elapsed();
+                match took > self.interval {
+                    true => self.pace(took * 2),
+                    false => self.pace(self.current / 2),
+                }
+
+                Ok(true)
+            },
+        }
+    }
+
+    // Writes what is left of the last frame to `output` and flushes it, keeping count of what was written.
+    fn write_rest(&mut self, output: &mut impl Write) -> IoResult<()> {
+        while self.written < self.shown.len() {
+            match output.write(&self.shown.as_bytes()[self.written..]) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(written) => self.written += written,
+                Err(error) if error.kind() == ErrorKind::Interrupted => {},
+                Err(error) => return Err(error),
+            }
+        }
+
+        output.flush()?;
+        self.flushed = true;
+        Ok(())
+    }
+
+    // Spaces frames `interval` apart, kept from the target rate to the slowest allowed,
+    // and tells the hook if that throttles or stops throttling the output.
+    fn pace(&mut self, interval: Duration) {
+        let interval = interval.clamp(self.interval, MAX_INTERVAL.max(self.interval));
+        let (throttled, slowed) = (self.is_throttled(), interval > self.current);
+        self.current = interval;
+
+        let change = match self.is_throttled() {
+            true if slowed => Throttle::Slowed { interval },
+            false if throttled => Throttle::Recovered,
+            _ => return,
+        };
+
+        if let Some(hook) = &self.hook { hook(change); }
+    }
+}
+
+impl fmt::Debug for FrameScheduler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameScheduler")
+            .field("interval", &self.interval)
+            .field("current", &self.current)
+            .field("dropped", &self.dropped)
+            .field("written", &self.written)
+            .field("pending", &self.pending)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,285 @@
+//! Bindings from keys to named actions, which end users can customize through a config file.
+//!
+//! With the `json` or `toml` features, a keymap can be loaded from a table mapping key specs
+//! (see [`Key`]'s [`FromStr`] implementation) to action names:
+//!
+//! ```toml
+//! q = "quit"
+//! Up = "scroll-up"
+//! PageDown = "next-page"
+//! ```
+//!
+//! ```rust,ignore
+//! use in_keys::Terminal;
+//! use in_keys::keymap::Keymap;
+//!
+//! let keymap = Keymap::from_toml(&std::fs::read_to_string("keys.toml")?)?;
+//! let terminal = Terminal::new();
+//!
+//! match keymap.action(&terminal.read_key()) {
+//!     Some("quit") => return Ok(()),
+//!     _ => {},
+//! }
+//! ```
+//!
+//! Keys pressed twice or paired up in quick succession, such as Escape Escape or `g g` in Vim-like
+//! bindings, can be read as one with a [`ChordReader`]:
+//!
+//! ```rust,ignore
+//! use std::time::Duration;
+//! use in_keys::keymap::{Chord, ChordReader};
+//!
+//! let mut chords = ChordReader::new(Duration::from_millis(400));
+//! chords.bind(Key::Char('g'), Key::Char('g'));
+//!
+//! match chords.read(&mut stdin)? {
+//!     Chord::Double(Key::Char('g'), Key::Char('g')) => scroll_to_top(),
+//!     Chord::Single(key) => handle(key),
+//!     _ => {},
+//! }
+//! ```
+//!
+//! [`FromStr`]: std::str::FromStr
+
+use std::collections::HashMap;
+use std::io::Result as IoResult;
+use std::time::Duration;
+use std::{error, fmt};
+
+use crate::keys::Key;
+use crate::streams::KeySource;
+
+/// A set of bindings from keys to the names of actions.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: HashMap<Key, String>,
+}
+
+/// An error from loading a [`Keymap`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeymapError {
+    /// The document could not be parsed, or is not a table.
+    Syntax(String),
+    /// A binding is invalid, such as one with an unknown key spec or an action that is not a string.
+    Binding {
+        /// The key spec of the binding.
+        spec: String,
+        /// Why the binding is invalid.
+        reason: String,
+    },
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeymapError::Syntax(message) => write!(f, "invalid keymap: {}", message),
+            KeymapError::Binding { spec, reason } => write!(f, "invalid binding `{}`: {}", spec, reason),
+        }
+    }
+}
+
+impl error::Error for KeymapError {}
+
+impl Keymap {
+    /// Creates a keymap with no bindings.
+    pub fn new() -> Self {
+        Keymap::default()
+    }
+
+    /// Binds a key to an action, giving the action it was bound to before, if any.
+    pub fn bind(&mut self, key: Key, action: &str) -> Option<String> {
+        self.bindings.insert(key, action.to_string())
+    }
+
+    /// Removes the binding of a key, giving the action it was bound to, if any.
+    pub fn unbind(&mut self, key: &Key) -> Option<String> {
+        self.bindings.remove(key)
+    }
+
+    /// Gives the action a key is bound to, if any.
+    pub fn action(&self, key: &Key) -> Option<&str> {
+        self.bindings.get(key).map(String::as_str)
+    }
+
+    /// Gives every binding, in no particular order.
+    pub fn bindings(&self) -> impl Iterator<Item = (&Key, &str)> {
+        self.bindings.iter().map(|(key, action)| (key, action.as_str()))
+    }
+
+    /// Binds the keys of another keymap over the ones of this one, such as user
+    /// bindings over the defaults of an application.
+    pub fn extend(&mut self, other: Keymap) {
+        self.bindings.extend(other.bindings);
+    }
+
+    // Builds a keymap from pairs of key specs and actions, the action being `None` if it is not a string.
+    #[cfg(any(feature = "json", feature = "toml"))]
+    fn from_specs<'a>(specs: impl Iterator<Item = (&'a str, Option<&'a str>)>) -> Result<Self, KeymapError> {
+        let mut keymap = Keymap::new();
+
+        for (spec, action) in specs {
+            let invalid = |reason: String| KeymapError::Binding { spec: spec.to_string(), reason };
+
+            let key = spec.parse::<Key>().map_err(|error| invalid(error.to_string()))?;
+            let action = action.ok_or_else(|| invalid("the action is not a string".to_string()))?;
+
+            // Two specs naming the same key, such as `Esc` and `Escape`, would shadow each other
+            if keymap.bind(key, action).is_some() {
+                return Err(invalid("the key is already bound".to_string()));
+            }
+        }
+
+        Ok(keymap)
+    }
+
+    /// Loads a keymap from a JSON object mapping key specs to action names.
+    /// Fails on the first invalid binding, naming it in the error.
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<Self, KeymapError> {
+        let value: serde_json::Value = serde_json::from_str(json)
+            .map_err(|error| KeymapError::Syntax(error.to_string()))?;
+        let object = value
+            .as_object()
+            .ok_or_else(|| KeymapError::Syntax("expected an object".to_string()))?;
+
+        Keymap::from_specs(object.iter().map(|(spec, action)| (spec.as_str(), action.as_str())))
+    }
+
+    /// Loads a keymap from a TOML table mapping key specs to action names.
+    /// Fails on the first invalid binding, naming it in the error.
+    #[cfg(feature = "toml")]
+    pub fn from_toml(toml: &str) -> Result<Self, KeymapError> {
+        let table: toml::Table = toml.parse().map_err(|error: toml::de::Error| KeymapError::Syntax(error.to_string()))?;
+
+        Keymap::from_specs(table.iter().map(|(spec, action)| (spec.as_str(), action.as_str())))
+    }
+}
+
+/// A key read by a [`ChordReader`], alone or completing a chord.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Chord {
+    /// A key that does not start a chord, or that was not followed by the rest of one in time.
+    Single(Key),
+    /// A bound chord, its second key pressed within the interval of the first.
+    Double(Key, Key),
+}
+
+/// Reads keys, recognizing chords of two keys pressed within an interval of each other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChordReader {
+    chords: Vec<(Key, Key)>,
+    interval: Duration,
+    // A key read while waiting for the end of a chord it did not complete, to be given next.
+    pending: Option<Key>,
+}
+
+impl ChordReader {
+    /// Creates a reader with no chords, waiting up to `interval` for the second key of one.
+    pub fn new(interval: Duration) -> Self {
+        ChordReader { chords: Vec::new(), interval, pending: None }
+    }
+
+    /// Binds a chord of `first` followed by `second`, which can be the same key.
+    pub fn bind(&mut self, first: Key, second: Key) {
+        if !self.chords.contains(&(first, second)) { self.chords.push((first, second)); }
+    }
+
+    /// Reads a key from `keys`, such as the terminal's [`StdinLock`], or the key following a chord that
+    /// was not completed by it. A key starting a chord is only given once the next key arrives or the
+    /// interval elapses, so keys starting no chord are given right away.
+    ///
+    /// [`StdinLock`]: crate::streams::StdinLock
+    pub fn read(&mut self, keys: &mut impl KeySource) -> IoResult<Chord> {
+        let first = match self.pending.take() {
+            Some(key) => key,
+            None => loop {
+                if let Some(key) = keys.read_key(None)? { break key; }
+            },
+        };
+
+        if !self.chords.iter().any(|(start, _)| *start == first) { return Ok(Chord::Single(first)); }
+
+        match keys.read_key(Some(self.interval))? {
+            Some(second) if self.chords.contains(&(first, second)) => Ok(Chord::Double(first, second)),
+            Some(second) => {
+                self.pending = Some(second);
+                Ok(Chord::Single(first))
+            },
+            None => Ok(Chord::Single(first)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binds_over_other_keymaps() {
+        let mut keymap = Keymap::new();
+        keymap.bind(Key::Char('q'), "quit");
+        keymap.bind(Key::ArrowUp, "scroll-up");
+
+        let mut user = Keymap::new();
+        user.bind(Key::Char('q'), "close");
+
+        keymap.extend(user);
+        assert_eq!(keymap.action(&Key::Char('q')), Some("close"));
+        assert_eq!(keymap.action(&Key::ArrowUp), Some("scroll-up"));
+        assert_eq!(keymap.unbind(&Key::ArrowUp).as_deref(), Some("scroll-up"));
+        assert_eq!(keymap.action(&Key::ArrowUp), None);
+    }
+
+    #[cfg(any(feature = "json", feature = "toml"))]
+    #[test]
+    fn loads_bindings_from_specs() {
+        let keymap = Keymap::from_specs([("q", Some("quit")), ("PgDn", Some("next-page"))].into_iter()).unwrap();
+
+        assert_eq!(keymap.action(&Key::Char('q')), Some("quit"));
+        assert_eq!(keymap.action(&Key::PageDown), Some("next-page"));
+        assert_eq!(keymap.bindings().count(), 2);
+    }
+
+    #[cfg(any(feature = "json", feature = "toml"))]
+    #[test]
+    fn rejects_duplicate_bindings() {
+        // Aliases of one key collide just as repeated specs do
+        let error = Keymap::from_specs([("Esc", Some("back")), ("Escape", Some("quit"))].into_iter()).unwrap_err();
+        assert_eq!(error, KeymapError::Binding {
+            spec: "Escape".to_string(),
+            reason: "the key is already bound".to_string(),
+        });
+
+        assert!(Keymap::from_specs([("Ctrl-W", Some("close")), ("ctrl_w", Some("close"))].into_iter()).is_err());
+    }
+
+    #[cfg(any(feature = "json", feature = "toml"))]
+    #[test]
+    fn rejects_invalid_bindings() {
+        let error = Keymap::from_specs([("Hyper", Some("quit"))].into_iter()).unwrap_err();
+        assert_eq!(error, KeymapError::Binding { spec: "Hyper".to_string(), reason: "unknown key `Hyper`".to_string() });
+
+        let error = Keymap::from_specs([("q", None)].into_iter()).unwrap_err();
+        assert_eq!(error, KeymapError::Binding { spec: "q".to_string(), reason: "the action is not a string".to_string() });
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn loads_toml_tables() {
+        let keymap = Keymap::from_toml("q = \"quit\"\nUp = \"scroll-up\"").unwrap();
+        assert_eq!(keymap.action(&Key::ArrowUp), Some("scroll-up"));
+
+        assert!(matches!(Keymap::from_toml("q = 1"), Err(KeymapError::Binding { .. })));
+        assert!(matches!(Keymap::from_toml("q = "), Err(KeymapError::Syntax(_))));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn loads_json_objects() {
+        let keymap = Keymap::from_json(r#"{ "Ctrl-W": "close" }"#).unwrap();
+        assert_eq!(keymap.action(&Key::Ctrl('w')), Some("close"));
+
+        assert!(matches!(Keymap::from_json(r#"{ "Esc": "back", "Escape": "quit" }"#), Err(KeymapError::Binding { .. })));
+        assert!(matches!(Keymap::from_json("[]"), Err(KeymapError::Syntax(_))));
+    }
+}
@@ -22,10 +22,10 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use std::str;
+use std::{error, fmt, str};
 
 /// Represents various types of keyboard input events.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Key {
     /// Unknown or unrecognized key
     Unknown,
@@ -63,8 +63,174 @@ pub enum Key {
     PageUp,
     /// Page Down key
     PageDown,
+    /// Function key, numbered from 1, such as `F(5)` for F5
+    F(u8),
+    /// The end of input: Ctrl+D, or the input stream having nothing more to give, such as once a
+    /// pipe read from is closed. Reads at the end of input keep giving it.
+    Eof,
     /// A printable character (UTF-8)
     Char(char),
+    /// A control character, as typed with Ctrl and a letter, given in lowercase, such as `Ctrl('w')` for Ctrl+W.
+    /// Ctrl+H, Ctrl+I, Ctrl+J and Ctrl+M are read as Backspace, Tab and Enter instead, as terminals send the same
+    /// bytes for them, Ctrl+D as [`Key::Eof`], and Ctrl+A and Ctrl+E as Home and End unless
+    /// [`TerminalBuilder::legacy_home_end`] is turned off.
+    ///
+    /// [`TerminalBuilder::legacy_home_end`]: crate::builder::TerminalBuilder::legacy_home_end
+    Ctrl(char),
+}
+
+/// A key press, as read through [`Event::Key`].
+///
+/// [`Event::Key`]: crate::events::Event::Key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// The key pressed
+    pub key: Key,
+    /// How many consecutive presses of the key this event stands for, at least 1.
+    /// Only above 1 when navigation keys are coalesced, see [`TerminalBuilder::coalesce_navigation`].
+    ///
+    /// [`TerminalBuilder::coalesce_navigation`]: crate::builder::TerminalBuilder::coalesce_navigation
+    pub repeat: u16,
+    /// Whether the key was pressed anew or is being held down
+    pub kind: KeyKind,
+    /// The modifier keys held along with the key, where the terminal reports them
+    pub modifiers: Modifiers,
+}
+
+/// The modifier keys held during a [`KeyEvent`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    /// Shift was held
+    pub shift: bool,
+    /// Alt (or Meta) was held
+    pub alt: bool,
+    /// Ctrl was held
+    pub ctrl: bool,
+}
+
+impl Modifiers {
+    /// Whether no modifier keys were held.
+    pub fn is_empty(&self) -> bool {
+        *self == Modifiers::default()
+    }
+
+    // Decodes the modifier parameter of a control sequence, one more than a bitmask
+    // of Shift (1), Alt (2), Ctrl (4) and Meta (8).
+    pub(crate) fn from_parameter(parameter: u16) -> Self {
+        let mask = parameter.saturating_sub(1);
+        Modifiers { shift: mask & 1 != 0, alt: mask & 0b1010 != 0, ctrl: mask & 4 != 0 }
+    }
+}
+
+/// How a key was pressed, as reported in a [`KeyEvent`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyKind {
+    /// The key was pressed.
+    #[default]
+    Press,
+    /// The key is being held down, as guessed from identical presses arriving in quick succession.
+    /// Only reported once enabled with [`TerminalBuilder::repeat_threshold`].
+    ///
+    /// [`TerminalBuilder::repeat_threshold`]: crate::builder::TerminalBuilder::repeat_threshold
+    Repeat,
+}
+
+/// How a terminal encodes the keys it sends, for [`Key::encode`].
+/// The default matches xterm and most terminals emulating it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TerminalProfile {
+    /// Whether Backspace sends DEL (`0x7f`) rather than BS (`0x08`).
+    pub backspace_del: bool,
+    /// Whether the arrows, Home and End are sent in application mode (`ESC O A`) rather than normal mode (`ESC [ A`),
+    /// as terminals do once a full-screen program asks for it.
+    pub application_cursor: bool,
+}
+
+impl Default for TerminalProfile {
+    fn default() -> Self {
+        TerminalProfile { backspace_del: true, application_cursor: false }
+    }
+}
+
+impl From<Key> for KeyEvent {
+    fn from(key: Key) -> Self {
+        KeyEvent { key, repeat: 1, kind: KeyKind::Press, modifiers: Modifiers::default() }
+    }
+}
+
+impl Key {
+    /// Whether the key produces a visible character, i.e. a non-control [`Key::Char`].
+    pub fn is_printable(&self) -> bool {
+        matches!(self, Key::Char(char) if !char.is_control())
+    }
+
+    /// Whether the key moves the cursor or viewport: the arrows, Home, End, Page Up and Page Down.
+    pub fn is_navigation(&self) -> bool {
+        matches!(
+            self,
+            Key::ArrowLeft | Key::ArrowRight | Key::ArrowUp | Key::ArrowDown
+                | Key::Home | Key::End | Key::PageUp | Key::PageDown
+        )
+    }
+
+    /// Whether the key edits text in place: Backspace, Delete and Insert.
+    pub fn is_editing(&self) -> bool {
+        matches!(self, Key::Backspace | Key::Del | Key::Insert)
+    }
+
+    /// Gives the bytes a terminal with the given profile sends for the key, for synthesizing input
+    /// to a program, such as one on a pseudo-terminal. Keys that send nothing on their own,
+    /// namely [`Key::Alt`], [`Key::Shift`] and [`Key::Unknown`], give no bytes, as do function keys past F12.
+    pub fn encode(&self, profile: &TerminalProfile) -> Vec<u8> {
+        // The cursor keys are introduced differently in application mode
+        let cursor = if profile.application_cursor { "\x1bO" } else { "\x1b[" };
+
+        let sequence = match self {
+            Key::Unknown | Key::Alt | Key::Shift => String::new(),
+            Key::ArrowUp => format!("{}A", cursor),
+            Key::ArrowDown => format!("{}B", cursor),
+            Key::ArrowRight => format!("{}C", cursor),
+            Key::ArrowLeft => format!("{}D", cursor),
+            Key::Home => format!("{}H", cursor),
+            Key::End => format!("{}F", cursor),
+            Key::Enter => "\r".to_string(),
+            Key::Escape => "\x1b".to_string(),
+            Key::Backspace if profile.backspace_del => "\x7f".to_string(),
+            Key::Backspace => "\x08".to_string(),
+            Key::Tab => "\t".to_string(),
+            Key::BackTab => "\x1b[Z".to_string(),
+            Key::Insert => "\x1b[2~".to_string(),
+            Key::Del => "\x1b[3~".to_string(),
+            Key::PageUp => "\x1b[5~".to_string(),
+            Key::PageDown => "\x1b[6~".to_string(),
+            Key::F(number @ 1..=4) => format!("\x1bO{}", (b'P' + number - 1) as char),
+            Key::F(number @ 5..=12) => format!("\x1b[{}~", function_parameter(*number)),
+            Key::F(_) => String::new(),
+            Key::Eof => "\x04".to_string(),
+            Key::Char(char) => char.to_string(),
+            Key::Ctrl(char @ ('@'..='_' | 'a'..='z')) => ((char.to_ascii_uppercase() as u8 & 0x1f) as char).to_string(),
+            Key::Ctrl(_) => String::new(),
+        };
+
+        sequence.into_bytes()
+    }
+
+    /// Gives the character of a [`Key::Char`], or [`None`] for any other key.
+    pub fn as_char(&self) -> Option<char> {
+        match self {
+            Key::Char(char) => Some(*char),
+            _ => None,
+        }
+    }
+}
+
+// Gives the parameter of the `CSI n ~` sequence of function keys F5 to F12, which skips 16 and 22.
+fn function_parameter(number: u8) -> u8 {
+    match number {
+        5 => 15,
+        6..=10 => number + 11,
+        _ => number + 12,
+    }
 }
 
 impl From<&[u8]> for Key {
@@ -74,4 +240,117 @@ impl From<&[u8]> for Key {
             .and_then(|string| string.chars().next())
             .map_or(Key::Unknown, Key::Char)
     }
-}
\ No newline at end of file
+}
+
+/// An error from parsing a key spec that names no key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseKeyError {
+    spec: String,
+}
+
+impl fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown key `{}`", self.spec)
+    }
+}
+
+impl error::Error for ParseKeyError {}
+
+impl str::FromStr for Key {
+    type Err = ParseKeyError;
+
+    /// Parses a key spec: a single character, such as `q`, or a key name, such as `Enter`, `PageUp`, `F5`,
+    /// `Space` or `Ctrl-W`. Names are matched case-insensitively, ignoring `-` and `_`.
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        // A single character stands for itself, keeping its case
+        let mut chars = spec.chars();
+        if let (Some(char), None) = (chars.next(), chars.next()) {
+            return Ok(Key::Char(char));
+        }
+
+        let name: String = spec
+            .chars()
+            .filter(|char| !matches!(char, '-' | '_'))
+            .flat_map(char::to_lowercase)
+            .collect();
+
+        let key = match name.as_str() {
+            "left" | "arrowleft" => Key::ArrowLeft,
+            "right" | "arrowright" => Key::ArrowRight,
+            "up" | "arrowup" => Key::ArrowUp,
+            "down" | "arrowdown" => Key::ArrowDown,
+            "enter" | "return" => Key::Enter,
+            "esc" | "escape" => Key::Escape,
+            "backspace" => Key::Backspace,
+            "home" => Key::Home,
+            "end" => Key::End,
+            "tab" => Key::Tab,
+            "backtab" | "shifttab" => Key::BackTab,
+            "del" | "delete" => Key::Del,
+            "insert" | "ins" => Key::Insert,
+            "pageup" | "pgup" => Key::PageUp,
+            "pagedown" | "pgdn" => Key::PageDown,
+            "space" => Key::Char(' '),
+            "eof" => Key::Eof,
+            name => match (name.strip_prefix("ctrl"), name.strip_prefix('f').and_then(|number| number.parse().ok())) {
+                (Some(letter), _) if letter.len() == 1 && letter.as_bytes()[0].is_ascii_lowercase() => {
+                    Key::Ctrl(letter.as_bytes()[0] as char)
+                },
+                (None, Some(number @ 1..=12)) => Key::F(number),
+                _ => return Err(ParseKeyError { spec: spec.to_string() }),
+            },
+        };
+
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_single_characters_keeping_case() {
+        assert_eq!("q".parse(), Ok(Key::Char('q')));
+        assert_eq!("Q".parse(), Ok(Key::Char('Q')));
+        assert_eq!("-".parse(), Ok(Key::Char('-')));
+        assert_eq!("é".parse(), Ok(Key::Char('é')));
+    }
+
+    #[test]
+    fn parses_key_name_aliases() {
+        let aliases = [
+            (["Up", "ArrowUp", "arrow_up"], Key::ArrowUp),
+            (["Enter", "Return", "RETURN"], Key::Enter),
+            (["Esc", "Escape", "escape"], Key::Escape),
+            (["Del", "Delete", "DELETE"], Key::Del),
+            (["PageUp", "PgUp", "page-up"], Key::PageUp),
+            (["PageDown", "PgDn", "page_down"], Key::PageDown),
+            (["BackTab", "Shift-Tab", "shift_tab"], Key::BackTab),
+        ];
+
+        for (specs, key) in aliases {
+            for spec in specs {
+                assert_eq!(spec.parse(), Ok(key), "{}", spec);
+            }
+        }
+
+        assert_eq!("Space".parse(), Ok(Key::Char(' ')));
+        assert_eq!("EOF".parse(), Ok(Key::Eof));
+    }
+
+    #[test]
+    fn parses_control_and_function_keys() {
+        assert_eq!("Ctrl-W".parse(), Ok(Key::Ctrl('w')));
+        assert_eq!("ctrl_a".parse(), Ok(Key::Ctrl('a')));
+        assert_eq!("F1".parse(), Ok(Key::F(1)));
+        assert_eq!("f12".parse(), Ok(Key::F(12)));
+    }
+
+    #[test]
+    fn rejects_unknown_specs() {
+        for spec in ["", "Ctrl-", "Ctrl-1", "Ctrl-WW", "F0", "F13", "Fn", "Hyper"] {
+            assert_eq!(spec.parse::<Key>(), Err(ParseKeyError { spec: spec.to_string() }), "{}", spec);
+        }
+    }
+}
@@ -46,22 +46,54 @@
 //!   Only Linux is truly supported as of the current moment.
 //! - Care should be taken when using asynchronous input, as it may introduce additional complexity
 //!   and overhead.
+//! - When `TERM` is `dumb` or unset, input stays line-buffered and echoed, and cursor or clearing
+//!   escape sequences are not written.
+//! - With the `zeroize` feature, the buffers hidden input is read through are wiped, and
+//!   `StdinLock::read_password` gives a `SecretString` that is wiped once dropped.
+//! - The asynchronous reads come with the default `async` feature. Building with
+//!   `default-features = false` leaves only blocking input and output, with `libc` as the sole dependency.
 
+use std::fs::File;
+use std::sync::Arc;
+use std::io::Result as IoResult;
+use std::time::Duration;
+
+use crate::builder::{is_closed, ErrorPolicy, TerminalBuilder};
+use crate::events::{Event, MouseMode};
 use crate::keys::Key;
-use crate::streams::{StderrLock, StdinLock, StdoutLock, Streams};
+use crate::streams::{detached, LongLine, OwnedStdin, ReadOptions, StderrLock, StdinLock, StdoutLock, Streams, WindowInfo};
+use crate::widgets::{Completer, Confirm, History};
 
 pub mod streams;
 pub mod keys;
+pub mod events;
+pub mod builder;
+pub mod prelude;
+pub mod diagnostics;
+pub mod widgets;
+pub mod width;
+pub mod keymap;
+pub mod style;
+pub mod sequence;
+pub mod proxy;
+pub mod pty;
+pub mod event_loop;
+pub mod frames;
+#[cfg(feature = "zeroize")]
+pub mod secret;
 
 const FAILED_WRITE: &str = "failed to write to stream";
 const FAILED_READ: &str = "failed to read from stream";
-const FAILED_CONNECT: &str = "failed to connect with attended input stream";
 
 /// A struct representing the terminal interface for input and output operations.
 /// Only simple operations are enabled; lock the respective stream for more methods.
-#[derive(Debug, Default)]
+///
+/// Cloning is cheap, as clones share the same streams; pass clones around rather than references.
+/// Terminals made with [`Terminal::new`] also share their streams, so the terminal is only detected once.
+#[derive(Debug, Clone)]
 pub struct Terminal {
-    streams: Streams,
+    streams: Arc<Streams>,
+    errors: ErrorPolicy,
 }
 
 /// An enum representing the target output stream, which can be either standard output or standard error.
@@ -70,24 +102,120 @@ pub enum Target {
     Stderr,
 }
 
+impl Default for Terminal {
+    fn default() -> Self {
+        Terminal::new()
+    }
+}
+
 impl Terminal {
     /// Creates a new instance of the `Terminal` struct, initializing the underlying input and output streams.
+    /// The streams are set up on the first call and shared by every later one.
     pub fn new() -> Self {
-        let streams = Streams::new();
-        Terminal { streams }
+        let streams = Streams::shared();
+        Terminal { streams, errors: ErrorPolicy::default() }
+    }
+
+    /// Creates a new instance of the `Terminal` struct reading input from an opened terminal device,
+    /// such as `/dev/tty`, and writing to the standard streams. See [`TerminalBuilder::build_input`].
+    pub fn from_tty(input: File) -> IoResult<Self> {
+        TerminalBuilder::new().build_input(input)
+    }
+
+    /// Creates a new instance of the `Terminal` struct over an opened serial device, such as `/dev/ttyUSB0`.
+    /// The line speed is set to `baud`; see [`TerminalBuilder::build_serial`].
+    pub fn from_serial(device: File, baud: u32) -> IoResult<Self> {
+        TerminalBuilder::new().build_serial(device, baud)
+    }
+
+    /// Creates a new instance of the `Terminal` struct over the master side of a pseudo-terminal.
+    /// See [`TerminalBuilder::build_pty`].
+    pub fn from_pty(input: File, output: File) -> Self {
+        TerminalBuilder::new().build_pty(input, output)
+    }
+
+    /// Creates a [`TerminalBuilder`] for configuring the terminal before it is created.
+    pub fn builder() -> TerminalBuilder {
+        TerminalBuilder::new()
     }
 
     /// Locks the standard input stream, allowing for synchronous read operations.
     /// Returns [`Some(StdinLock)`] if successful, or [`None`] if locking the stream fails.
+    /// Waits for any other lock on the input, including ones taken with [`Terminal::try_lock_stdin`], to be released.
     pub fn lock_stdin(&self) -> Option<StdinLock> {
         self.streams.lock_stdin()
     }
 
+    /// Locks the input stream without waiting, bypassing the standard library's lock on standard input,
+    /// so another thread holding it, such as one blocked reading a line, cannot cause a deadlock.
+    /// Returns [`None`] if the input is not attached to a terminal, or if another lock on it is held,
+    /// whichever method took it, so no two locks read the input at once.
+    pub fn try_lock_stdin(&self) -> Option<StdinLock> {
+        self.streams.try_lock_stdin()
+    }
+
+    /// Locks the input stream like [`Terminal::try_lock_stdin`], waiting up to `timeout` for
+    /// another lock on it to be released.
+    pub fn lock_stdin_timeout(&self, timeout: Duration) -> Option<StdinLock> {
+        self.streams.lock_stdin_timeout(timeout)
+    }
+
+    /// Creates an input handle owning a duplicate of the input stream's descriptor.
+    /// Unlike [`StdinLock`], the handle can be sent to other threads or asynchronous tasks.
+    /// Fails if the input is not attached to a terminal, or the descriptor cannot be duplicated.
+    pub fn owned_stdin(&self) -> IoResult<OwnedStdin> {
+        self.streams.owned_stdin()
+    }
+
+    /// Registers a callback run with the new dimensions of the terminal, (`rows`, `columns`),
+    /// each time it is resized, for keeping a cached layout up to date without reading events.
+    /// Callbacks run one after another on a background thread, and stay registered until every clone of the
+    /// terminal is dropped, so a callback holding a clone of it keeps it open for the rest of the program.
+    /// Any `SIGWINCH` handler installed beforehand keeps being called.
+    /// Fails if the signal handler or the thread running the callbacks cannot be set up.
+    pub fn on_resize(&self, callback: impl FnMut(u16, u16) + Send + 'static) -> IoResult<()> {
+        self.streams.on_resize(Box::new(callback))
+    }
+
+    /// Delivers resizes of the terminal as [`Event::Resize`] to every reader of its events,
+    /// waking those waiting for input. Resize events stay enabled for as long as the terminal is kept.
+    /// Fails if the signal handler or the thread following resizes cannot be set up, see [`Terminal::on_resize`].
+    pub fn enable_resize_events(&self) -> IoResult<()> {
+        self.streams.enable_resize_events()
+    }
+
+    /// Asks the terminal for the position and dimensions of its window, waiting up to half a second
+    /// for the replies. See [`StdinLock::window_info`].
+    /// Fails if the input is not attached to a terminal, or if reading or writing fails.
+    pub fn window_info(&self) -> IoResult<WindowInfo> {
+        const TIMEOUT: Duration = Duration::from_millis(500);
+
+        let mut stdin = self.streams.lock_stdin().ok_or_else(detached)?;
+        stdin.window_info(&mut self.streams.lock_stdout(), TIMEOUT)
+    }
+
+    /// Asks the terminal where the cursor is, (`row`, `column`) starting from 1, waiting up to half a second
+    /// for the reply. See [`StdoutLock::cursor_position`].
+    /// Fails if the input is not attached to a terminal, if no position is reported, or if reading or writing fails.
+    pub fn cursor_position(&self) -> IoResult<(usize, usize)> {
+        const TIMEOUT: Duration = Duration::from_millis(500);
+
+        let mut stdin = self.streams.lock_stdin().ok_or_else(detached)?;
+        self.streams.lock_stdout().cursor_position(&mut stdin, TIMEOUT)
+    }
+
     /// Locks the standard output stream, allowing for synchronous write operations.
     pub fn lock_stdout(&self) -> StdoutLock {
         self.streams.lock_stdout()
     }
 
+    /// Locks the stream prompts, widgets and other interactive output are drawn on, which is the
+    /// standard output stream unless another is chosen with [`TerminalBuilder::prompt_output`].
+    /// The lock offers the same methods as the standard output lock, such as moving the cursor.
+    pub fn lock_prompt(&self) -> StdoutLock {
+        self.streams.lock_prompt()
+    }
+
     /// Locks the standard error stream, allowing for synchronous write operations.
     pub fn lock_stderr(&self) -> StderrLock {
         self.streams.lock_stderr()
@@ -96,75 +224,271 @@ impl Terminal {
     /// Prints a string to the specified target stream.
     /// If the target is [`Target::Stderr`], the string is printed to the standard error stream.
     /// If the target is [`Target::Stdout`], the string is printed to the standard output stream.
-    /// Panics if an error occurs during writing.
+    /// Panics if an error occurs during writing, unless another [`ErrorPolicy`] is chosen.
     pub fn print(&self, target: Target, str: &str) {
+        self.handle(self.try_print(target, str), (), FAILED_WRITE)
+    }
+
+    /// Prints a string to the specified target stream, as [`Terminal::print`] does, giving back any error.
+    pub fn try_print(&self, target: Target, str: &str) -> IoResult<()> {
         match target {
             Target::Stderr => self.streams.lock_stderr().print(str),
             Target::Stdout => self.streams.lock_stdout().print(str),
         }
-        .unwrap_or_else(|error| panic!("{}: {}", error, FAILED_WRITE))
     }
 
     /// Prints a string followed by a newline to the specified target stream.
     /// If the target is [`Target::Stderr`], the string is printed to the standard error stream.
     /// If the target is [`Target::Stdout`], the string is printed to the standard output stream.
-    /// Panics if an error occurs during writing.
+    /// Panics if an error occurs during writing, unless another [`ErrorPolicy`] is chosen.
     pub fn println(&self, target: Target, str: &str) {
+        self.handle(self.try_println(target, str), (), FAILED_WRITE)
+    }
+
+    /// Prints a string followed by a newline to the specified target stream, as [`Terminal::println`] does,
+    /// giving back any error.
+    pub fn try_println(&self, target: Target, str: &str) -> IoResult<()> {
         match target {
             Target::Stderr => self.streams.lock_stderr().println(str),
             Target::Stdout => self.streams.lock_stdout().println(str),
         }
-        .unwrap_or_else(|error| panic!("{}: {}", error, FAILED_WRITE))
     }
 
     /// Clears the screen by sending an escape sequence.
     /// This function sends the escape sequence to clear the entire screen.
     /// It moves the cursor to the top-left corner of the terminal.
-    /// Panics if an error occurs during writing.
+    /// Panics if an error occurs during writing, unless another [`ErrorPolicy`] is chosen.
     pub fn clear(&self) {
-        self.streams
-            .lock_stdout()
-            .clear()
-            .unwrap_or_else(|error| panic!("{}: {}", error, FAILED_WRITE))
+        self.handle(self.try_clear(), (), FAILED_WRITE)
+    }
+
+    /// Clears the screen, as [`Terminal::clear`] does, giving back any error.
+    pub fn try_clear(&self) -> IoResult<()> {
+        self.streams.lock_stdout().clear()
     }
 
     /// Hides the cursor in the terminal.
     /// This function sends the escape sequence to hide the cursor in the terminal.
-    /// Panics if an error occurs during writing.
+    /// Panics if an error occurs during writing, unless another [`ErrorPolicy`] is chosen.
     pub fn hide(&self) {
-        self.streams
-            .lock_stdout()
-            .hide()
-            .unwrap_or_else(|error| panic!("{}: {}", error, FAILED_WRITE))
+        self.handle(self.try_hide(), (), FAILED_WRITE)
+    }
+
+    /// Hides the cursor in the terminal, giving back any error.
+    pub fn try_hide(&self) -> IoResult<()> {
+        self.streams.lock_stdout().hide()
     }
 
     /// Shows the cursor in the terminal.
     /// This function sends the escape sequence to show the cursor in the terminal.
-    /// Panics if an error occurs during writing.
+    /// Panics if an error occurs during writing, unless another [`ErrorPolicy`] is chosen.
     pub fn show(&self) {
-        self.streams
-            .lock_stdout()
-            .show()
-            .unwrap_or_else(|error| panic!("{}: {}", error, FAILED_WRITE))
+        self.handle(self.try_show(), (), FAILED_WRITE)
+    }
+
+    /// Shows the cursor in the terminal, giving back any error.
+    pub fn try_show(&self) -> IoResult<()> {
+        self.streams.lock_stdout().show()
+    }
+
+    /// Enables mouse capture, after which mouse reports are read as [`Event::Mouse`] through [`Terminal::read_event`].
+    /// See [`StdoutLock::enable_mouse_capture`].
+    /// Panics if an error occurs during writing, unless another [`ErrorPolicy`] is chosen.
+    pub fn enable_mouse_capture(&self, mode: MouseMode) {
+        self.handle(self.try_enable_mouse_capture(mode), (), FAILED_WRITE)
+    }
+
+    /// Enables mouse capture, as [`Terminal::enable_mouse_capture`] does, giving back any error.
+    pub fn try_enable_mouse_capture(&self, mode: MouseMode) -> IoResult<()> {
+        self.streams.lock_stdout().enable_mouse_capture(mode)
+    }
+
+    /// Disables mouse capture, including pixel positions.
+    /// Panics if an error occurs during writing, unless another [`ErrorPolicy`] is chosen.
+    pub fn disable_mouse_capture(&self) {
+        self.handle(self.try_disable_mouse_capture(), (), FAILED_WRITE)
+    }
+
+    /// Disables mouse capture, including pixel positions, giving back any error.
+    pub fn try_disable_mouse_capture(&self) -> IoResult<()> {
+        self.streams.lock_stdout().disable_mouse_capture()
+    }
+
+    /// Undoes every mode this terminal enabled through the output streams and did not disable since,
+    /// latest first: the alternate screen, a hidden cursor, mouse capture, focus events and bracketed paste.
+    /// Modes another terminal writing to the same stream also enabled are left for it to undo.
+    /// Every mode is undone if the program panics, or is ended by a signal once [`Terminal::restore_on_signals`]
+    /// is called, along with restoring the input settings changed by active guards, such as [`StdinLock::raw_mode`].
+    /// This terminal's modes are also undone once the last clone of a terminal made by its builder is dropped.
+    /// Panics if an error occurs during writing, unless another [`ErrorPolicy`] is chosen.
+    pub fn restore_all(&self) {
+        self.handle(self.try_restore_all(), (), FAILED_WRITE)
+    }
+
+    /// Undoes every mode enabled, as [`Terminal::restore_all`] does, giving back any error.
+    pub fn try_restore_all(&self) -> IoResult<()> {
+        self.streams.restore_all()
+    }
+
+    /// Has every terminal put back as it was, as on a panic, if the program is ended by `SIGHUP`, `SIGINT`,
+    /// `SIGQUIT` or `SIGTERM`. The signal is then delivered again, ending the program as it would have.
+    /// Signals the program handles or ignores itself are left alone. Calling this again changes nothing.
+    /// Fails if the signal handler or the thread restoring the terminal cannot be set up.
+    pub fn restore_on_signals(&self) -> IoResult<()> {
+        self.streams.restore_on_signals()
+    }
+
+    /// Reads a single event, such as a key or a mouse report, from the standard input stream.
+    /// Panics if the input is not attached to a terminal or an error occurs during reading,
+    /// unless another [`ErrorPolicy`] is chosen.
+    pub fn read_event(&self) -> Event {
+        self.handle(self.try_read_event(), Key::Unknown.into(), FAILED_READ)
+    }
+
+    /// Reads a single event from the standard input stream, as [`Terminal::read_event`] does, giving back any error.
+    /// Fails with [`ErrorKind::NotConnected`] if the input is not attached to a terminal.
+    ///
+    /// [`ErrorKind::NotConnected`]: std::io::ErrorKind::NotConnected
+    pub fn try_read_event(&self) -> IoResult<Event> {
+        self.streams.lock_stdin().ok_or_else(detached)?.read_event()
     }
 
     /// Reads a single key from the standard input stream.
-    /// Panics if an error occurs during reading.
+    /// Panics if the input is not attached to a terminal or an error occurs during reading,
+    /// unless another [`ErrorPolicy`] is chosen.
     pub fn read_key(&self) -> Key {
-        self.streams
-            .lock_stdin()
-            .expect(FAILED_CONNECT)
-            .read_key()
-            .unwrap_or_else(|error| panic!("{}: {}", error, FAILED_READ))
+        self.handle(self.try_read_key(), Key::Unknown, FAILED_READ)
+    }
+
+    /// Reads a single key from the standard input stream, giving back any error, such as the terminal
+    /// being closed. The end of input is read as [`Key::Eof`] rather than failing. Fails with [`ErrorKind::NotConnected`] if the input is not attached to a terminal.
+    ///
+    /// [`ErrorKind::NotConnected`]: std::io::ErrorKind::NotConnected
+    pub fn try_read_key(&self) -> IoResult<Key> {
+        self.streams.lock_stdin().ok_or_else(detached)?.read_key()
+    }
+
+    /// Reads a single key from the standard input stream, echoing it on the prompt stream if it is printable.
+    /// See [`StdinLock::read_key_echoed`].
+    /// Panics if the input is not attached to a terminal or an error occurs during reading or writing,
+    /// unless another [`ErrorPolicy`] is chosen.
+    pub fn read_key_echoed(&self) -> Key {
+        self.handle(self.try_read_key_echoed(), Key::Unknown, FAILED_READ)
+    }
+
+    /// Reads a single key from the standard input stream, echoing it as [`Terminal::read_key_echoed`] does,
+    /// giving back any error.
+    pub fn try_read_key_echoed(&self) -> IoResult<Key> {
+        self.streams.lock_stdin().ok_or_else(detached)?.read_key_echoed(&mut self.streams.lock_prompt())
+    }
+
+    /// Reads a single key from the standard input stream with the terminal set up as `options` choose,
+    /// such as with the terminal's own echo on, which shows each key as typed. See [`StdinLock::read_key_with`].
+    /// Panics if the input is not attached to a terminal or an error occurs during reading,
+    /// unless another [`ErrorPolicy`] is chosen.
+    pub fn read_key_with(&self, options: ReadOptions) -> Key {
+        self.handle(self.try_read_key_with(options), Key::Unknown, FAILED_READ)
+    }
+
+    /// Reads a single key with the terminal set up as `options` choose, as [`Terminal::read_key_with`] does,
+    /// giving back any error.
+    pub fn try_read_key_with(&self, options: ReadOptions) -> IoResult<Key> {
+        self.streams.lock_stdin().ok_or_else(detached)?.read_key_with(options)
     }
 
     /// Reads a line of text from the standard input stream.
-    /// Panics if an error occurs during reading.
+    /// Panics if the input is not attached to a terminal or an error occurs during reading,
+    /// unless another [`ErrorPolicy`] is chosen.
     pub fn read_string(&self) -> String {
-        self.streams
-            .lock_stdin()
-            .expect(FAILED_CONNECT)
-            .read_string()
-            .unwrap_or_else(|error| panic!("{}: {}", error, FAILED_READ))
+        self.handle(self.try_read_string(), String::new(), FAILED_READ)
+    }
+
+    /// Reads a line of text from the standard input stream, giving back any error.
+    pub fn try_read_string(&self) -> IoResult<String> {
+        self.streams.lock_stdin().ok_or_else(detached)?.read_string()
+    }
+
+    /// Reads a line of text from the standard input stream without the kernel's limit on line length,
+    /// keeping up to `limit` bytes of it. See [`StdinLock::read_long_line`].
+    /// Panics if the input is not attached to a terminal or an error occurs during reading,
+    /// unless another [`ErrorPolicy`] is chosen.
+    pub fn read_long_line(&self, limit: Option<usize>) -> LongLine {
+        self.handle(self.try_read_long_line(limit), LongLine::Complete(String::new()), FAILED_READ)
+    }
+
+    /// Reads a line of text without the kernel's limit on line length, as [`Terminal::read_long_line`] does,
+    /// giving back any error.
+    pub fn try_read_long_line(&self, limit: Option<usize>) -> IoResult<LongLine> {
+        self.streams.lock_stdin().ok_or_else(detached)?.read_long_line(limit)
+    }
+
+    /// Reads a line of text from the standard input stream, showing `mask` on the prompt stream in place
+    /// of each character typed. See [`StdinLock::read_string_masked`].
+    /// Panics if the input is not attached to a terminal or an error occurs during reading or writing,
+    /// unless another [`ErrorPolicy`] is chosen.
+    pub fn read_string_masked(&self, mask: char) -> String {
+        self.handle(self.try_read_string_masked(mask), String::new(), FAILED_READ)
+    }
+
+    /// Reads a line of text with each character masked, as [`Terminal::read_string_masked`] does, giving back any error.
+    pub fn try_read_string_masked(&self, mask: char) -> IoResult<String> {
+        self.streams.lock_stdin().ok_or_else(detached)?.read_string_masked(&mut self.streams.lock_prompt(), mask)
+    }
+
+    /// Reads a line of text from the standard input stream, completing it with `completer` when Tab is pressed.
+    /// See [`StdinLock::read_string_with_completion`].
+    /// Panics if the input is not attached to a terminal or an error occurs during reading or writing,
+    /// unless another [`ErrorPolicy`] is chosen.
+    pub fn read_string_with_completion(&self, completer: &impl Completer) -> String {
+        self.handle(self.try_read_string_with_completion(completer), String::new(), FAILED_READ)
+    }
+
+    /// Reads a line of text completed with `completer`, as [`Terminal::read_string_with_completion`] does,
+    /// giving back any error.
+    pub fn try_read_string_with_completion(&self, completer: &impl Completer) -> IoResult<String> {
+        self.streams.lock_stdin().ok_or_else(detached)?.read_string_with_completion(&mut self.streams.lock_prompt(), completer)
+    }
+
+    /// Reads a line of text from the standard input stream, with the up and down arrows recalling the entries
+    /// of `history`, and adds it to `history`. See [`StdinLock::read_string_with_history`].
+    /// Panics if the input is not attached to a terminal or an error occurs during reading or writing,
+    /// unless another [`ErrorPolicy`] is chosen.
+    pub fn read_string_with_history(&self, history: &mut History) -> String {
+        self.handle(self.try_read_string_with_history(history), String::new(), FAILED_READ)
+    }
+
+    /// Reads a line of text recalling the entries of `history`, as [`Terminal::read_string_with_history`] does,
+    /// giving back any error.
+    pub fn try_read_string_with_history(&self, history: &mut History) -> IoResult<String> {
+        self.streams.lock_stdin().ok_or_else(detached)?.read_string_with_history(&mut self.streams.lock_prompt(), history)
+    }
+
+    /// Asks a yes or no question, answered with `y` or `n`.
+    /// Use [`Confirm`] for a default answer or other keys.
+    /// Panics if an error occurs during reading or writing, unless another [`ErrorPolicy`] is chosen.
+    pub fn confirm(&self, prompt: &str) -> bool {
+        self.handle(self.try_confirm(prompt), false, FAILED_READ)
+    }
+
+    /// Asks a yes or no question, as [`Terminal::confirm`] does, giving back any error.
+    pub fn try_confirm(&self, prompt: &str) -> IoResult<bool> {
+        Confirm::new(prompt).ask(self)
+    }
+
+    // Deals with the error of a failed operation as the error policy dictates,
+    // giving `fallback` in place of the value if the error is not raised as a panic.
+    fn handle<T>(&self, result: IoResult<T>, fallback: T, failed: &str) -> T {
+        let error = match result {
+            Ok(value) => return value,
+            Err(error) => error,
+        };
+
+        match &self.errors {
+            ErrorPolicy::IgnoreClosed if is_closed(&error) => {},
+            ErrorPolicy::Handler(handler) => handler(&error),
+            _ => panic!("{}: {}", error, failed),
+        }
+
+        fallback
     }
 }
\ No newline at end of file
@@ -0,0 +1,12 @@
+//! A convenience module re-exporting the most commonly used items.
+//!
+//! ```rust,ignore
+//! use in_keys::prelude::*;
+//! ```
+
+pub use crate::{Target, Terminal};
+pub use crate::builder::TerminalBuilder;
+pub use crate::events::Event;
+pub use crate::keys::Key;
+pub use crate::streams::TerminalOutput;
+pub use crate::widgets::Confirm;
@@ -0,0 +1,71 @@
+//! Relaying a child program's terminal through the real one, while observing what is typed.
+//!
+//! This is the core of wrappers such as `script`, detachers and keystroke overlays: the child runs on
+//! a pseudo-terminal, its output is copied to the real terminal, and every key typed is decoded
+//! before being passed on, so it can be logged, dropped or replaced.
+//!
+//! ```rust,ignore
+//! use in_keys::Terminal;
+//! use in_keys::events::Event;
+//! use in_keys::keys::Key;
+//! use in_keys::proxy::{Intercept, Proxy};
+//!
+//! // `master` is the master side of the pseudo-terminal the child runs on
+//! let child = Terminal::from_pty(master.try_clone()?, master);
+//! let proxy = Proxy::new(Terminal::new(), child);
+//!
+//! proxy.run(|event, bytes| match event {
+//!     Event::Key(event) if event.key == Key::Insert => Intercept::Stop,
+//!     _ => {
+//!         log.write_all(bytes).ok();
+//!         Intercept::Forward
+//!     },
+//! })?;
+//! ```
+
+use std::io::Result as IoResult;
+
+use crate::events::Event;
+use crate::streams::detached;
+use crate::Terminal;
+
+/// What to do with an event read by a [`Proxy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Intercept {
+    /// Pass the bytes of the event on to the child.
+    Forward,
+    /// Pass other bytes on to the child in place of the ones of the event.
+    Replace(Vec<u8>),
+    /// Keep the event from the child.
+    Drop,
+    /// Stop relaying, keeping the event from the child.
+    Stop,
+}
+
+/// A relay between a terminal and the pseudo-terminal of a child program.
+#[derive(Debug, Clone)]
+pub struct Proxy {
+    terminal: Terminal,
+    child: Terminal,
+}
+
+impl Proxy {
+    /// Creates a proxy relaying between `terminal`, usually the real one, and `child`, a terminal
+    /// over the master side of the child's pseudo-terminal (see [`Terminal::from_pty`]).
+    pub fn new(terminal: Terminal, child: Terminal) -> Self {
+        Proxy { terminal, child }
+    }
+
+    /// Relays until the child closes its side of the pseudo-terminal, such as by exiting, or until
+    /// `observe` gives [`Intercept::Stop`]. Whatever the child writes is copied to the terminal, and
+    /// each event read from the terminal is given to `observe` along with the bytes it was decoded from.
+    /// Input is read raw in the meantime, so control characters such as Ctrl+C reach the child
+    /// rather than raising signals.
+    /// Fails if either side is not attached to a terminal, or if reading or writing fails.
+    pub fn run(&self, observe: impl FnMut(&Event, &[u8]) -> Intercept) -> IoResult<()> {
+        let mut input = self.terminal.lock_stdin().ok_or_else(detached)?;
+        let mut child = self.child.lock_stdin().ok_or_else(detached)?;
+
+        input.relay(&mut child, &mut self.terminal.lock_stdout(), &mut self.child.lock_stdout(), observe)
+    }
+}
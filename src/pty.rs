@@ -0,0 +1,112 @@
+//! Running programs on pseudo-terminals of their own, for building terminal multiplexers and runners.
+//!
+//! The master side of the pseudo-terminal is given as a [`Terminal`]: reading from it gives what the
+//! program writes, and writing to it types into the program.
+//!
+//! ```rust,ignore
+//! use std::process::Command;
+//! use in_keys::Terminal;
+//! use in_keys::proxy::{Intercept, Proxy};
+//! use in_keys::pty::Pty;
+//!
+//! let terminal = Terminal::new();
+//! let mut pty = Pty::spawn(Command::new("bash"), 24, 80)?;
+//! pty.forward_resizes(&terminal)?;
+//!
+//! let proxy = Proxy::new(terminal, pty.terminal().clone());
+//!
+//! proxy.run(|_, _| Intercept::Forward)?;
+//! pty.child().wait()?;
+//! ```
+
+use std::io::{Error as IoError, Result as IoResult};
+use std::os::fd::AsRawFd;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command};
+use std::sync::Arc;
+
+use crate::streams::{open_pty, set_size};
+use crate::Terminal;
+
+/// Sets the dimensions, (`rows`, `columns`), of the terminal behind a descriptor, such as the master
+/// side of a pseudo-terminal opened elsewhere, which notifies the programs running on it with `SIGWINCH`.
+/// Fails if the descriptor is not a terminal or the dimensions exceed what a terminal can hold.
+pub fn set_size_on(fd: &impl AsRawFd, rows: usize, columns: usize) -> IoResult<()> {
+    set_size(fd, rows, columns)
+}
+
+/// A child process running on a pseudo-terminal of its own.
+#[derive(Debug)]
+pub struct Pty {
+    terminal: Terminal,
+    child: Child,
+}
+
+impl Pty {
+    /// Spawns `command` on a new pseudo-terminal of the given dimensions, (`rows`, `columns`).
+    /// The program's standard streams are connected to the pseudo-terminal, which becomes its
+    /// controlling terminal in a session of its own.
+    /// Fails if the pseudo-terminal cannot be opened or the program cannot be spawned.
+    pub fn spawn(mut command: Command, rows: usize, columns: usize) -> IoResult<Self> {
+        let (master, slave) = open_pty()?;
+        let terminal = Terminal::from_pty(master.try_clone()?, master);
+        terminal.lock_stdout().set_size(rows, columns)?;
+
+        command.stdin(slave.try_clone()?).stdout(slave.try_clone()?).stderr(slave);
+
+        // Safety: Only async-signal-safe calls are made between forking and executing.
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() < 0 { return Err(IoError::last_os_error()); }
+
+                #[allow(clippy::useless_conversion)]
+                match libc::ioctl(libc::STDIN_FILENO, libc::TIOCSCTTY.into(), 0) {
+                    0 => Ok(()),
+                    _ => Err(IoError::last_os_error()),
+                }
+            });
+        }
+
+        let child = command.spawn()?;
+        // The command's handles to the slave side are closed here, so reading the
+        // master side fails once the program and its children are done with it
+        drop(command);
+
+        Ok(Pty { terminal, child })
+    }
+
+    /// Gives the terminal over the master side of the pseudo-terminal.
+    /// Its input is what the program writes, and its output is typed into the program.
+    pub fn terminal(&self) -> &Terminal {
+        &self.terminal
+    }
+
+    /// Gives the spawned program, for waiting on or killing it.
+    pub fn child(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// Changes the dimensions of the pseudo-terminal, (`rows`, `columns`), notifying the program with `SIGWINCH`.
+    pub fn resize(&self, rows: usize, columns: usize) -> IoResult<()> {
+        self.terminal.lock_stdout().set_size(rows, columns)
+    }
+
+    /// Keeps the pseudo-terminal the size of `terminal`, resizing it now and whenever `terminal` is
+    /// resized, so the program reflows as the terminal it is shown on does.
+    /// See [`Terminal::on_resize`] for how resizes are followed.
+    pub fn forward_resizes(&self, terminal: &Terminal) -> IoResult<()> {
+        if let Some((rows, columns)) = terminal.lock_stdout().size() {
+            self.resize(rows, columns)?;
+        }
+
+        // The callback stays registered, so it only holds on to the pseudo-terminal weakly,
+        // leaving it to be closed once the `Pty` and its clones of the terminal are dropped
+        let child = Arc::downgrade(&self.terminal.streams);
+        terminal.on_resize(move |rows, columns| {
+            if let Some(child) = child.upgrade() {
+                // Errors have nowhere to be reported from the background thread
+                let _ = child.lock_stdout().set_size(rows.into(), columns.into());
+            }
+        })
+    }
+}
@@ -0,0 +1,35 @@
+//! Holding sensitive text, such as passwords, so it is wiped from memory once dropped.
+//! Only available with the `zeroize` feature.
+
+use std::fmt;
+
+use zeroize::Zeroize;
+
+/// A string, such as a password, that is overwritten with zeroes when dropped.
+/// It is kept out of [`Debug`] output, and only reachable through [`SecretString::expose_secret`].
+pub struct SecretString(String);
+
+impl SecretString {
+    /// Gives the text, which should not be copied into longer-lived strings.
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(string: String) -> Self {
+        SecretString(string)
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString(..)")
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
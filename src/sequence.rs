@@ -0,0 +1,250 @@
+//! Parsing escape sequences, for handling the ones this crate does not model yet.
+//!
+//! ```rust,ignore
+//! use in_keys::sequence::{parse_sequence, ControlSequence};
+//!
+//! // A focus report from a terminal with focus events enabled
+//! match parse_sequence(b"\x1b[I") {
+//!     ControlSequence::Csi { last: b'I', .. } => println!("focus gained"),
+//!     ControlSequence::Csi { last: b'O', .. } => println!("focus lost"),
+//!     _ => {},
+//! }
+//! ```
+
+/// An escape sequence, as parsed by [`parse_sequence`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlSequence {
+    /// A control sequence (`ESC [` or `CSI`), such as `ESC [ 1 ; 5 C`.
+    Csi {
+        /// The private marker before the parameters, one of `<`, `=`, `>` and `?`.
+        private: Option<u8>,
+        /// The numeric parameters, with empty ones being 0. Sub-parameters separated by `:`
+        /// are given as parameters of their own.
+        parameters: Vec<u16>,
+        /// The intermediate bytes, from space to `/`.
+        intermediates: Vec<u8>,
+        /// The final byte, from `@` to `~`.
+        last: u8,
+    },
+    /// An operating system command (`ESC ]` or `OSC`), such as a clipboard reply, with its
+    /// payload between the introducer and the BEL or ST ending it.
+    Osc(Vec<u8>),
+    /// A device control string (`ESC P` or `DCS`), such as a terminfo reply.
+    Dcs {
+        /// The private marker before the parameters, one of `<`, `=`, `>` and `?`.
+        private: Option<u8>,
+        /// The numeric parameters, with empty ones being 0.
+        parameters: Vec<u16>,
+        /// The intermediate bytes, from space to `/`.
+        intermediates: Vec<u8>,
+        /// The final byte, from `@` to `~`.
+        last: u8,
+        /// The data after the final byte, up to the ST ending it.
+        data: Vec<u8>,
+    },
+    /// Any other escape sequence, such as `ESC 7` (saving the cursor).
+    Escape {
+        /// The intermediate bytes, from space to `/`.
+        intermediates: Vec<u8>,
+        /// The final byte, from `0` to `~`.
+        last: u8,
+    },
+    /// The bytes start a sequence but end before it does.
+    Incomplete,
+    /// The bytes do not start with a valid sequence.
+    Invalid,
+}
+
+// The parts of a control sequence or device control string before its data.
+struct Header {
+    private: Option<u8>,
+    parameters: Vec<u16>,
+    intermediates: Vec<u8>,
+    last: u8,
+}
+
+/// Parses the escape sequence at the start of `bytes`, introduced either by ESC or by its
+/// 8-bit C1 counterpart. Bytes after the end of the sequence are ignored.
+pub fn parse_sequence(bytes: &[u8]) -> ControlSequence {
+    match bytes {
+        [b'\x1b', b'[', rest @ ..] | [0x9b, rest @ ..] => match parse_header(rest) {
+            Ok((Header { private, parameters, intermediates, last }, _)) => {
+                ControlSequence::Csi { private, parameters, intermediates, last }
+            },
+            Err(sequence) => sequence,
+        },
+        [b'\x1b', b']', rest @ ..] | [0x9d, rest @ ..] => match terminated(rest, true) {
+            Some(payload) => ControlSequence::Osc(payload.to_vec()),
+            None => ControlSequence::Incomplete,
+        },
+        [b'\x1b', b'P', rest @ ..] | [0x90, rest @ ..] => match parse_header(rest) {
+            Ok((Header { private, parameters, intermediates, last }, rest)) => match terminated(rest, false) {
+                Some(data) => ControlSequence::Dcs { private, parameters, intermediates, last, data: data.to_vec() },
+                None => ControlSequence::Incomplete,
+            },
+            Err(sequence) => sequence,
+        },
+        [b'\x1b', rest @ ..] => {
+            let (intermediates, rest) = split_while(rest, |byte| (0x20..=0x2f).contains(&byte));
+            match rest {
+                [] => ControlSequence::Incomplete,
+                [last @ 0x30..=0x7e, ..] => ControlSequence::Escape { intermediates: intermediates.to_vec(), last: *last },
+                _ => ControlSequence::Invalid,
+            }
+        },
+        [] => ControlSequence::Incomplete,
+        _ => ControlSequence::Invalid,
+    }
+}
+
+// Parses the private marker, parameters, intermediates and final byte following the introducer of
+// a control sequence or device control string, giving them along with the bytes after them.
+fn parse_header(bytes: &[u8]) -> Result<(Header, &[u8]), ControlSequence> {
+    let (private, bytes) = match bytes {
+        [marker @ b'<'..=b'?', rest @ ..] => (Some(*marker), rest),
+        _ => (None, bytes),
+    };
+
+    let (parameters, bytes) = split_while(bytes, |byte| (b'0'..=b'?').contains(&byte));
+    let (intermediates, bytes) = split_while(bytes, |byte| (0x20..=0x2f).contains(&byte));
+
+    let (last, rest) = match bytes {
+        [] => return Err(ControlSequence::Incomplete),
+        [last @ 0x40..=0x7e, rest @ ..] => (*last, rest),
+        _ => return Err(ControlSequence::Invalid),
+    };
+
+    // Numbers too large for a parameter are capped rather than rejected
+    let parameters = match parameters.is_empty() {
+        true => Vec::new(),
+        false => parameters
+            .split(|&byte| byte == b';' || byte == b':')
+            .map(|parameter| {
+                parameter
+                    .iter()
+                    .filter(|byte| byte.is_ascii_digit())
+                    .fold(0_u16, |number, digit| number.saturating_mul(10).saturating_add((digit - b'0') as u16))
+            })
+            .collect(),
+    };
+
+    Ok((Header { private, parameters, intermediates: intermediates.to_vec(), last }, rest))
+}
+
+// Gives the bytes before the ST (`ESC \`) ending a string, or before a BEL if `bell` is set,
+// or `None` if the string is not ended yet. The 8-bit ST is not looked for, as it can be part of UTF-8 text.
+fn terminated(bytes: &[u8], bell: bool) -> Option<&[u8]> {
+    let end = bytes.iter().enumerate().position(|(index, &byte)| match byte {
+        b'\x07' => bell,
+        b'\x1b' => bytes.get(index + 1) == Some(&b'\\'),
+        _ => false,
+    })?;
+
+    Some(&bytes[..end])
+}
+
+// Splits off the longest prefix of bytes that satisfy `predicate`.
+fn split_while(bytes: &[u8], predicate: impl Fn(u8) -> bool) -> (&[u8], &[u8]) {
+    let end = bytes.iter().position(|&byte| !predicate(byte)).unwrap_or(bytes.len());
+    bytes.split_at(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A control sequence with no private marker or intermediates.
+    fn csi(parameters: &[u16], last: u8) -> ControlSequence {
+        ControlSequence::Csi { private: None, parameters: parameters.to_vec(), intermediates: Vec::new(), last }
+    }
+
+    #[test]
+    fn parses_control_sequences() {
+        assert_eq!(parse_sequence(b"\x1b[1;5C"), csi(&[1, 5], b'C'));
+        assert_eq!(parse_sequence(b"\x9bA"), csi(&[], b'A'));
+        assert_eq!(parse_sequence(b"\x1b[;5H"), csi(&[0, 5], b'H'));
+        assert_eq!(parse_sequence(b"\x1b[38:2:1:2:3m"), csi(&[38, 2, 1, 2, 3], b'm'));
+        assert_eq!(parse_sequence(b"\x1b[99999A"), csi(&[u16::MAX], b'A'));
+        assert_eq!(parse_sequence(b"\x1b[Aextra"), csi(&[], b'A'));
+        assert_eq!(
+            parse_sequence(b"\x1b[?1049h"),
+            ControlSequence::Csi { private: Some(b'?'), parameters: vec![1049], intermediates: Vec::new(), last: b'h' },
+        );
+        assert_eq!(
+            parse_sequence(b"\x1b[2 q"),
+            ControlSequence::Csi { private: None, parameters: vec![2], intermediates: b" ".to_vec(), last: b'q' },
+        );
+    }
+
+    #[test]
+    fn incomplete_and_invalid_control_sequences() {
+        assert_eq!(parse_sequence(b""), ControlSequence::Incomplete);
+        assert_eq!(parse_sequence(b"\x1b"), ControlSequence::Incomplete);
+        assert_eq!(parse_sequence(b"\x1b["), ControlSequence::Incomplete);
+        assert_eq!(parse_sequence(b"\x1b[1;5"), ControlSequence::Incomplete);
+        assert_eq!(parse_sequence(b"\x1b[?"), ControlSequence::Incomplete);
+        assert_eq!(parse_sequence(b"\x1b[1\x01"), ControlSequence::Invalid);
+        assert_eq!(parse_sequence(b"\x1b[1 5"), ControlSequence::Invalid);
+        assert_eq!(parse_sequence(b"a\x1b[A"), ControlSequence::Invalid);
+    }
+
+    #[test]
+    fn parses_operating_system_commands() {
+        assert_eq!(parse_sequence(b"\x1b]52;c;aGk=\x07"), ControlSequence::Osc(b"52;c;aGk=".to_vec()));
+        assert_eq!(parse_sequence(b"\x1b]0;title\x1b\\"), ControlSequence::Osc(b"0;title".to_vec()));
+        assert_eq!(parse_sequence(b"\x9d0;title\x07"), ControlSequence::Osc(b"0;title".to_vec()));
+        assert_eq!(parse_sequence(b"\x1b]\x07"), ControlSequence::Osc(Vec::new()));
+    }
+
+    #[test]
+    fn incomplete_operating_system_commands() {
+        assert_eq!(parse_sequence(b"\x1b]"), ControlSequence::Incomplete);
+        assert_eq!(parse_sequence(b"\x1b]0;title"), ControlSequence::Incomplete);
+        // An escape byte alone is not yet a string terminator
+        assert_eq!(parse_sequence(b"\x1b]0;title\x1b"), ControlSequence::Incomplete);
+        // The 8-bit ST is left to be part of the text
+        assert_eq!(parse_sequence(b"\x1b]0;title\x9c"), ControlSequence::Incomplete);
+    }
+
+    #[test]
+    fn parses_device_control_strings() {
+        assert_eq!(
+            parse_sequence(b"\x1bP1$r0m\x1b\\"),
+            ControlSequence::Dcs {
+                private: None,
+                parameters: vec![1],
+                intermediates: b"$".to_vec(),
+                last: b'r',
+                data: b"0m".to_vec(),
+            },
+        );
+        assert_eq!(
+            parse_sequence(b"\x90>|xterm\x1b\\"),
+            ControlSequence::Dcs {
+                private: Some(b'>'),
+                parameters: Vec::new(),
+                intermediates: Vec::new(),
+                last: b'|',
+                data: b"xterm".to_vec(),
+            },
+        );
+    }
+
+    #[test]
+    fn incomplete_and_invalid_device_control_strings() {
+        assert_eq!(parse_sequence(b"\x1bP"), ControlSequence::Incomplete);
+        assert_eq!(parse_sequence(b"\x1bP1$"), ControlSequence::Incomplete);
+        assert_eq!(parse_sequence(b"\x1bP1$r0m"), ControlSequence::Incomplete);
+        // Only ST ends a device control string, not BEL
+        assert_eq!(parse_sequence(b"\x1bP1$r0m\x07"), ControlSequence::Incomplete);
+        assert_eq!(parse_sequence(b"\x1bP1\x01"), ControlSequence::Invalid);
+    }
+
+    #[test]
+    fn parses_other_escape_sequences() {
+        assert_eq!(parse_sequence(b"\x1b7"), ControlSequence::Escape { intermediates: Vec::new(), last: b'7' });
+        assert_eq!(parse_sequence(b"\x1b(B"), ControlSequence::Escape { intermediates: b"(".to_vec(), last: b'B' });
+        assert_eq!(parse_sequence(b"\x1b("), ControlSequence::Incomplete);
+        assert_eq!(parse_sequence(b"\x1b\x01"), ControlSequence::Invalid);
+    }
+}
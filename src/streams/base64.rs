@@ -0,0 +1,92 @@
+// The standard base64 alphabet, as used by OSC 52 clipboard sequences.
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Encodes bytes as padded base64.
+pub(super) fn encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0_u32, |group, (index, &byte)| {
+            group | (byte as u32) << (16 - 8 * index)
+        });
+
+        // A chunk of `n` bytes fills `n + 1` characters, the rest being padding
+        for index in 0..4 {
+            match index <= chunk.len() {
+                true => encoded.push(ALPHABET[(group >> (18 - 6 * index) & 0b111111) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+
+    encoded
+}
+
+// Decodes base64, with or without padding. Gives `None` if a character is outside the alphabet.
+pub(super) fn decode(encoded: &[u8]) -> Option<Vec<u8>> {
+    let encoded = encoded.strip_suffix(b"==").or(encoded.strip_suffix(b"=")).unwrap_or(encoded);
+    let mut bytes = Vec::with_capacity(encoded.len() / 4 * 3 + 2);
+
+    for chunk in encoded.chunks(4) {
+        // A lone character cannot hold a whole byte
+        if chunk.len() == 1 { return None; }
+
+        let mut group = 0_u32;
+        for (index, &char) in chunk.iter().enumerate() {
+            let value = ALPHABET.iter().position(|&letter| letter == char)? as u32;
+            group |= value << (18 - 6 * index);
+        }
+
+        // A chunk of `n` characters holds `n - 1` bytes
+        bytes.extend(group.to_be_bytes()[1..chunk.len()].iter());
+    }
+
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The test vectors of RFC 4648, covering each amount of padding.
+    const VECTORS: [(&str, &str); 7] = [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
+
+    #[test]
+    fn encodes_with_padding() {
+        for (plain, encoded) in VECTORS {
+            assert_eq!(encode(plain.as_bytes()), encoded);
+        }
+    }
+
+    #[test]
+    fn decodes_with_and_without_padding() {
+        for (plain, encoded) in VECTORS {
+            assert_eq!(decode(encoded.as_bytes()).as_deref(), Some(plain.as_bytes()));
+            assert_eq!(decode(encoded.trim_end_matches('=').as_bytes()).as_deref(), Some(plain.as_bytes()));
+        }
+    }
+
+    #[test]
+    fn round_trips_every_byte() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        for length in 0..bytes.len() {
+            assert_eq!(decode(encode(&bytes[..length]).as_bytes()).as_deref(), Some(&bytes[..length]));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert_eq!(decode(b"Zm9v!"), None);
+        assert_eq!(decode(b"Zm9vY"), None);
+        assert_eq!(decode(b"Zg==Zg=="), None);
+        assert_eq!(decode(b"Zm9v\n"), None);
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    io::{Error as IoError, Result as IoResult},
+    os::fd::RawFd,
+    panic,
+    sync::{Mutex, Once, OnceLock, PoisonError, TryLockError, atomic::{AtomicI32, Ordering}},
+    thread,
+};
+
+use crate::streams::unix::{restore_settings, with_errno_kept};
+
+// Tells apart the terminals modes are enabled through, by the address of the state they share.
+pub(super) type Owner = usize;
+
+// The sequences undoing the modes enabled on terminals, along with the terminal that enabled each
+// and the output descriptor it was enabled through, in the order they were enabled.
+static ENABLED: Mutex<Vec<(Owner, RawFd, &'static str)>> = Mutex::new(Vec::new());
+// Installs the panic hook, which only happens once.
+static INSTALLED: Once = Once::new();
+// The result of hooking the termination signals, which only happens once.
+static HOOKED: OnceLock<Result<(), i32>> = OnceLock::new();
+// The writing end of the pipe the signal handler hands caught signals to the restoring thread through.
+static PIPE: AtomicI32 = AtomicI32::new(-1);
+// The signals ending the program that the terminal is restored on, if they have no handler of their own.
+const SIGNALS: [libc::c_int; 4] = [libc::SIGHUP, libc::SIGINT, libc::SIGQUIT, libc::SIGTERM];
+
+// Has the terminal put back as it was if the program panics, before the panic is reported, so the
+// report is readable and the terminal is left usable even if the panic aborts.
+pub(super) fn install() {
+    INSTALLED.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore_everything();
+            previous(info);
+        }));
+    });
+}
+
+// Records that a mode undone by `undo` is enabled by `owner` on the terminal written to through `fd`.
+pub(super) fn enabled(owner: Owner, fd: RawFd, undo: &'static str) {
+    install();
+
+    let mut enabled = ENABLED.lock().unwrap_or_else(PoisonError::into_inner);
+    if !enabled.contains(&(owner, fd, undo)) { enabled.push((owner, fd, undo)); }
+}
+
+// Records that the mode undone by `undo` is no longer enabled by `owner` on the terminal written to through `fd`.
+pub(super) fn disabled(owner: Owner, fd: RawFd, undo: &'static str) {
+    ENABLED.lock().unwrap_or_else(PoisonError::into_inner).retain(|entry| *entry != (owner, fd, undo));
+}
+
+// Takes the sequences undoing the modes `owner` enabled through `fd`, latest first. Modes another
+// terminal enabled on the same descriptor, such as the standard output stream, are still relied
+// upon by it, so the sequences undoing them are left out.
+pub(super) fn take(owner: Owner, fd: RawFd) -> Vec<&'static str> {
+    let mut enabled = ENABLED.lock().unwrap_or_else(PoisonError::into_inner);
+    let (taken, kept): (Vec<_>, Vec<_>) = enabled.drain(..).partition(|&(other, other_fd, _)| (other, other_fd) == (owner, fd));
+    *enabled = kept;
+
+    taken
+        .into_iter()
+        .rev()
+        .filter(|&(_, _, undo)| !enabled.iter().any(|&(_, other_fd, other)| (other_fd, other) == (fd, undo)))
+        .map(|(_, _, undo)| undo)
+        .collect()
+}
+
+// Has the terminal put back as it was, as on a panic, when the program is ended by one of `SIGNALS`,
+// which is then delivered again as it would have been. Signals with a handler of their own are left alone.
+pub(super) fn restore_on_signals() -> IoResult<()> {
+    HOOKED
+        .get_or_init(|| hook_signals().map_err(|error| error.raw_os_error().unwrap_or(libc::EINVAL)))
+        .map_err(IoError::from_raw_os_error)
+}
+
+// Creates the pipe, starts the thread restoring the terminal, and installs the signal handler.
+fn hook_signals() -> IoResult<()> {
+    let mut fds = [0; 2];
+    // Safety: `fds` has room for both ends of the pipe.
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+        return Err(IoError::last_os_error());
+    }
+
+    let [reader, writer] = fds;
+    PIPE.store(writer, Ordering::Relaxed);
+    thread::Builder::new().name("in-keys-signals".into()).spawn(move || restore_on_signal(reader))?;
+
+    // Safety: The handler only makes async-signal-safe calls, and `action` is fully initialized.
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+
+        for signal in SIGNALS {
+            let mut previous: libc::sigaction = std::mem::zeroed();
+            if libc::sigaction(signal, std::ptr::null(), &mut previous) < 0 {
+                return Err(IoError::last_os_error());
+            }
+
+            // Signals the program handles or ignores are up to it
+            if previous.sa_sigaction == libc::SIG_DFL && libc::sigaction(signal, &action, std::ptr::null_mut()) < 0 {
+                return Err(IoError::last_os_error());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Hands a caught signal over to the restoring thread. Only async-signal-safe calls are made.
+extern "C" fn handle(signal: libc::c_int) {
+    // Safety: Writing to a pipe is async-signal-safe.
+    with_errno_kept(|| unsafe { libc::write(PIPE.load(Ordering::Relaxed), [signal as u8].as_ptr().cast(), 1); });
+}
+
+// Restores the terminal once a signal is caught, then delivers the signal again with its default action.
+fn restore_on_signal(reader: RawFd) {
+    let mut signal = 0_u8;
+
+    // Safety: Valid `fd` and buffer, the pipe never being closed.
+    while unsafe { libc::read(reader, (&mut signal as *mut u8).cast(), 1) } != 1 {}
+    restore_everything();
+
+    // Safety: Resetting the action to the default one before sending the signal to the whole program.
+    unsafe {
+        libc::signal(signal.into(), libc::SIG_DFL);
+        libc::kill(libc::getpid(), signal.into());
+    }
+}
+
+// Undoes every mode enabled and the settings changed by active guards, straight through the descriptors,
+// as the locks may be held by the code that panicked.
+fn restore_everything() {
+    // The panic may have happened while holding the lock
+    let enabled = match ENABLED.try_lock() {
+        Ok(enabled) => enabled,
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        Err(TryLockError::WouldBlock) => return restore_settings(),
+    };
+
+    for (_, fd, undo) in enabled.iter().rev() {
+        // Safety: Writing from a valid buffer; a descriptor closed since fails harmlessly.
+        unsafe { libc::write(*fd, undo.as_ptr().cast(), undo.len()) };
+    }
+
+    restore_settings();
+}
@@ -1,8 +1,13 @@
 pub(crate) use crate::streams::unix::Config;
 
+#[derive(Clone, Copy)]
 pub(crate) enum Flag {
     Echo,
     Canonical,
     NotEcho,
     NotCanonical,
+    // Leaves control characters and carriage returns uninterpreted: no signals, flow control or translation.
+    Passthrough,
+    // Stops Ctrl+C from sending `SIGINT`, leaving the other signal keys as they are.
+    NoInterrupt,
 }
\ No newline at end of file
@@ -0,0 +1,98 @@
+use crate::width::{char_width, skip_escape, Escape};
+
+// The final bytes of control sequences that leave the cursor where it is:
+// styling, erasing, and setting or resetting modes.
+const STILL: &[char] = &['m', 'K', 'J', 'h', 'l'];
+
+// Gives where the cursor ends up after `text` is written with the cursor at `position`,
+// (`row`, `column`) starting from 1, on a terminal of the given `size`, if known.
+// A column one past the last marks a pending wrap, which happens once the next character is written.
+// Gives `None` if the text holds a control sequence that moves the cursor in ways not followed here.
+pub(super) fn advance(position: (usize, usize), size: Option<(usize, usize)>, text: &str) -> Option<(usize, usize)> {
+    let (mut row, mut column) = position;
+    let (rows, columns) = size.unwrap_or((usize::MAX, usize::MAX));
+
+    let mut chars = text.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            '\x1b' => match skip_escape(&mut chars) {
+                Escape::Control(Some(last)) if STILL.contains(&last) => {},
+                Escape::Command => {},
+                _ => return None,
+            },
+            '\n' => (row, column) = ((row + 1).min(rows), 1),
+            '\r' => column = 1,
+            // Tab stops are set every eight columns
+            '\t' => column = ((column - 1) / 8 * 8 + 9).min(columns),
+            '\x08' => column = column.saturating_sub(1).max(1),
+            char => {
+                let width = char_width(char);
+                if width == 0 { continue; }
+
+                // Characters that do not fit wrap onto the next line
+                if column + width - 1 > columns {
+                    (row, column) = ((row + 1).min(rows), 1);
+                }
+
+                column += width;
+            },
+        }
+    }
+
+    Some((row, column))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: Option<(usize, usize)> = Some((24, 80));
+
+    #[test]
+    fn advances_by_character_width() {
+        assert_eq!(advance((1, 1), SIZE, "abc"), Some((1, 4)));
+        assert_eq!(advance((1, 1), SIZE, "中"), Some((1, 3)));
+        assert_eq!(advance((1, 1), SIZE, "e\u{301}"), Some((1, 2)));
+        assert_eq!(advance((1, 1), SIZE, "a\u{200B}b"), Some((1, 3)));
+    }
+
+    #[test]
+    fn wraps_at_the_last_column() {
+        // Writing the last column leaves a pending wrap, done once the next character is written
+        assert_eq!(advance((1, 80), SIZE, "a"), Some((1, 81)));
+        assert_eq!(advance((1, 80), SIZE, "ab"), Some((2, 2)));
+        // A wide character not fitting in the last column moves to the next line whole
+        assert_eq!(advance((1, 80), SIZE, "中"), Some((2, 3)));
+        // Zero-width characters do not take up the pending wrap
+        assert_eq!(advance((1, 81), SIZE, "\u{301}"), Some((1, 81)));
+    }
+
+    #[test]
+    fn stays_on_the_last_row() {
+        assert_eq!(advance((24, 5), SIZE, "\n"), Some((24, 1)));
+        assert_eq!(advance((24, 80), SIZE, "ab"), Some((24, 2)));
+    }
+
+    #[test]
+    fn follows_control_characters() {
+        assert_eq!(advance((3, 10), SIZE, "\r"), Some((3, 1)));
+        assert_eq!(advance((1, 1), SIZE, "\t"), Some((1, 9)));
+        assert_eq!(advance((1, 78), SIZE, "\t"), Some((1, 80)));
+        assert_eq!(advance((1, 1), SIZE, "\x08"), Some((1, 1)));
+        assert_eq!(advance((1, 5), SIZE, "\x08"), Some((1, 4)));
+    }
+
+    #[test]
+    fn escape_sequences() {
+        assert_eq!(advance((1, 1), SIZE, "\x1b[31mab\x1b[0m"), Some((1, 3)));
+        assert_eq!(advance((1, 1), SIZE, "\x1b]0;title\x07x"), Some((1, 2)));
+        // Sequences moving the cursor are not followed
+        assert_eq!(advance((1, 1), SIZE, "\x1b[5A"), None);
+        assert_eq!(advance((1, 1), SIZE, "\x1b7"), None);
+    }
+
+    #[test]
+    fn unknown_size_never_wraps() {
+        assert_eq!(advance((1, 200), None, "a"), Some((1, 201)));
+    }
+}
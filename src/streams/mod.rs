@@ -2,52 +2,319 @@ use std::io::{
     self,
     Stderr, Stdout, Stdin,
     Write,
-    Result as IoResult,
+    Error as IoError, ErrorKind, Result as IoResult,
     IsTerminal,
 };
 
 use std::{
-    future::Future,
-    pin::Pin,
-    task::{Context, Poll},
-    time::Duration,
+    env,
+    error,
+    fmt,
+    fs::File,
+    ops,
+    os::fd::{AsFd, AsRawFd, RawFd},
+    collections::{HashMap, VecDeque},
+    str,
+    sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock, PoisonError, atomic::{AtomicBool, AtomicU64, Ordering}},
+    time::{Duration, Instant},
 };
-
-use derived_deref::{Deref, DerefMut};
+#[cfg(feature = "async")]
+use std::{future::Future, pin::Pin, task::{Context, Poll}};
 
 mod unix;
 mod config;
+mod base64;
+mod cursor;
+mod resize;
+#[cfg(feature = "async")]
+mod reactor;
+mod cleanup;
+mod poller;
 
-use crate::keys::Key;
-use unix::{read_key, read_string, size};
+use crate::builder::{ColorChoice, CtrlC, Newline, PromptOutput, Settings};
+use crate::events::{Event, MouseMode};
+use crate::keys::{Key, KeyEvent};
+use crate::proxy::Intercept;
+use crate::style::{Color, Style, RESET};
+use crate::widgets::{Completer, Completion, History};
+use crate::width::{column_after, grapheme_len, graphemes, wrap};
+#[cfg(feature = "zeroize")]
+use crate::secret::SecretString;
+use unix::{open_pipe, poll_input, read_available, read_clipboard, read_event, read_key, read_line_bytes, read_long_line, read_string, read_pending, read_cursor_report, read_window_reports, relay, size};
+pub(crate) use unix::{attributes, open_pty, set_size, set_speed};
 use crate::streams::config::{Flag, Config};
 
-// This struct represents the standard streams: stderr, stdout, and stdin.
+// This struct represents the streams of a terminal: stderr, stdout, and stdin.
 #[derive(Debug)]
 pub(super) struct Streams {
-    // The standard error stream.
-    stderr: Stderr,
-    // The standard output stream.
-    stdout: Stdout,
-    // The standard input stream, if available (i.e., in a user-attended terminal).
-    stdin: Option<Stdin>,
+    // The output streams.
+    output: Output,
+    // Where prompts are drawn.
+    prompt: Prompt,
+    // The input stream, if available (i.e., in a user-attended terminal).
+    stdin: Option<Input>,
+    // The settings handed to every lock.
+    settings: Settings,
+    // The state shared with every lock.
+    state: Arc<State>,
+}
+
+// State of the terminal that every lock taken from it must agree on.
+#[derive(Debug, Default)]
+struct State {
+    // Whether mouse reports carry pixel rather than cell positions.
+    mouse_pixels: AtomicBool,
+    // Events that have been read from the terminal but not yet handed out.
+    pending: Mutex<VecDeque<Event>>,
+    // Where the cursor is thought to be, if known and tracking is enabled.
+    cursor: Mutex<Option<(usize, usize)>>,
+    // Recorded sequences of key events.
+    macros: Mutex<Macros>,
+    // Measurements of the waits for input.
+    stats: Stats,
+    // The key read last and when, for telling held keys apart from new presses.
+    last_press: Mutex<Option<(Key, Instant)>>,
+    // The key event kept last and when, for dropping duplicates of it while debouncing.
+    last_kept: Mutex<Option<(KeyEvent, Instant)>>,
+    // Whether an input lock is held, so locks taken with and without the standard library's lock exclude each other.
+    claimed: Mutex<bool>,
+    // Notified as the claim on the input is released.
+    released: Condvar,
+    // The pipe waking event reads whenever a resize event is kept aside, once resize events are enabled.
+    resizes: OnceLock<(File, File)>,
+    // Bytes read past the end of a line by `read_long_line`, handed out before any further input.
+    unread: Mutex<VecDeque<u8>>,
+    // Escape sequences registered on top of the built-in ones, along with the key events they stand for.
+    sequences: Mutex<Vec<(Vec<u8>, KeyEvent)>>,
+    // Held while writing to the output locks, so writes made from several threads do not interleave.
+    output: Mutex<()>,
+}
+
+// The claim every input lock holds on the input, released once dropped.
+#[derive(Debug)]
+struct Claim(Arc<State>);
+
+impl Claim {
+    // Claims the input, waiting until `deadline` for another lock to release it, or for as long as it takes if `None`.
+    fn take(state: &Arc<State>, deadline: Option<Instant>) -> Option<Self> {
+        let mut claimed = state.claimed.lock().unwrap_or_else(PoisonError::into_inner);
+
+        while *claimed {
+            claimed = match deadline {
+                None => state.released.wait(claimed).unwrap_or_else(PoisonError::into_inner),
+                Some(deadline) => {
+                    let left = deadline.checked_duration_since(Instant::now()).filter(|left| !left.is_zero())?;
+                    state.released.wait_timeout(claimed, left).unwrap_or_else(PoisonError::into_inner).0
+                },
+            };
+        }
+
+        *claimed = true;
+        Some(Claim(Arc::clone(state)))
+    }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        *self.0.claimed.lock().unwrap_or_else(PoisonError::into_inner) = false;
+        self.0.released.notify_one();
+    }
+}
+
+// Counters behind `InputStats`.
+#[derive(Debug, Default)]
+struct Stats {
+    // How many times input was waited for.
+    polls: AtomicU64,
+    // How many of the waits ended with input available.
+    wakeups: AtomicU64,
+    // The time spent waiting in total, in nanoseconds.
+    waited: AtomicU64,
+}
+
+impl Stats {
+    // Adds a wait of the given length, which ended with input available if `ready`.
+    fn record(&self, waited: Duration, ready: bool) {
+        self.polls.fetch_add(1, Ordering::Relaxed);
+        self.wakeups.fetch_add(ready as u64, Ordering::Relaxed);
+        self.waited.fetch_add(waited.as_nanos().min(u64::MAX as u128) as u64, Ordering::Relaxed);
+    }
+}
+
+/// Measurements of how input has been waited for, as taken from [`StdinLock::input_stats`].
+/// Useful for tuning the escape timeout or frame pacing of latency-sensitive applications.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InputStats {
+    /// How many times input was waited for, including checks that did not wait.
+    pub polls: u64,
+    /// How many of the waits ended with input available, rather than timing out.
+    pub wakeups: u64,
+    /// The average time spent per wait.
+    pub avg_wait: Duration,
+}
+
+/// The position and dimensions of the terminal window, as taken from [`StdinLock::window_info`].
+/// Details the terminal does not report are [`None`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WindowInfo {
+    /// The position of the window on the screen, (`x`, `y`) in pixels.
+    pub position: Option<(usize, usize)>,
+    /// The size of the text area of the window, (`height`, `width`) in pixels.
+    pub text_area: Option<(usize, usize)>,
+    /// The size of a single cell, (`height`, `width`) in pixels, as reported or derived from
+    /// the size of the text area and the dimensions of the terminal.
+    pub cell: Option<(usize, usize)>,
+}
+
+// Key events recorded by the user, to be replayed later.
+#[derive(Debug, Default)]
+struct Macros {
+    // The register being recorded into, along with the events recorded so far.
+    recording: Option<(char, Vec<KeyEvent>)>,
+    // The finished recordings.
+    registers: HashMap<char, Vec<KeyEvent>>,
+}
+
+impl State {
+    // Gives the mode mouse reports are currently decoded in.
+    fn mouse_mode(&self) -> MouseMode {
+        match self.mouse_pixels.load(Ordering::Relaxed) {
+            true => MouseMode::Pixels,
+            false => MouseMode::Cells,
+        }
+    }
+
+    // Takes the oldest event kept aside by an earlier read, if any.
+    fn pop_pending(&self) -> Option<Event> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner).pop_front()
+    }
+
+    // Keeps an event aside for the next read.
+    fn push_pending(&self, event: Event) {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner).push_back(event)
+    }
+
+    // Whether any events are kept aside.
+    fn has_pending(&self) -> bool {
+        !self.pending.lock().unwrap_or_else(PoisonError::into_inner).is_empty()
+    }
+
+    // Gives access to the recorded key events.
+    fn macros(&self) -> MutexGuard<'_, Macros> {
+        self.macros.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Adds an event being handed out to the recording, if one is in progress.
+    fn record(&self, event: &Event) {
+        if let (Some((_, events)), Event::Key(key)) = (&mut self.macros().recording, event) {
+            events.push(*key);
+        }
+    }
+
+    // Whether bytes read ahead are waiting to be handed out.
+    fn has_unread(&self) -> bool {
+        !self.unread.lock().unwrap_or_else(PoisonError::into_inner).is_empty()
+    }
+
+    // Takes up to `count` of the bytes read ahead.
+    fn take_unread(&self, count: usize) -> Vec<u8> {
+        let mut unread = self.unread.lock().unwrap_or_else(PoisonError::into_inner);
+        let count = count.min(unread.len());
+        unread.drain(..count).collect()
+    }
+
+    // Takes the bytes read ahead up to and including the first newline, or all of them if there is none.
+    fn take_unread_line(&self) -> Vec<u8> {
+        let mut unread = self.unread.lock().unwrap_or_else(PoisonError::into_inner);
+        let count = unread.iter().position(|byte| *byte == b'\n').map_or(unread.len(), |index| index + 1);
+        unread.drain(..count).collect()
+    }
+
+    // Keeps bytes read ahead for the next reads.
+    fn push_unread(&self, bytes: &[u8]) {
+        self.unread.lock().unwrap_or_else(PoisonError::into_inner).extend(bytes);
+    }
+
+    // Gives the escape sequences registered so far.
+    fn sequences(&self) -> Vec<(Vec<u8>, KeyEvent)> {
+        self.sequences.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    // Whether any of the sequences registered so far starts with `byte`, checked without copying them.
+    fn starts_sequence(&self, byte: u8) -> bool {
+        self.sequences.lock().unwrap_or_else(PoisonError::into_inner).iter().any(|(sequence, _)| sequence[0] == byte)
+    }
+}
+
+// The terminal device output is written to.
+#[derive(Debug)]
+enum Output {
+    // The standard output and error streams.
+    Stdio { stdout: Stdout, stderr: Stderr },
+    // A terminal device receiving both regular and error output.
+    Tty(Arc<File>),
+}
+
+// The stream prompts are drawn on.
+#[derive(Debug)]
+enum Prompt {
+    // The regular output stream.
+    Stdout,
+    // The error output stream.
+    Stderr,
+    // A separately opened terminal device.
+    Tty(Arc<File>),
+}
+
+// The locked form of `Output`.
+#[derive(Debug)]
+enum Sink {
+    Stdout(io::StdoutLock<'static>),
+    Stderr(io::StderrLock<'static>),
+    Tty(Arc<File>),
+}
+
+// The terminal device input is read from.
+#[derive(Debug)]
+enum Input {
+    // The standard input stream.
+    Stdin(Stdin),
+    // A terminal device, such as the controlling terminal or a serial line.
+    Tty(Arc<File>),
+}
+
+// The locked form of `Input`.
+#[derive(Debug)]
+enum Source {
+    Stdin(io::StdinLock<'static>),
+    Tty(Arc<File>),
 }
 
 /// A wrapper for the standard input lock, allowing for synchronous read operations.
-#[derive(Debug, Deref, DerefMut)]
-pub struct StdinLock(io::StdinLock<'static>);
+#[derive(Debug)]
+pub struct StdinLock {
+    source: Source,
+    settings: Settings,
+    state: Arc<State>,
+    // The bytes read so far, while they are being kept for `read_key_with_bytes`.
+    capture: Option<Vec<u8>>,
+    // The claim held by every lock but the one of an `OwnedStdin`, released as the lock is dropped.
+    _claim: Option<Claim>,
+}
 
 // This macro generates asynchronous read functions with associated documentation.
+#[cfg(feature = "async")]
 macro_rules! read_future {
     // For each provided set of identifiers, types, and associated documentation...
-    ( $( $docs:literal | $read_future:ident as $future_read:ident with $flush:expr, $flags:expr => $ret:ty ),* $( , )? ) => { $(
+    ( $( $docs:literal | $read_future:ident / $read_future_until:ident as $future_read:ident with $flush:expr, $flags:expr => $ret:ty ),* $( , )? ) => { $(
         // Generate a function with the specified identifier and return type,
         // along with its associated documentation.
         #[doc = $docs]
         pub fn $read_future(&mut self) -> impl Future<Output = IoResult<$ret>> + '_ {
             // Define a struct for the asynchronous read operation.
+            // The error of setting the flags, if any, is given on the first poll.
             struct ReadFuture<'a> {
-                config: Config<'a>,
+                config: Result<Config<'a>, Option<IoError>>,
             }
 
             // Implement the Future trait for the asynchronous read operation.
@@ -56,12 +323,17 @@ macro_rules! read_future {
 
                 // Define how the future is polled.
                 fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-                    match $future_read(self.config.lock, 0)? {
+                    let config = match &mut self.config {
+                        Ok(config) => config,
+                        Err(error) => return Poll::Ready(Err(error.take().unwrap_or_else(|| ErrorKind::Other.into()))),
+                    };
+
+                    match $future_read(config.lock, 0)? {
                         // If ready, return the result
                         Some(out) => Poll::Ready(Ok(out)),
-                        // If no data is available, wake the task for later polling.
+                        // If no data is available, wake the task once there is.
                         None => {
-                            cx.waker().wake_by_ref();
+                            reactor::wake_when_ready(config.lock.wake_fds(), cx.waker(), config.lock.settings.poll_backend);
                             Poll::Pending
                         },
                     }
@@ -69,10 +341,63 @@ macro_rules! read_future {
             }
 
             // Sets the flags
-            let config = Config::set(self, $flush, $flags);
+            let config = Config::set(self, $flush, $flags).map_err(Some);
             // Return an instance of the asynchronous read future.
             ReadFuture { config }
         }
+
+        // Generate a cancellable counterpart, racing the read against the provided future.
+        #[doc = concat!(
+            "Like [`", stringify!($read_future), "`], but gives [`None`] once `cancel` completes first.\n",
+            "The terminal settings are restored as soon as the returned future resolves or is dropped."
+        )]
+        pub fn $read_future_until<'a, F>(
+            &'a mut self,
+            cancel: F,
+        ) -> impl Future<Output = IoResult<Option<$ret>>> + 'a
+        where
+            F: Future + 'a,
+        {
+            // Define a struct for the cancellable read operation.
+            struct ReadFutureUntil<'a, F> {
+                config: Result<Config<'a>, Option<IoError>>,
+                cancel: Pin<Box<F>>,
+            }
+
+            // Implement the Future trait for the cancellable read operation.
+            impl<'a, F: Future> Future for ReadFutureUntil<'a, F> {
+                type Output = IoResult<Option<$ret>>;
+
+                // Define how the future is polled.
+                fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+                    // If cancelled, give up without reading
+                    if self.cancel.as_mut().poll(cx).is_ready() {
+                        return Poll::Ready(Ok(None));
+                    }
+
+                    let config = match &mut self.config {
+                        Ok(config) => config,
+                        Err(error) => return Poll::Ready(Err(error.take().unwrap_or_else(|| ErrorKind::Other.into()))),
+                    };
+
+                    match $future_read(config.lock, 0)? {
+                        // If ready, return the result
+                        Some(out) => Poll::Ready(Ok(Some(out))),
+                        // If no data is available, wake the task once there is.
+                        None => {
+                            reactor::wake_when_ready(config.lock.wake_fds(), cx.waker(), config.lock.settings.poll_backend);
+                            Poll::Pending
+                        },
+                    }
+                }
+            }
+
+            // Sets the flags
+            let config = Config::set(self, $flush, $flags).map_err(Some);
+            let cancel = Box::pin(cancel);
+            // Return an instance of the cancellable read future.
+            ReadFutureUntil { config, cancel }
+        }
     )* };
 }
 
@@ -86,234 +411,2150 @@ macro_rules! read_or_timeout {
         pub fn $read_or_timeout(
             &mut self,
             timeout: Duration,
-        ) -> IoResult<Option<$ret>>
+        ) -> IoResult<Read<$ret>>
         {
-            // Convert the timeout duration to milliseconds.
-            let mut timeout = timeout.as_millis();
+            let start = Instant::now();
             // Set the flags for the input stream
-            let config = Config::set(self, $flush, $flags);
-
-            loop {
-                // If the remaining timeout is greater than the maximum i32 value...
-                if timeout > i32::MAX as u128 {
-                    // Call the provided read function with the maximum timeout value.
-                    match $timeout_read(config.lock, i32::MAX)? {
-                        // If data is available, give it as a [`Some`] variant.
-                        Some(read) => return Ok(Some(read)),
-                        // Otherwise, decrement the remaining timeout by the maximum value.
-                        None => timeout -= i32::MAX as u128,
-                    }
-                } else {
-                    // If the remaining timeout is within the i32 range...
-                    // Call the provided read function with the converted timeout value.
-                    return $timeout_read(config.lock, timeout as i32);
-                }
+            let config = Config::set(self, $flush, $flags)?;
+
+            // Wait for the read function to produce a value within the timeout.
+            match read_timed(config.lock, timeout, $timeout_read)? {
+                Some(value) => Ok(Read::Value(value)),
+                None => Ok(Read::TimedOut { elapsed: start.elapsed() }),
             }
         }
     )* };
 }
 
+// Calls the provided read function until it yields a value or the timeout elapses.
+// The timeout is split into chunks as `poll` only accepts an `i32` amount of milliseconds.
+fn read_timed<T>(
+    lock: &mut StdinLock,
+    timeout: Duration,
+    read: fn(&mut StdinLock, i32) -> IoResult<Option<T>>,
+) -> IoResult<Option<T>>
+{
+    // Convert the timeout duration to milliseconds.
+    let mut timeout = timeout.as_millis();
+
+    loop {
+        // If the remaining timeout is greater than the maximum i32 value...
+        if timeout > i32::MAX as u128 {
+            // Call the provided read function with the maximum timeout value.
+            match read(lock, i32::MAX)? {
+                // If data is available, give it as a [`Some`] variant.
+                Some(read) => return Ok(Some(read)),
+                // Otherwise, decrement the remaining timeout by the maximum value.
+                None => timeout -= i32::MAX as u128,
+            }
+        } else {
+            // If the remaining timeout is within the i32 range...
+            // Call the provided read function with the converted timeout value.
+            return read(lock, timeout as i32);
+        }
+    }
+}
+
+/// The outcome of reading with a timeout through the `read_*_or_timeout` methods, such as
+/// [`StdinLock::read_key_or_timeout`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Read<T> {
+    /// A value was read before the timeout elapsed.
+    Value(T),
+    /// The timeout elapsed first.
+    TimedOut {
+        /// How long was actually waited.
+        elapsed: Duration,
+    },
+}
+
+impl<T> Read<T> {
+    /// Gives the value read, or [`None`] if the timeout elapsed first.
+    pub fn value(self) -> Option<T> {
+        match self {
+            Read::Value(value) => Some(value),
+            Read::TimedOut { .. } => None,
+        }
+    }
+}
+
+/// The terminal modes to read in with [`StdinLock::read_key_with`] and [`StdinLock::read_string_with`],
+/// in place of the ones [`StdinLock::read_key`] and [`StdinLock::read_string`] choose.
+/// Defaults to the terminal's usual line editing, with echo on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadOptions {
+    /// Whether typed characters are echoed by the terminal.
+    pub echo: bool,
+    /// Whether input is gathered into lines, edited by the terminal, before being read (canonical mode).
+    /// Keys are only read once the line they are on is ended.
+    pub canonical: bool,
+    /// Whether control characters and carriage returns are left uninterpreted, so Ctrl+Z is read as a key
+    /// rather than sending a signal, and Ctrl+C is reported as chosen with [`TerminalBuilder::ctrl_c`].
+    /// Enter is then no longer read as a newline, so it does not end lines read with [`StdinLock::read_string_with`].
+    ///
+    /// [`TerminalBuilder::ctrl_c`]: crate::builder::TerminalBuilder::ctrl_c
+    pub passthrough: bool,
+    /// Whether input typed before the read is discarded.
+    pub flush: bool,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        ReadOptions { echo: true, canonical: true, passthrough: false, flush: false }
+    }
+}
+
+impl ReadOptions {
+    // Gives the flags setting the terminal up as chosen.
+    fn flags(&self) -> Vec<Flag> {
+        let mut flags = vec![
+            if self.canonical { Flag::Canonical } else { Flag::NotCanonical },
+            if self.echo { Flag::Echo } else { Flag::NotEcho },
+        ];
+
+        if self.passthrough { flags.push(Flag::Passthrough); }
+        flags
+    }
+}
+
+/// The volume of a terminal bell, for [`StdoutLock::set_bell_volume`] and [`StdoutLock::set_margin_bell_volume`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BellVolume {
+    /// The bell is silent.
+    Off,
+    /// The bell rings quietly.
+    Low,
+    /// The bell rings at full volume.
+    High,
+}
+
+impl BellVolume {
+    // Gives the parameter of the volume in DECSWBV and DECSMBV, which take 0 or 1 for off,
+    // 2 to 4 for low and 5 to 8 for high.
+    fn parameter(&self) -> u8 {
+        match self {
+            BellVolume::Off => 1,
+            BellVolume::Low => 4,
+            BellVolume::High => 8,
+        }
+    }
+}
+
+/// The outcome of reading a line with [`StdinLock::read_long_line`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LongLine {
+    /// The whole line, including the trailing newline unless the input ended first.
+    Complete(String),
+    /// The start of a line longer than the limit, cut back to a character boundary.
+    /// The rest of the line was read and discarded.
+    Truncated(String),
+}
+
+/// The outcome of reading a line with a timeout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Timeout {
+    /// A full line was entered before the timeout elapsed.
+    Complete(String),
+    /// The timeout elapsed first, holding whatever had been typed on the line so far.
+    Partial(String),
+}
+
 impl StdinLock {
-    /// Reads a single key from the standard input stream.
+    /// Reads a single key from the standard input stream. Typed keys are not echoed; see
+    /// [`StdinLock::read_key_echoed`] for printing them as they are read, or [`StdinLock::read_key_with`]
+    /// for reading with the terminal's own echo on.
     pub fn read_key(&mut self) -> IoResult<Key> {
-        let config = Config::set(self, false, &[Flag::NotCanonical, Flag::NotEcho]);
+        let config = Config::set(self, false, &[Flag::NotCanonical, Flag::NotEcho])?;
+        let value = read_key(config.lock, -1).map(Option::unwrap)?;
+
+        Ok(value)
+    }
+
+    /// Reads a single key from the standard input stream with the terminal set up as `options` choose.
+    /// With echo on and canonical mode off, as for typing tutors and quizzes, each key appears as it is typed;
+    /// keys without a character of their own, such as the arrows, are echoed the way the terminal shows them.
+    ///
+    /// ```rust,ignore
+    /// let options = ReadOptions { canonical: false, ..ReadOptions::default() };
+    /// let key = stdin.read_key_with(options)?;
+    /// ```
+    pub fn read_key_with(&mut self, options: ReadOptions) -> IoResult<Key> {
+        let config = Config::set(self, options.flush, &options.flags())?;
         let value = read_key(config.lock, -1).map(Option::unwrap)?;
 
         Ok(value)
     }
 
+    /// Reads a single key from the standard input stream, along with the bytes it was decoded from,
+    /// so the input can be logged or forwarded exactly as it was sent.
+    /// Keys left over from an earlier read, such as replayed ones, come with no bytes.
+    pub fn read_key_with_bytes(&mut self) -> IoResult<(Key, Vec<u8>)> {
+        let config = Config::set(self, false, &[Flag::NotCanonical, Flag::NotEcho])?;
+        config.lock.capture = Some(Vec::new());
+        let value = read_key(config.lock, -1).map(Option::unwrap);
+        let bytes = config.lock.capture.take().unwrap_or_default();
+
+        Ok((value?, bytes))
+    }
+
+    /// Reads a single key from the standard input stream, writing it to `stdout` if it is printable,
+    /// as the terminal would with echo on. Other keys, such as Enter or the arrows, are not echoed,
+    /// so simple "type to filter" interfaces can show what is typed without a line editor.
+    pub fn read_key_echoed(&mut self, stdout: &mut StdoutLock) -> IoResult<Key> {
+        let key = self.read_key()?;
+
+        if let Some(char) = key.as_char().filter(|_| key.is_printable()) {
+            stdout.print(char.encode_utf8(&mut [0; 4]))?;
+            stdout.flush()?;
+        }
+
+        Ok(key)
+    }
+
+    /// Reads a single event, such as a key or a mouse report, from the standard input stream.
+    /// Mouse reports are only sent once enabled with [`StdoutLock::enable_mouse_capture`], and
+    /// resizes are only read once enabled with [`Terminal::enable_resize_events`].
+    ///
+    /// [`Terminal::enable_resize_events`]: crate::Terminal::enable_resize_events
+    pub fn read_event(&mut self) -> IoResult<Event> {
+        let config = Config::set(self, false, &[Flag::NotCanonical, Flag::NotEcho])?;
+        let value = read_event(config.lock, -1).map(Option::unwrap)?;
+
+        Ok(value)
+    }
+
+    // Relays input to `child_input` and what is read from `child` to `output`, until the child's side
+    // is closed or `observe` stops the relay. See `Proxy::run`.
+    pub(crate) fn relay(
+        &mut self,
+        child: &mut StdinLock,
+        output: &mut StdoutLock,
+        child_input: &mut StdoutLock,
+        observe: impl FnMut(&Event, &[u8]) -> Intercept,
+    ) -> IoResult<()> {
+        // Ctrl+C is passed on to the child like any other key for as long as the relay lasts
+        let ctrl_c = std::mem::replace(&mut self.settings.ctrl_c, CtrlC::Key);
+        let relayed = Config::set(self, false, &[Flag::NotCanonical, Flag::NotEcho, Flag::Passthrough])
+            .and_then(|config| relay(config.lock, child, output, child_input, observe));
+
+        self.settings.ctrl_c = ctrl_c;
+        relayed
+    }
+
+    // Reads the raw bytes of the next key, as they are available in a single read.
+    pub(crate) fn read_raw(&mut self) -> IoResult<Vec<u8>> {
+        let config = Config::set(self, false, &[Flag::NotCanonical, Flag::NotEcho])?;
+        let value = read_available(config.lock, -1).map(Option::unwrap)?;
+
+        Ok(value)
+    }
+
     /// Reads a line of text from the standard input stream.
     pub fn read_string(&mut self) -> IoResult<String> {
-        let config = Config::set(self, false, &[Flag::Canonical, Flag::NotEcho]);
+        let config = Config::set(self, false, &[Flag::Canonical, Flag::NotEcho])?;
         let value = read_string(config.lock, -1).map(Option::unwrap)?;
 
         Ok(value)
     }
 
-    /// Reads a line of text from the standard input stream, but with the text hidden.
-    pub fn read_string_hidden(&mut self) -> IoResult<String> {
-        let config = Config::set(self, true, &[Flag::Canonical, Flag::NotEcho]);
+    /// Reads a line of text from the standard input stream with the terminal set up as `options` choose.
+    /// Outside of canonical mode, the line cannot be edited and is ended by the first newline read.
+    pub fn read_string_with(&mut self, options: ReadOptions) -> IoResult<String> {
+        let config = Config::set(self, options.flush, &options.flags())?;
         let value = read_string(config.lock, -1).map(Option::unwrap)?;
 
         Ok(value)
     }
 
-    read_or_timeout! {
-        "Reads a key with an optional timeout." |
-        read_key_or_timeout as read_key with false, &[Flag::NotCanonical, Flag::NotEcho] => Key,
-        "Reads a line of text with an optional timeout." |
-        read_string_or_timeout as read_string with false, &[Flag::Canonical, Flag::Echo] => String,
-        "Reads a line of text with an optional timeout, the text hidden." |
-        read_string_hidden_or_timeout as read_string with true, &[Flag::Canonical, Flag::NotEcho] => String,
+    /// Reads a line of text, editing it without the terminal's canonical mode, and gives it without the newline.
+    /// The line is drawn on `stdout` from the cursor onwards, so it can follow a prompt printed beforehand.
+    /// Backspace, Delete, the left and right arrows, Home, End, Ctrl+U (erasing up to the cursor)
+    /// and Ctrl+W (erasing the word before the cursor) edit the line, and Enter ends it.
+    /// Use [`LineEditor`] for history, completion or masking.
+    ///
+    /// [`LineEditor`]: crate::widgets::LineEditor
+    pub fn read_line_edited(&mut self, stdout: &mut StdoutLock) -> IoResult<String> {
+        let config = Config::set(self, false, &[Flag::NotCanonical, Flag::NotEcho])?;
+        edit_line(config.lock, stdout, LineOptions::default())
     }
 
-    read_future! {
-        "\
-            Reads a key asynchronously.\n\
-            `.await` should be used with caution as for each failed poll, the\n\
-            future will request to be polled again immediately. To combat this,\n\
-            the flags are set preemptively.\n\
-            ```rust\n\
-            let terminal = Terminal::new();\n\
-            let mut stdin = terminal.lock_stdin().expect(\"Failed to connect with terminal\");\n\
-            let future_key = stdin.read_key_future(); // Flags are set to correctly handle input\n\n\
-            // ...Code between runs (recommended to keep <30 ms)\n\n\
-            let key = future_key.await.expect(\"Failed to read from input stream\");\n\
-            ```\
-        " |
-        read_key_future as read_key with false, &[Flag::NotCanonical, Flag::NotEcho] => Key,
-        "Reads a line of text asynchronously." |
-        read_string_future as read_string with false, &[Flag::Canonical, Flag::Echo] => String,
-        "Reads a line of text asynchronously, the text hidden." |
-        read_string_hidden_future as read_string with true, &[Flag::Canonical, Flag::NotEcho] => String,
+    /// Reads a line of text as [`StdinLock::read_line_edited`] does, with the up and down arrows
+    /// recalling the entries of `history`, as a minimal readline would. The line is added to `history` once entered.
+    pub fn read_string_with_history(&mut self, stdout: &mut StdoutLock, history: &mut History) -> IoResult<String> {
+        let config = Config::set(self, false, &[Flag::NotCanonical, Flag::NotEcho])?;
+        edit_line(config.lock, stdout, LineOptions { history: Some(history), ..LineOptions::default() })
     }
-}
 
-/// A wrapper for the standard output lock.
-#[derive(Debug, Deref, DerefMut)]
-pub struct StdoutLock(io::StdoutLock<'static>);
+    /// Reads a line of text as [`StdinLock::read_line_edited`] does, completing it with `completer` when Tab
+    /// is pressed. A single candidate, or the prefix several share if it adds to what was typed, is inserted
+    /// first; pressing Tab again then cycles through the candidates.
+    pub fn read_string_with_completion(&mut self, stdout: &mut StdoutLock, completer: &impl Completer) -> IoResult<String> {
+        let config = Config::set(self, false, &[Flag::NotCanonical, Flag::NotEcho])?;
+        edit_line(config.lock, stdout, LineOptions { completer: Some(completer), ..LineOptions::default() })
+    }
 
-// Internal function for printing a string to the specified writer.
-fn print_<const LN: bool>(writer: &mut impl Write, str: &str) -> IoResult<()> {
-    writer.write_all(str.as_bytes())?;
+    /// Reads a line of text as [`StdinLock::read_line_edited`] does, showing `mask` in place of each
+    /// character typed, such as `*` for passwords, so typing gives feedback without revealing the text.
+    pub fn read_string_masked(&mut self, stdout: &mut StdoutLock, mask: char) -> IoResult<String> {
+        let config = Config::set(self, false, &[Flag::NotCanonical, Flag::NotEcho])?;
+        edit_line(config.lock, stdout, LineOptions { mask: Some(mask), ..LineOptions::default() })
+    }
 
-    if LN { writer.write_all(&[b'\n']) }
-    else { writer.flush() }
-}
+    /// Asks the terminal for the contents of the system clipboard (OSC 52), writing the query to
+    /// `stdout` and waiting up to `timeout` for the reply. Gives [`None`] if no reply arrives in time,
+    /// as terminals that do not support or permit clipboard reads stay silent.
+    /// Replies above 1 MiB fail with [`ErrorKind::InvalidData`], as do ones that are not UTF-8 text.
+    /// Keys pressed while waiting for the reply are discarded.
+    pub fn request_clipboard(&mut self, stdout: &mut StdoutLock, timeout: Duration) -> IoResult<Option<String>> {
+        const QUERY: &str = "\x1b]52;c;?\x07";
+        const LIMIT: usize = 1 << 20;
 
-impl StdoutLock {
-    /// Prints the specified string to the standard output.
-    pub fn print(&mut self, str: &str) -> IoResult<()> {
-        print_::<false>(&mut **self, str)
+        if self.settings.dumb { return Ok(None); }
+
+        let config = Config::set(self, false, &[Flag::NotCanonical, Flag::NotEcho])?;
+        stdout.print(QUERY)?;
+
+        // Durations too long to add to the current time wait for as long as an `Instant` allows
+        let now = Instant::now();
+        let deadline = now.checked_add(timeout).unwrap_or_else(|| now + Duration::from_secs(u32::MAX.into()));
+
+        let Some(data) = read_clipboard(config.lock, deadline, LIMIT * 4 / 3)? else {
+            return Ok(None);
+        };
+
+        let invalid = |message| IoError::new(ErrorKind::InvalidData, message);
+        let bytes = base64::decode(&data).ok_or_else(|| invalid("clipboard reply is not valid base64"))?;
+        String::from_utf8(bytes).map(Some).map_err(|_| invalid("clipboard contents are not valid UTF-8"))
     }
 
-    /// Prints the specified string to the standard output, followed by a newline character.
-    pub fn println(&mut self, str: &str) -> IoResult<()> {
-        print_::<true>(&mut **self, str)
+    /// Asks the terminal for the position and dimensions of its window (XTWINOPS), writing the
+    /// queries to `stdout` and waiting up to `timeout` for the replies, such as for placing images
+    /// precisely. Terminals that answer device attribute queries end the wait as soon as they are done
+    /// replying. Keys pressed while waiting for the replies are discarded.
+    pub fn window_info(&mut self, stdout: &mut StdoutLock, timeout: Duration) -> IoResult<WindowInfo> {
+        // The window position, text area size and cell size, followed by the primary device attributes
+        const QUERY: &str = "\x1b[13t\x1b[14t\x1b[16t\x1b[c";
+
+        if self.settings.dumb { return Ok(WindowInfo::default()); }
+
+        let config = Config::set(self, false, &[Flag::NotCanonical, Flag::NotEcho])?;
+        stdout.print(QUERY)?;
+
+        // Durations too long to add to the current time wait for as long as an `Instant` allows
+        let now = Instant::now();
+        let deadline = now.checked_add(timeout).unwrap_or_else(|| now + Duration::from_secs(u32::MAX.into()));
+
+        let mut info = WindowInfo::default();
+        for report in read_window_reports(config.lock, deadline)? {
+            let pair = Some((report[1] as usize, report[2] as usize));
+            match report[0] {
+                3 => info.position = pair,
+                4 => info.text_area = pair,
+                6 => info.cell = pair,
+                _ => {},
+            }
+        }
+
+        // Derive the cell size from the text area if it was not reported
+        if let (None, Some((height, width)), Some((rows, columns))) = (info.cell, info.text_area, stdout.size()) {
+            info.cell = Some((height / rows.max(1), width / columns.max(1)));
+        }
+
+        Ok(info)
     }
 
-    /// Clears the screen by sending an escape sequence.
-    pub fn clear(&mut self) -> IoResult<()> {
-        const CLEAR_SCREEN: &str = "\r\x1b[2J\r\x1b[H";
-        self.print(CLEAR_SCREEN)
+    /// Reads a line from the standard input stream as raw bytes, including the trailing newline.
+    /// Unlike [`StdinLock::read_string`], the input is not required to be valid UTF-8, so text
+    /// in legacy encodings or non-UTF-8 file names are kept exactly as entered.
+    pub fn read_line_bytes(&mut self) -> IoResult<Vec<u8>> {
+        let config = Config::set(self, false, &[Flag::Canonical, Flag::Echo])?;
+        let value = read_line_bytes(config.lock, -1).map(Option::unwrap)?;
+
+        Ok(value)
     }
 
-    /// Clears the screen from the cursor position to the end of the screen.
-    pub fn clear_to_end(&mut self) -> IoResult<()> {
-        const CLEAR_TO_END: &str = "\x1b[J";
-        self.print(CLEAR_TO_END)
+    /// Reads a line of text from the standard input stream without the limit the kernel puts on lines
+    /// read in canonical mode (4096 bytes on Linux), past which pasted text is silently cut off.
+    /// The line is gathered in non-canonical mode instead, so typed text is echoed but cannot be edited:
+    /// Backspace is echoed as `^?` and its byte (0x7f) is kept in the line like any other.
+    /// Lines longer than `limit` bytes, if given, are read to their end but given as [`LongLine::Truncated`].
+    /// Input following the line, such as further pasted lines, is kept for the next reads.
+    /// The end of input, or Ctrl+D pressed on its own, ends the line without a newline.
+    /// Fails with [`ErrorKind::UnexpectedEof`] if the input ended before any of the line was read,
+    /// or with [`ErrorKind::InvalidData`] if the line is not valid UTF-8.
+    pub fn read_long_line(&mut self, limit: Option<usize>) -> IoResult<LongLine> {
+        let config = Config::set(self, false, &[Flag::NotCanonical, Flag::Echo])?;
+        let (mut line, truncated) = read_long_line(config.lock, limit)?;
+
+        // A line cut short can end partway through a character
+        if let (true, Err(error)) = (truncated, str::from_utf8(&line)) {
+            if error.error_len().is_none() { line.truncate(error.valid_up_to()); }
+        }
+
+        let line = String::from_utf8(line)
+            .map_err(|_| IoError::new(ErrorKind::InvalidData, "stream did not contain valid UTF-8"))?;
+
+        match truncated {
+            true => Ok(LongLine::Truncated(line)),
+            false => Ok(LongLine::Complete(line)),
+        }
     }
 
-    /// Clears the screen from the beginning to the cursor position.
-    pub fn clear_to_beginning(&mut self) -> IoResult<()> {
-        const CLEAR_TO_BEGINNING: &str = "\x1b[1J";
-        self.print(CLEAR_TO_BEGINNING)
+    /// Reads a line of text from the standard input stream, but with the text hidden.
+    pub fn read_string_hidden(&mut self) -> IoResult<String> {
+        let config = Config::set(self, true, &[Flag::Canonical, Flag::NotEcho])?;
+        let value = read_string(config.lock, -1).map(Option::unwrap)?;
+
+        Ok(value)
     }
 
-    /// Clears the current line from the cursor position to the end of the line.
-    pub fn clear_line_to_end(&mut self) -> IoResult<()> {
-        const CLEAR_LINE_TO_END: &str = "\x1b[K";
-        self.print(CLEAR_LINE_TO_END)
+    /// Reads a password from the standard input stream, with the text hidden and the trailing newline removed.
+    /// The buffers it is read through are wiped, as is the returned [`SecretString`] once dropped.
+    #[cfg(feature = "zeroize")]
+    pub fn read_password(&mut self) -> IoResult<SecretString> {
+        let mut password = self.read_string_hidden()?;
+
+        // Truncating leaves the allocation as it is, to be wiped along with the rest
+        let length = password.trim_end_matches(['\r', '\n']).len();
+        password.truncate(length);
+
+        Ok(SecretString::from(password))
     }
 
-    /// Clears the current line from the beginning to the cursor position.
-    pub fn clear_line_to_beginning(&mut self) -> IoResult<()> {
-        const CLEAR_LINE_TO_BEGINNING: &str = "\x1b[1K";
-        self.print(CLEAR_LINE_TO_BEGINNING)
+    /// Starts recording the key events read from this terminal into `register`, as with `q` in Vim.
+    /// A recording already in progress is stopped first, as with [`StdinLock::stop_recording`].
+    pub fn start_recording(&mut self, register: char) {
+        self.stop_recording();
+        self.state.macros().recording = Some((register, Vec::new()));
     }
 
-    /// Moves the cursor to the specified row and column.
-    pub fn move_cursor(&mut self, rows: usize, columns: usize) -> IoResult<()> {
-        let move_cursor = format!("\x1b[{};{}H", rows, columns);
-        self.print(&move_cursor)
+    /// Stops recording, storing the recorded key events in the register, which replaces its earlier contents.
+    /// Recordings are meant to be stopped in response to a key, so the last key event read,
+    /// being that key, is left out. Gives the register recorded into, if a recording was in progress.
+    pub fn stop_recording(&mut self) -> Option<char> {
+        let mut macros = self.state.macros();
+        let (register, mut events) = macros.recording.take()?;
+
+        events.pop();
+        macros.registers.insert(register, events);
+        Some(register)
     }
 
-    /// Moves the cursor up by a specified number of rows.
-    pub fn move_cursor_up(&mut self, rows: usize) -> IoResult<()> {
-        let move_up = format!("\x1b[{}A", rows);
-        self.print(&move_up)
+    /// Gives the register being recorded into, if a recording is in progress.
+    pub fn recording(&self) -> Option<char> {
+        self.state.macros().recording.as_ref().map(|(register, _)| *register)
     }
 
-    /// Moves the cursor down by a specified number of rows.
-    pub fn move_cursor_down(&mut self, rows: usize) -> IoResult<()> {
-        let move_down = format!("\x1b[{}B", rows);
-        self.print(&move_down)
+    /// Replays the key events stored in a register, which are then read through the regular
+    /// reading methods before any further input. Returns whether the register holds a recording.
+    pub fn replay(&mut self, register: char) -> bool {
+        let Some(events) = self.state.macros().registers.get(&register).cloned() else { return false };
+
+        for event in events {
+            self.state.push_pending(Event::Key(event));
+        }
+
+        true
     }
 
-    /// Moves the cursor forward (right) by a specified number of columns.
-    pub fn move_cursor_forward(&mut self, columns: usize) -> IoResult<()> {
-        let move_forward = format!("\x1b[{}C", columns);
-        self.print(&move_forward)
+    /// Registers a sequence of bytes to be read as `event`, for terminals sending keys the built-in
+    /// sequences do not cover, or devices such as barcode scanners and custom keyboards sending their own.
+    /// Registered sequences take precedence over the built-in ones and are shared by every lock of
+    /// this terminal, with a sequence registered again being replaced. The bytes of a sequence are
+    /// expected to arrive within the escape timeout of each other; bytes starting a sequence but not
+    /// making one up are read as the keys they are on their own.
+    /// Returns whether the sequence was registered, which all are but an empty one and a lone escape character.
+    pub fn register_sequence(&mut self, sequence: &[u8], event: impl Into<KeyEvent>) -> bool {
+        if sequence.is_empty() || sequence == b"\x1b" { return false; }
+
+        let mut sequences = self.state.sequences.lock().unwrap_or_else(PoisonError::into_inner);
+        sequences.retain(|(other, _)| other != sequence);
+        sequences.push((sequence.to_vec(), event.into()));
+        true
     }
 
-    /// Moves the cursor backward (left) by a specified number of columns.
-    pub fn move_cursor_backward(&mut self, columns: usize) -> IoResult<()> {
-        let move_backward = format!("\x1b[{}D", columns);
-        self.print(&move_backward)
+    /// Gives measurements of how input has been waited for through every lock of this terminal,
+    /// since it was created or the measurements were last reset.
+    pub fn input_stats(&self) -> InputStats {
+        let stats = &self.state.stats;
+        let polls = stats.polls.load(Ordering::Relaxed);
+        let waited = stats.waited.load(Ordering::Relaxed);
+
+        InputStats {
+            polls,
+            wakeups: stats.wakeups.load(Ordering::Relaxed),
+            avg_wait: Duration::from_nanos(waited.checked_div(polls).unwrap_or_default()),
+        }
     }
 
-    /// Hides the cursor in the terminal.
-    pub fn hide(&mut self) -> IoResult<()> {
-        const HIDE_CURSOR: &str = "\x1b[?25l";
-        self.print(HIDE_CURSOR)
+    /// Resets the measurements given by [`StdinLock::input_stats`].
+    pub fn reset_input_stats(&mut self) {
+        let stats = &self.state.stats;
+        for counter in [&stats.polls, &stats.wakeups, &stats.waited] {
+            counter.store(0, Ordering::Relaxed);
+        }
     }
 
-    /// Shows the cursor in the terminal.
-    pub fn show(&mut self) -> IoResult<()> {
-        const SHOW_CURSOR: &str = "\x1b[?25h";
-        self.print(SHOW_CURSOR)
+    /// Checks whether input is available without consuming it.
+    /// Waits up to `timeout` for input to arrive, or indefinitely if it is [`None`].
+    /// A zero timeout checks without waiting at all. A single key pressed is input, as the terminal
+    /// is taken out of canonical mode while waiting, rather than only a whole line once Enter is pressed.
+    pub fn has_input(&mut self, timeout: Option<Duration>) -> IoResult<bool> {
+        // Events kept aside by an earlier read are input that is already available
+        if self.state.has_pending() { return Ok(true); }
+
+        let config = Config::set(self, false, &[Flag::NotCanonical, Flag::NotEcho])?;
+        match timeout {
+            Some(timeout) => read_timed(config.lock, timeout, |lock, timeout| {
+                poll_input(lock, timeout).map(|ready| ready.then_some(()))
+            })
+            .map(|ready| ready.is_some()),
+            None => poll_input(config.lock, -1),
+        }
     }
 
-    /// Gives the dimensions of the terminal, (`row`, `column`).
-    pub fn size(&self) -> Option<(usize, usize)> {
-        size(self)
+    // Gives the descriptors whose readiness means a read can make progress: the input,
+    // and the pipe resize events are announced through, if enabled.
+    #[cfg(feature = "async")]
+    fn wake_fds(&self) -> Vec<RawFd> {
+        let resizes = self.state.resizes.get().map(|(reader, _)| reader.as_raw_fd());
+        [self.as_raw_fd()].into_iter().chain(resizes).collect()
     }
-}
 
-/// A wrapper for the standard error lock.
-#[derive(Debug, Deref, DerefMut)]
-pub struct StderrLock(io::StderrLock<'static>);
+    /// Sets how Ctrl+C is reported while reading keys with this lock, in place of the choice
+    /// the terminal was built with, see [`TerminalBuilder::ctrl_c`].
+    ///
+    /// [`TerminalBuilder::ctrl_c`]: crate::builder::TerminalBuilder::ctrl_c
+    pub fn set_ctrl_c(&mut self, ctrl_c: CtrlC) {
+        self.settings.ctrl_c = ctrl_c;
+    }
 
-impl StderrLock {
-    /// Prints the specified string to the standard error stream.
-    pub fn print(&mut self, str: &str) -> IoResult<()> {
-        print_::<false>(&mut **self, str)
+    /// Turns terminal echo off until the returned guard is dropped.
+    /// Reads can still be made through the guard, which restores the previous settings on drop.
+    /// Fails if the terminal settings cannot be changed.
+    pub fn echo_off(&mut self) -> IoResult<EchoGuard<'_>> {
+        let config = Config::set(self, false, &[Flag::NotEcho])?;
+        Ok(EchoGuard { config })
     }
 
-    /// Prints the specified string to the standard error stream, followed by a newline character.
-    pub fn println(&mut self, str: &str) -> IoResult<()> {
-        print_::<true>(&mut **self, str)
+    /// Makes input raw, unbuffered and unechoed, until the returned guard is dropped, as every key
+    /// read does for its own duration. Reading many keys through the guard saves changing the
+    /// terminal settings for each of them, which is measurable and makes some terminals flicker.
+    /// Reads needing other settings, such as reading a line, still get them for their own duration.
+    /// Fails if the terminal settings cannot be changed.
+    pub fn raw_mode(&mut self) -> IoResult<RawModeGuard<'_>> {
+        let config = Config::set(self, false, &[Flag::NotCanonical, Flag::NotEcho])?;
+        Ok(RawModeGuard { config })
     }
-}
 
-impl Streams {
-    // Creates a new Streams instance with the standard input, output, and error streams.
-    pub(super) fn new() -> Self {
-        let stderr = io::stderr();
-        let stdout = io::stdout();
-        let stdin = Some(io::stdin()).filter(Stdin::is_terminal);
+    /// Turns terminal echo on until the returned guard is dropped.
+    /// Reads can still be made through the guard, which restores the previous settings on drop.
+    /// Fails if the terminal settings cannot be changed.
+    pub fn echo_on(&mut self) -> IoResult<EchoGuard<'_>> {
+        let config = Config::set(self, false, &[Flag::Echo])?;
+        Ok(EchoGuard { config })
+    }
 
-        Streams { stderr, stdout, stdin }
+    read_or_timeout! {
+        "Reads a key with an optional timeout." |
+        read_key_or_timeout as read_key with false, &[Flag::NotCanonical, Flag::NotEcho] => Key,
+        "Reads an event with an optional timeout." |
+        read_event_or_timeout as read_event with false, &[Flag::NotCanonical, Flag::NotEcho] => Event,
     }
 
-    // Locks the standard output stream, providing a controlled interface for writing.
-    pub(super) fn lock_stdout(&self) -> StdoutLock {
-        let lock = self.stdout.lock();
-        StdoutLock(lock)
+    /// Reads a line of text with an optional timeout.
+    /// If the timeout elapses, the partially typed line is returned as [`Timeout::Partial`].
+    pub fn read_string_or_timeout(&mut self, timeout: Duration) -> IoResult<Timeout> {
+        let config = Config::set(self, false, &[Flag::Canonical, Flag::Echo])?;
+
+        match read_timed(config.lock, timeout, read_string)? {
+            Some(line) => Ok(Timeout::Complete(line)),
+            None => read_pending(config.lock).map(Timeout::Partial),
+        }
     }
 
-    // Locks the standard error stream, providing a controlled interface for writing.
-    pub(super) fn lock_stderr(&self) -> StderrLock {
-        let lock = self.stderr.lock();
-        StderrLock(lock)
+    /// Reads a line of text with an optional timeout, the text hidden.
+    /// If the timeout elapses, the partially typed line is returned as [`Timeout::Partial`].
+    pub fn read_string_hidden_or_timeout(&mut self, timeout: Duration) -> IoResult<Timeout> {
+        let config = Config::set(self, true, &[Flag::Canonical, Flag::NotEcho])?;
+
+        match read_timed(config.lock, timeout, read_string)? {
+            Some(line) => Ok(Timeout::Complete(line)),
+            None => read_pending(config.lock).map(Timeout::Partial),
+        }
     }
 
-    // Attempts to lock the standard input stream if it is associated with a user-attended terminal.
-    pub(super) fn lock_stdin(&self) -> Option<StdinLock> {
-        self.stdin
-            .as_ref()
-            .map(Stdin::lock)
-            .map(StdinLock)
+    #[cfg(feature = "async")]
+    read_future! {
+        "\
+            Reads a key asynchronously.\n\
+            The future is woken once input arrives, by a background thread shared by\n\
+            every pending read, so it works with any executor without polling in a loop.\n\
+            The flags are set as the future is created, so keys typed before it is\n\
+            first polled are read correctly.\n\
+            ```rust,ignore\n\
+            let terminal = Terminal::new();\n\
+            let mut stdin = terminal.lock_stdin().expect(\"Failed to connect with terminal\");\n\
+            let future_key = stdin.read_key_future(); // Flags are set to correctly handle input\n\n\
+            // ...Code between runs (recommended to keep <30 ms)\n\n\
+            let key = future_key.await.expect(\"Failed to read from input stream\");\n\
+            ```\
+        " |
+        read_key_future / read_key_future_until as read_key with false, &[Flag::NotCanonical, Flag::NotEcho] => Key,
+        "Reads an event asynchronously, woken like [`StdinLock::read_key_future`]." |
+        read_event_future / read_event_future_until as read_event with false, &[Flag::NotCanonical, Flag::NotEcho] => Event,
+        "Reads a line of text asynchronously." |
+        read_string_future / read_string_future_until as read_string with false, &[Flag::Canonical, Flag::Echo] => String,
+        "Reads a line of text asynchronously, the text hidden." |
+        read_string_hidden_future / read_string_hidden_future_until as read_string with true, &[Flag::Canonical, Flag::NotEcho] => String,
+    }
+}
+
+/// An input handle owning its own descriptor, which unlike [`StdinLock`] can be sent to other threads
+/// or moved into asynchronous tasks. It offers the reading methods of [`StdinLock`], each behaving as there.
+///
+/// The handle does not hold the lock of [`io::Stdin`], so reading through it at the same time as
+/// through another handle splits the input between the two.
+#[derive(Debug)]
+pub struct OwnedStdin(StdinLock);
+
+// Safety: The inner lock is only ever made with an owned descriptor, never with the
+// thread-bound lock of the standard input stream, leaving nothing tied to the creating thread.
+// The lock is never handed out by mutable reference, so it cannot be swapped for one that is.
+unsafe impl Send for OwnedStdin {}
+
+// This macro generates methods of `OwnedStdin` handing over to the methods of `StdinLock` with the same name.
+macro_rules! forward_stdin {
+    ( $( $( #[$attr:meta] )* fn $name:ident(&mut self $( , $arg:ident: $ty:ty )* ) $( -> $ret:ty )?; )* ) => { $(
+        #[doc = concat!("See [`StdinLock::", stringify!($name), "`].")]
+        $( #[$attr] )*
+        pub fn $name(&mut self $( , $arg: $ty )* ) $( -> $ret )? {
+            self.0.$name($( $arg ),*)
+        }
+    )* };
+}
+
+impl OwnedStdin {
+    forward_stdin! {
+        fn read_key(&mut self) -> IoResult<Key>;
+        fn read_key_with(&mut self, options: ReadOptions) -> IoResult<Key>;
+        fn read_key_with_bytes(&mut self) -> IoResult<(Key, Vec<u8>)>;
+        fn read_key_echoed(&mut self, stdout: &mut StdoutLock) -> IoResult<Key>;
+        fn read_event(&mut self) -> IoResult<Event>;
+        fn read_string(&mut self) -> IoResult<String>;
+        fn read_string_with(&mut self, options: ReadOptions) -> IoResult<String>;
+        fn read_line_edited(&mut self, stdout: &mut StdoutLock) -> IoResult<String>;
+        fn read_string_with_history(&mut self, stdout: &mut StdoutLock, history: &mut History) -> IoResult<String>;
+        fn read_string_with_completion(&mut self, stdout: &mut StdoutLock, completer: &impl Completer) -> IoResult<String>;
+        fn read_string_masked(&mut self, stdout: &mut StdoutLock, mask: char) -> IoResult<String>;
+        fn request_clipboard(&mut self, stdout: &mut StdoutLock, timeout: Duration) -> IoResult<Option<String>>;
+        fn window_info(&mut self, stdout: &mut StdoutLock, timeout: Duration) -> IoResult<WindowInfo>;
+        fn read_line_bytes(&mut self) -> IoResult<Vec<u8>>;
+        fn read_long_line(&mut self, limit: Option<usize>) -> IoResult<LongLine>;
+        fn read_string_hidden(&mut self) -> IoResult<String>;
+        #[cfg(feature = "zeroize")]
+        fn read_password(&mut self) -> IoResult<SecretString>;
+        fn start_recording(&mut self, register: char);
+        fn stop_recording(&mut self) -> Option<char>;
+        fn replay(&mut self, register: char) -> bool;
+        fn register_sequence(&mut self, sequence: &[u8], event: impl Into<KeyEvent>) -> bool;
+        fn reset_input_stats(&mut self);
+        fn has_input(&mut self, timeout: Option<Duration>) -> IoResult<bool>;
+        fn set_ctrl_c(&mut self, ctrl_c: CtrlC);
+        fn read_key_or_timeout(&mut self, timeout: Duration) -> IoResult<Read<Key>>;
+        fn read_event_or_timeout(&mut self, timeout: Duration) -> IoResult<Read<Event>>;
+        fn read_string_or_timeout(&mut self, timeout: Duration) -> IoResult<Timeout>;
+        fn read_string_hidden_or_timeout(&mut self, timeout: Duration) -> IoResult<Timeout>;
+        #[cfg(feature = "async")]
+        fn read_key_future(&mut self) -> impl Future<Output = IoResult<Key>> + '_;
+        #[cfg(feature = "async")]
+        fn read_event_future(&mut self) -> impl Future<Output = IoResult<Event>> + '_;
+        #[cfg(feature = "async")]
+        fn read_string_future(&mut self) -> impl Future<Output = IoResult<String>> + '_;
+        #[cfg(feature = "async")]
+        fn read_string_hidden_future(&mut self) -> impl Future<Output = IoResult<String>> + '_;
+    }
+
+    /// See [`StdinLock::read_key_future_until`].
+    #[cfg(feature = "async")]
+    pub fn read_key_future_until<'a>(&'a mut self, cancel: impl Future + 'a) -> impl Future<Output = IoResult<Option<Key>>> + 'a {
+        self.0.read_key_future_until(cancel)
+    }
+
+    /// See [`StdinLock::read_event_future_until`].
+    #[cfg(feature = "async")]
+    pub fn read_event_future_until<'a>(&'a mut self, cancel: impl Future + 'a) -> impl Future<Output = IoResult<Option<Event>>> + 'a {
+        self.0.read_event_future_until(cancel)
+    }
+
+    /// See [`StdinLock::read_string_future_until`].
+    #[cfg(feature = "async")]
+    pub fn read_string_future_until<'a>(&'a mut self, cancel: impl Future + 'a) -> impl Future<Output = IoResult<Option<String>>> + 'a {
+        self.0.read_string_future_until(cancel)
+    }
+
+    /// See [`StdinLock::read_string_hidden_future_until`].
+    #[cfg(feature = "async")]
+    pub fn read_string_hidden_future_until<'a>(
+        &'a mut self,
+        cancel: impl Future + 'a,
+    ) -> impl Future<Output = IoResult<Option<String>>> + 'a {
+        self.0.read_string_hidden_future_until(cancel)
+    }
+
+    /// See [`StdinLock::recording`].
+    pub fn recording(&self) -> Option<char> {
+        self.0.recording()
+    }
+
+    /// See [`StdinLock::input_stats`].
+    pub fn input_stats(&self) -> InputStats {
+        self.0.input_stats()
+    }
+}
+
+impl io::Read for OwnedStdin {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl AsRawFd for OwnedStdin {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+/// The error reads fail with once the terminal is gone, such as when its window is closed or an SSH
+/// connection drops, carried inside an [`io::Error`] of kind [`ErrorKind::BrokenPipe`].
+/// See [`is_terminal_closed`] for telling it apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerminalClosed;
+
+impl fmt::Display for TerminalClosed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the terminal was closed")
+    }
+}
+
+impl error::Error for TerminalClosed {}
+
+/// Whether `error` means the terminal is gone, so the application can exit cleanly rather than
+/// report a failure. Besides [`TerminalClosed`], this covers the `EIO` and `ENXIO` errors writes
+/// fail with once the terminal hangs up.
+pub fn is_terminal_closed(error: &io::Error) -> bool {
+    error.get_ref().is_some_and(|inner| inner.is::<TerminalClosed>())
+        || matches!(error.raw_os_error(), Some(libc::EIO | libc::ENXIO))
+}
+
+// The error given once the terminal has hung up.
+pub(crate) fn terminal_closed() -> IoError {
+    IoError::new(ErrorKind::BrokenPipe, TerminalClosed)
+}
+
+// What the line editor behind `read_line_edited` and the methods built on it adds to plain editing.
+#[derive(Default)]
+struct LineOptions<'a> {
+    // Shown in place of each character.
+    mask: Option<char>,
+    // Recalled with the up and down arrows, and added to once the line is entered.
+    history: Option<&'a mut History>,
+    // Completes the line when Tab is pressed.
+    completer: Option<&'a dyn Completer>,
+}
+
+// Edits a line for `read_line_edited` and the methods built on it, until Enter is pressed.
+fn edit_line(lock: &mut StdinLock, stdout: &mut StdoutLock, options: LineOptions) -> IoResult<String> {
+    let LineOptions { mask, history, completer } = options;
+    let mut text = String::new();
+    let mut cursor = 0;
+    // The entry shown, with the new line being one past the last entry, and the new line while it is not
+    let mut index = history.as_deref().map_or(0, |history| history.entries().len());
+    let mut draft = String::new();
+    // The completion Tab cycles through, with the candidate shown, once pressed with several candidates
+    let mut cycle: Option<(Completion, usize)> = None;
+    // The columns between the start of the line and the cursor, as last drawn
+    let mut drawn = 0;
+
+    loop {
+        let key = read_key(lock, -1).map(Option::unwrap)?;
+        let cycling = cycle.take();
+        match key {
+            // The line cannot be ended any other way once the input has
+            Key::Eof => return Err(IoError::from(ErrorKind::UnexpectedEof)),
+            Key::Enter => {
+                stdout.println("")?;
+                if let Some(history) = history { history.push(&text); }
+                return Ok(text);
+            },
+            Key::ArrowUp | Key::ArrowDown => {
+                let Some(entries) = history.as_deref().map(History::entries) else { continue };
+                let next = match key {
+                    Key::ArrowUp => index.checked_sub(1),
+                    _ => (index < entries.len()).then_some(index + 1),
+                };
+                let Some(next) = next else { continue };
+
+                if index == entries.len() { draft = std::mem::take(&mut text); }
+                index = next;
+                text = entries.get(index).cloned().unwrap_or_else(|| std::mem::take(&mut draft));
+                cursor = text.len();
+            },
+            // Pressing Tab again moves on to the next candidate
+            Key::Tab if cycling.is_some() => {
+                let Some((completion, shown)) = cycling else { continue };
+                let start = completion.range.start;
+                let next = (shown + 1) % completion.candidates.len();
+
+                text.replace_range(start..start + completion.candidates[shown].len(), &completion.candidates[next]);
+                cursor = start + completion.candidates[next].len();
+                cycle = Some((completion, next));
+            },
+            Key::Tab => {
+                let Some(completer) = completer else { continue };
+                let completion = completer.complete(&text, cursor);
+                // Ranges not on character boundaries of the line are ignored
+                let Some(typed) = text.get(completion.range.clone()).filter(|_| !completion.candidates.is_empty()) else { continue };
+
+                // A prefix adding to what was typed is inserted before cycling through the candidates
+                let prefix = completion.shared_prefix();
+                let (replacement, shown) = match completion.candidates.len() == 1 || !(prefix.is_empty() || prefix == typed) {
+                    true => (prefix.to_string(), None),
+                    false => (completion.candidates[0].clone(), Some(0)),
+                };
+
+                text.replace_range(completion.range.clone(), &replacement);
+                cursor = completion.range.start + replacement.len();
+                cycle = shown.map(|shown| (completion, shown));
+            },
+            Key::Char(char) if !char.is_control() => {
+                text.insert(cursor, char);
+                cursor += char.len_utf8();
+            },
+            Key::Backspace => if let Some(grapheme) = graphemes(&text[..cursor]).last() {
+                let start = cursor - grapheme.len();
+                text.replace_range(start..cursor, "");
+                cursor = start;
+            },
+            Key::Del => {
+                let end = cursor + grapheme_len(&text[cursor..]);
+                text.replace_range(cursor..end, "");
+            },
+            Key::ArrowLeft => cursor -= graphemes(&text[..cursor]).last().map_or(0, str::len),
+            Key::ArrowRight => cursor += grapheme_len(&text[cursor..]),
+            Key::Home | Key::Ctrl('a') => cursor = 0,
+            Key::End | Key::Ctrl('e') => cursor = text.len(),
+            // Ctrl+U
+            Key::Ctrl('u') => {
+                text.replace_range(..cursor, "");
+                cursor = 0;
+            },
+            // Ctrl+W
+            Key::Ctrl('w') => {
+                let word = text[..cursor].trim_end_matches(char::is_whitespace);
+                let start = word.trim_end_matches(|char: char| !char.is_whitespace()).len();
+                text.replace_range(start..cursor, "");
+                cursor = start;
+            },
+            _ => continue,
+        }
+
+        drawn = redraw_line(stdout, &text, cursor, drawn, mask)?;
+    }
+}
+
+// Redraws a line being edited, with the cursor `drawn` columns into it, leaving the cursor
+// `cursor` bytes into `text`. Gives the columns the cursor is then into the line.
+fn redraw_line(stdout: &mut StdoutLock, text: &str, cursor: usize, drawn: usize, mask: Option<char>) -> IoResult<usize> {
+    let shown = |text: &str| match mask {
+        Some(mask) => graphemes(text).map(|_| mask).collect(),
+        None => text.to_string(),
+    };
+    let (before, after) = (shown(&text[..cursor]), shown(&text[cursor..]));
+
+    stdout.atomic(|stdout| {
+        // Moving by no columns moves by one
+        if drawn > 0 { stdout.move_cursor_backward(drawn)?; }
+        stdout.clear_line_to_end()?;
+        stdout.print(&before)?;
+        stdout.print(&after)?;
+
+        let columns = column_after(&after, 1) - 1;
+        if columns > 0 { stdout.move_cursor_backward(columns)?; }
+        stdout.flush()
+    })?;
+
+    Ok(column_after(&before, 1) - 1)
+}
+
+// The sequences undoing the modes that are registered for cleanup once enabled.
+const SHOW_CURSOR: &str = "\x1b[?25h";
+const LEAVE_ALTERNATE_SCREEN: &str = "\x1b[?1049l";
+const DISABLE_MOUSE: &str = "\x1b[?1016l\x1b[?1006l\x1b[?1002l";
+const DISABLE_FOCUS: &str = "\x1b[?1004l";
+const DISABLE_PASTE: &str = "\x1b[?2004l";
+
+// The error given when input is needed but is not attached to a terminal.
+pub(crate) fn detached() -> IoError {
+    IoError::new(ErrorKind::NotConnected, "input is not attached to a terminal")
+}
+
+/// A guard holding the terminal echo setting chosen through [`StdinLock::echo_off`] or [`StdinLock::echo_on`].
+/// The previous setting is restored when the guard is dropped.
+pub struct EchoGuard<'a> {
+    config: Config<'a>,
+}
+
+impl ops::Deref for EchoGuard<'_> {
+    type Target = StdinLock;
+
+    fn deref(&self) -> &Self::Target {
+        self.config.lock
+    }
+}
+
+impl ops::DerefMut for EchoGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.config.lock
+    }
+}
+
+/// A guard keeping input raw, as chosen through [`StdinLock::raw_mode`].
+/// The previous settings are restored when the guard is dropped.
+pub struct RawModeGuard<'a> {
+    config: Config<'a>,
+}
+
+impl ops::Deref for RawModeGuard<'_> {
+    type Target = StdinLock;
+
+    fn deref(&self) -> &Self::Target {
+        self.config.lock
+    }
+}
+
+impl ops::DerefMut for RawModeGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.config.lock
+    }
+}
+
+/// A guard holding a title set through [`StdoutLock::push_title`].
+/// The previous title is restored when the guard is dropped.
+pub struct TitleGuard<'a> {
+    lock: &'a mut StdoutLock,
+}
+
+impl ops::Deref for TitleGuard<'_> {
+    type Target = StdoutLock;
+
+    fn deref(&self) -> &Self::Target {
+        self.lock
+    }
+}
+
+impl ops::DerefMut for TitleGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.lock
+    }
+}
+
+impl Drop for TitleGuard<'_> {
+    fn drop(&mut self) {
+        const POP_TITLE: &str = "\x1b[23;0t";
+        // Errors cannot be reported from here, and leave the title as it was set at worst
+        let _ = self.lock.print_escape(POP_TITLE).and_then(|_| self.lock.flush());
+    }
+}
+
+/// A guard holding mouse capture enabled through [`StdoutLock::capture_mouse`].
+/// Capture is disabled when the guard is dropped.
+pub struct MouseCaptureGuard<'a> {
+    lock: &'a mut StdoutLock,
+}
+
+impl ops::Deref for MouseCaptureGuard<'_> {
+    type Target = StdoutLock;
+
+    fn deref(&self) -> &Self::Target {
+        self.lock
+    }
+}
+
+impl ops::DerefMut for MouseCaptureGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.lock
+    }
+}
+
+impl Drop for MouseCaptureGuard<'_> {
+    fn drop(&mut self) {
+        // Errors cannot be reported from here, and leave mouse reports being sent at worst
+        let _ = self.lock.disable_mouse_capture().and_then(|_| self.lock.flush());
+    }
+}
+
+impl io::Read for StdinLock {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        match &mut self.source {
+            Source::Stdin(lock) => lock.read(buf),
+            Source::Tty(file) => (&**file).read(buf),
+        }
+    }
+}
+
+impl AsRawFd for StdinLock {
+    fn as_raw_fd(&self) -> RawFd {
+        match &self.source {
+            Source::Stdin(lock) => lock.as_raw_fd(),
+            Source::Tty(file) => file.as_raw_fd(),
+        }
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        match self {
+            Sink::Stdout(lock) => lock.write(buf),
+            Sink::Stderr(lock) => lock.write(buf),
+            Sink::Tty(file) => (&**file).write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        match self {
+            Sink::Stdout(lock) => lock.flush(),
+            Sink::Stderr(lock) => lock.flush(),
+            Sink::Tty(file) => (&**file).flush(),
+        }
+    }
+}
+
+impl AsRawFd for Sink {
+    fn as_raw_fd(&self) -> RawFd {
+        match self {
+            Sink::Stdout(lock) => lock.as_raw_fd(),
+            Sink::Stderr(lock) => lock.as_raw_fd(),
+            Sink::Tty(file) => file.as_raw_fd(),
+        }
+    }
+}
+
+impl Sink {
+    // Whether the sink is connected to a terminal.
+    fn is_terminal(&self) -> bool {
+        match self {
+            Sink::Stdout(lock) => lock.is_terminal(),
+            Sink::Stderr(lock) => lock.is_terminal(),
+            Sink::Tty(file) => file.is_terminal(),
+        }
+    }
+}
+
+/// A wrapper for the standard output lock.
+#[derive(Debug)]
+pub struct StdoutLock {
+    sink: Sink,
+    settings: Settings,
+    state: Arc<State>,
+    // Whether the writes are made within `StdoutLock::atomic`, which holds the output of the terminal already.
+    atomic: bool,
+}
+
+// Internal function for printing a string to the specified writer.
+fn print_<const LN: bool>(writer: &mut impl Write, settings: &Settings, str: &str) -> IoResult<()> {
+    writer.write_all(str.as_bytes())?;
+
+    if LN {
+        match settings.newline {
+            Newline::Lf => writer.write_all(b"\n"),
+            Newline::CrLf => writer.write_all(b"\r\n"),
+        }
+    } else if !settings.manual_flush {
+        writer.flush()
+    } else {
+        Ok(())
+    }
+}
+
+// Internal function for holding the output of the terminal while writing, unless it is held already.
+fn hold_output(state: &State, atomic: bool) -> Option<MutexGuard<'_, ()>> {
+    (!atomic).then(|| state.output.lock().unwrap_or_else(PoisonError::into_inner))
+}
+
+// Internal function for deciding whether colors should be written to the specified sink.
+fn colors_enabled(sink: &Sink, color: ColorChoice, dumb: bool) -> bool {
+    match color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => sink.is_terminal() && !dumb && env::var_os("NO_COLOR").is_none(),
+    }
+}
+
+// Generates the methods shared by the output locks, which control the cursor and screen of the
+// terminal they are written to. The lock needs `sink`, `settings` and `state` fields.
+macro_rules! output_methods {
+    () => {
+        /// Prints text wrapped on word boundaries to fit in `width` columns, or the width of the
+        /// terminal if [`None`] (80 columns if unknown). Existing newlines are kept, and words too long
+        /// for a line of their own are broken up. No newline is added after the last line.
+        pub fn print_wrapped(&mut self, text: &str, width: Option<usize>) -> IoResult<()> {
+            self.print_wrapped_indented(text, width, 0)
+        }
+
+        /// Prints text wrapped like [`StdoutLock::print_wrapped`], with every line after the first
+        /// of each paragraph indented by `indent` spaces, as in a hanging indent.
+        pub fn print_wrapped_indented(&mut self, text: &str, width: Option<usize>, indent: usize) -> IoResult<()> {
+            const FALLBACK_WIDTH: usize = 80;
+
+            let width = width.or_else(|| self.size().map(|(_, columns)| columns)).unwrap_or(FALLBACK_WIDTH);
+            let lines = wrap(text, width, indent);
+
+            // Every line but the last is ended with the configured line ending
+            let (last, lines) = lines.split_last().expect("wrapping gives at least one line");
+            for line in lines {
+                self.println(line)?;
+            }
+
+            self.print(last)
+        }
+
+        // Updates the tracked cursor position, if tracking is enabled,
+        // given the current position and the size of the terminal.
+        fn track(&self, update: impl FnOnce(Option<(usize, usize)>, Option<(usize, usize)>) -> Option<(usize, usize)>) {
+            if !self.settings.track_cursor { return; }
+
+            let mut cursor = self.state.cursor.lock().unwrap_or_else(PoisonError::into_inner);
+            *cursor = update(*cursor, size(&self.sink));
+        }
+
+        /// Gives where the cursor is thought to be, (`row`, `column`) starting from 1, without asking the terminal.
+        /// The position is followed through the moves and prints made with this lock and others taken
+        /// from the same terminal, once tracking is enabled with [`TerminalBuilder::track_cursor`].
+        /// It becomes known after [`StdoutLock::clear`] or [`StdoutLock::move_cursor`], and is lost
+        /// after writes it cannot follow, such as unrecognized escape sequences or invalid UTF-8.
+        /// Output echoed by the terminal is not accounted for.
+        ///
+        /// [`TerminalBuilder::track_cursor`]: crate::builder::TerminalBuilder::track_cursor
+        pub fn cursor_position_hint(&self) -> Option<(usize, usize)> {
+            if !self.settings.track_cursor { return None; }
+
+            let cursor = *self.state.cursor.lock().unwrap_or_else(PoisonError::into_inner);
+            let columns = size(&self.sink).map_or(usize::MAX, |(_, columns)| columns);
+
+            // A pending wrap leaves the cursor on the last column
+            cursor.map(|(row, column)| (row, column.min(columns)))
+        }
+
+        /// Makes the writes of `draw` as one, such as the prints redrawing a prompt, so writes made to
+        /// the same terminal from other threads meanwhile wait until it is done rather than landing in the
+        /// middle of an escape sequence. Other output locks of the terminal must not be written to from
+        /// within `draw`, as they would wait for it too.
+        pub fn atomic<T>(&mut self, draw: impl FnOnce(&mut Self) -> IoResult<T>) -> IoResult<T> {
+            let state = Arc::clone(&self.state);
+            let _output = hold_output(&state, self.atomic);
+
+            let atomic = std::mem::replace(&mut self.atomic, true);
+            let result = draw(self);
+            self.atomic = atomic;
+
+            result
+        }
+
+        // Prints an escape sequence, unless the terminal is too dumb to understand it.
+        // The cursor position is left for the caller to track.
+        fn print_escape(&mut self, sequence: &str) -> IoResult<()> {
+            match self.settings.dumb {
+                true => Ok(()),
+                false => {
+                    let _output = hold_output(&self.state, self.atomic);
+                    print_::<false>(&mut self.sink, &self.settings, sequence)
+                },
+            }
+        }
+
+        // Records that a mode undone by `undo` was enabled, to be undone by `Terminal::restore_all`
+        // or if the program panics.
+        fn enabled(&self, undo: &'static str) {
+            if !self.settings.dumb { cleanup::enabled(self.owner(), self.sink.as_raw_fd(), undo); }
+        }
+
+        // Records that the mode undone by `undo` was disabled.
+        fn disabled(&self, undo: &'static str) {
+            cleanup::disabled(self.owner(), self.sink.as_raw_fd(), undo);
+        }
+
+        // Tells the terminal this stream belongs to apart from others writing to the same descriptor.
+        fn owner(&self) -> cleanup::Owner {
+            Arc::as_ptr(&self.state) as cleanup::Owner
+        }
+
+        // Undoes the modes the terminal enabled through this stream, latest first.
+        fn restore_all(&mut self) -> IoResult<()> {
+            for undo in cleanup::take(self.owner(), self.sink.as_raw_fd()) {
+                self.print_escape(undo)?;
+            }
+
+            self.flush()
+        }
+
+        /// Clears the screen by sending an escape sequence.
+        pub fn clear(&mut self) -> IoResult<()> {
+            const CLEAR_SCREEN: &str = "\r\x1b[2J\r\x1b[H";
+
+            // Without escape sequences, the best that can be done is starting on a fresh line
+            match self.settings.dumb {
+                true => self.println(""),
+                false => {
+                    self.print_escape(CLEAR_SCREEN)?;
+                    self.track(|_, _| Some((1, 1)));
+                    Ok(())
+                },
+            }
+        }
+
+        /// Clears the screen by scrolling its content into the scrollback, where it can still be
+        /// scrolled back to, rather than erasing it like [`StdoutLock::clear`]. The cursor is moved
+        /// to the top-left corner. Only the rows up to the cursor's are scrolled away if its position
+        /// is tracked, and the whole screen otherwise.
+        pub fn clear_keep_scrollback(&mut self) -> IoResult<()> {
+            let rows = match (self.settings.dumb, self.size()) {
+                (false, Some((rows, _))) => rows,
+                // Without escape sequences or a known height, erasing is the best that can be done
+                _ => return self.clear(),
+            };
+
+            // Newlines written on the last row scroll the rows above it into the scrollback
+            let scrolled = self.cursor_position_hint().map_or(rows, |(row, _)| row);
+            let clear = format!("\x1b[{};1H{}\x1b[H\x1b[J", rows, "\n".repeat(scrolled));
+            self.print_escape(&clear)?;
+            self.track(|_, _| Some((1, 1)));
+            Ok(())
+        }
+
+        /// Erases the scrollback, leaving the screen as it is.
+        pub fn clear_scrollback(&mut self) -> IoResult<()> {
+            const CLEAR_SCROLLBACK: &str = "\x1b[3J";
+            self.print_escape(CLEAR_SCROLLBACK)
+        }
+
+        /// Clears the screen from the cursor position to the end of the screen.
+        pub fn clear_to_end(&mut self) -> IoResult<()> {
+            const CLEAR_TO_END: &str = "\x1b[J";
+            self.print_escape(CLEAR_TO_END)
+        }
+
+        /// Clears the screen from the beginning to the cursor position.
+        pub fn clear_to_beginning(&mut self) -> IoResult<()> {
+            const CLEAR_TO_BEGINNING: &str = "\x1b[1J";
+            self.print_escape(CLEAR_TO_BEGINNING)
+        }
+
+        /// Clears the current line from the cursor position to the end of the line.
+        pub fn clear_line_to_end(&mut self) -> IoResult<()> {
+            const CLEAR_LINE_TO_END: &str = "\x1b[K";
+            self.print_escape(CLEAR_LINE_TO_END)
+        }
+
+        /// Clears the current line from the beginning to the cursor position.
+        pub fn clear_line_to_beginning(&mut self) -> IoResult<()> {
+            const CLEAR_LINE_TO_BEGINNING: &str = "\x1b[1K";
+            self.print_escape(CLEAR_LINE_TO_BEGINNING)
+        }
+
+        /// Moves the cursor to the specified row and column.
+        pub fn move_cursor(&mut self, rows: usize, columns: usize) -> IoResult<()> {
+            let move_cursor = format!("\x1b[{};{}H", rows, columns);
+            self.print_escape(&move_cursor)?;
+            self.track(|_, _| Some((rows.max(1), columns.max(1))));
+            Ok(())
+        }
+
+        /// Moves the cursor up by a specified number of rows.
+        pub fn move_cursor_up(&mut self, rows: usize) -> IoResult<()> {
+            let move_up = format!("\x1b[{}A", rows);
+            self.print_escape(&move_up)?;
+            self.track(|position, _| position.map(|(row, column)| (row.saturating_sub(rows.max(1)).max(1), column)));
+            Ok(())
+        }
+
+        /// Moves the cursor down by a specified number of rows.
+        pub fn move_cursor_down(&mut self, rows: usize) -> IoResult<()> {
+            let move_down = format!("\x1b[{}B", rows);
+            self.print_escape(&move_down)?;
+            self.track(|position, size| position.map(|(row, column)| {
+                let height = size.map_or(usize::MAX, |(height, _)| height);
+                ((row + rows.max(1)).min(height), column)
+            }));
+            Ok(())
+        }
+
+        /// Moves the cursor forward (right) by a specified number of columns.
+        pub fn move_cursor_forward(&mut self, columns: usize) -> IoResult<()> {
+            let move_forward = format!("\x1b[{}C", columns);
+            self.print_escape(&move_forward)?;
+            self.track(|position, size| position.map(|(row, column)| {
+                let width = size.map_or(usize::MAX, |(_, width)| width);
+                (row, (column + columns.max(1)).min(width))
+            }));
+            Ok(())
+        }
+
+        /// Moves the cursor backward (left) by a specified number of columns.
+        pub fn move_cursor_backward(&mut self, columns: usize) -> IoResult<()> {
+            let move_backward = format!("\x1b[{}D", columns);
+            self.print_escape(&move_backward)?;
+            self.track(|position, _| position.map(|(row, column)| (row, column.saturating_sub(columns.max(1)).max(1))));
+            Ok(())
+        }
+
+        /// Moves the cursor to the specified column of the current line.
+        pub fn move_to_column(&mut self, column: usize) -> IoResult<()> {
+            let move_to_column = format!("\x1b[{}G", column);
+            self.print_escape(&move_to_column)?;
+            self.track(|position, size| position.map(|(row, _)| {
+                let width = size.map_or(usize::MAX, |(_, width)| width);
+                (row, column.max(1).min(width))
+            }));
+            Ok(())
+        }
+
+        /// Moves the cursor to the specified row, staying in the same column.
+        pub fn move_to_row(&mut self, row: usize) -> IoResult<()> {
+            let move_to_row = format!("\x1b[{}d", row);
+            self.print_escape(&move_to_row)?;
+            self.track(|position, size| position.map(|(_, column)| {
+                let height = size.map_or(usize::MAX, |(height, _)| height);
+                (row.max(1).min(height), column)
+            }));
+            Ok(())
+        }
+
+        /// Moves the cursor to the start of the line a specified number of rows down.
+        pub fn next_line(&mut self, rows: usize) -> IoResult<()> {
+            let next_line = format!("\x1b[{}E", rows);
+            self.print_escape(&next_line)?;
+            self.track(|position, size| position.map(|(row, _)| {
+                let height = size.map_or(usize::MAX, |(height, _)| height);
+                ((row + rows.max(1)).min(height), 1)
+            }));
+            Ok(())
+        }
+
+        /// Moves the cursor to the start of the line a specified number of rows up.
+        pub fn previous_line(&mut self, rows: usize) -> IoResult<()> {
+            let previous_line = format!("\x1b[{}F", rows);
+            self.print_escape(&previous_line)?;
+            self.track(|position, _| position.map(|(row, _)| (row.saturating_sub(rows.max(1)).max(1), 1)));
+            Ok(())
+        }
+
+        /// Moves the cursor forward past the columns `text` would take up if written at the cursor,
+        /// accounting for wide characters and tabs, without writing it.
+        /// Tab stops are measured from the tracked cursor position if known, or from the start of the line otherwise.
+        pub fn advance_cursor_by_text(&mut self, text: &str) -> IoResult<()> {
+            let start = self.cursor_position_hint().map_or(1, |(_, column)| column);
+            let columns = column_after(text, start) - start;
+
+            match columns {
+                0 => Ok(()),
+                columns => self.move_cursor_forward(columns),
+            }
+        }
+
+        /// Moves the cursor onto the character at `index` (counted in characters) of `text`,
+        /// written from the start of the current line, accounting for wide characters and tabs.
+        /// An `index` past the end of `text` places the cursor right after it.
+        pub fn move_cursor_to_column_for(&mut self, text: &str, index: usize) -> IoResult<()> {
+            let end = text.char_indices().nth(index).map_or(text.len(), |(end, _)| end);
+            let column = column_after(&text[..end], 1);
+
+            let move_to_column = format!("\x1b[{}G", column);
+            self.print_escape(&move_to_column)?;
+            self.track(|position, _| position.map(|(row, _)| (row, column)));
+            Ok(())
+        }
+
+        /// Hides the cursor in the terminal.
+        pub fn hide(&mut self) -> IoResult<()> {
+            const HIDE_CURSOR: &str = "\x1b[?25l";
+            self.print_escape(HIDE_CURSOR)?;
+            self.enabled(SHOW_CURSOR);
+            Ok(())
+        }
+
+        /// Shows the cursor in the terminal.
+        pub fn show(&mut self) -> IoResult<()> {
+            self.print_escape(SHOW_CURSOR)?;
+            self.disabled(SHOW_CURSOR);
+            Ok(())
+        }
+
+        /// Switches to the alternate screen, which has no scrollback, for full-screen applications.
+        /// The screen shown before is brought back as it was with [`StdoutLock::leave_alternate_screen`].
+        pub fn enter_alternate_screen(&mut self) -> IoResult<()> {
+            const ENTER_ALTERNATE_SCREEN: &str = "\x1b[?1049h";
+            self.print_escape(ENTER_ALTERNATE_SCREEN)?;
+            self.enabled(LEAVE_ALTERNATE_SCREEN);
+            Ok(())
+        }
+
+        /// Switches back from the alternate screen to the screen shown before it.
+        pub fn leave_alternate_screen(&mut self) -> IoResult<()> {
+            self.print_escape(LEAVE_ALTERNATE_SCREEN)?;
+            self.disabled(LEAVE_ALTERNATE_SCREEN);
+            Ok(())
+        }
+
+        /// Applies `style` to the text printed after it, until the style is reset with [`StdoutLock::reset_style`].
+        /// Nothing is written if styled output is disabled, as told by [`StdoutLock::colors_enabled`].
+        pub fn set_style(&mut self, style: &Style) -> IoResult<()> {
+            match self.colors_enabled() {
+                true => self.print_escape(&style.render()),
+                false => Ok(()),
+            }
+        }
+
+        /// Sets the color of the text printed after it, from the 16 named colors, the 256-color palette or truecolor.
+        /// Nothing is written if styled output is disabled.
+        pub fn set_foreground(&mut self, color: Color) -> IoResult<()> {
+            self.set_style(&Style::new().foreground(color))
+        }
+
+        /// Sets the color behind the text printed after it. Nothing is written if styled output is disabled.
+        pub fn set_background(&mut self, color: Color) -> IoResult<()> {
+            self.set_style(&Style::new().background(color))
+        }
+
+        /// Makes the text printed after it bold. Nothing is written if styled output is disabled.
+        pub fn bold(&mut self) -> IoResult<()> {
+            self.set_style(&Style::new().bold())
+        }
+
+        /// Resets every color and attribute set before. Nothing is written if styled output is disabled.
+        pub fn reset_style(&mut self) -> IoResult<()> {
+            match self.colors_enabled() {
+                true => self.print_escape(RESET),
+                false => Ok(()),
+            }
+        }
+
+        /// Gives the dimensions of the terminal, (`row`, `column`).
+        pub fn size(&self) -> Option<(usize, usize)> {
+            size(&self.sink)
+        }
+    };
+}
+
+impl StdoutLock {
+    /// Prints the specified string to the standard output.
+    pub fn print(&mut self, str: &str) -> IoResult<()> {
+        let output = hold_output(&self.state, self.atomic);
+        print_::<false>(&mut self.sink, &self.settings, str)?;
+        drop(output);
+        self.track(|position, size| cursor::advance(position?, size, str));
+        Ok(())
+    }
+
+    /// Prints the specified string to the standard output, followed by a newline character.
+    pub fn println(&mut self, str: &str) -> IoResult<()> {
+        let output = hold_output(&self.state, self.atomic);
+        print_::<true>(&mut self.sink, &self.settings, str)?;
+        drop(output);
+        self.track(|position, size| cursor::advance(position?, size, &[str, "\n"].concat()));
+        Ok(())
+    }
+
+    /// Whether styled output should be written to the standard output,
+    /// following the color choice the terminal was built with.
+    pub fn colors_enabled(&self) -> bool {
+        colors_enabled(&self.sink, self.settings.color, self.settings.dumb)
+    }
+
+    output_methods!();
+
+    /// Enables mouse capture, after which mouse reports are read as [`Event::Mouse`]
+    /// through [`StdinLock::read_event`]. Presses, releases, drags and scrolling are reported.
+    /// With [`MouseMode::Pixels`], positions are additionally reported in pixels where supported.
+    pub fn enable_mouse_capture(&mut self, mode: MouseMode) -> IoResult<()> {
+        const ENABLE_MOUSE: &str = "\x1b[?1002h\x1b[?1006h";
+        const ENABLE_PIXELS: &str = "\x1b[?1016h";
+
+        match mode {
+            MouseMode::Cells => self.print_escape(ENABLE_MOUSE)?,
+            MouseMode::Pixels => self.print_escape(&[ENABLE_MOUSE, ENABLE_PIXELS].concat())?,
+        }
+
+        self.state.mouse_pixels.store(mode == MouseMode::Pixels, Ordering::Relaxed);
+        self.enabled(DISABLE_MOUSE);
+        Ok(())
+    }
+
+    /// Enables mouse capture as [`StdoutLock::enable_mouse_capture`] does, until the returned guard is dropped.
+    /// Capture is also disabled if the program panics while the guard is held, before the panic is reported,
+    /// so a crash does not leave the terminal printing a sequence on every click.
+    pub fn capture_mouse(&mut self, mode: MouseMode) -> IoResult<MouseCaptureGuard<'_>> {
+        self.enable_mouse_capture(mode)?;
+        Ok(MouseCaptureGuard { lock: self })
+    }
+
+    /// Disables mouse capture, including pixel positions.
+    pub fn disable_mouse_capture(&mut self) -> IoResult<()> {
+        self.state.mouse_pixels.store(false, Ordering::Relaxed);
+        self.print_escape(DISABLE_MOUSE)?;
+        self.disabled(DISABLE_MOUSE);
+        Ok(())
+    }
+
+    /// Enables focus events, after which the window gaining and losing focus is read as
+    /// [`Event::FocusGained`] and [`Event::FocusLost`] through [`StdinLock::read_event`].
+    pub fn enable_focus_events(&mut self) -> IoResult<()> {
+        self.print_escape("\x1b[?1004h")?;
+        self.enabled(DISABLE_FOCUS);
+        Ok(())
+    }
+
+    /// Disables focus events.
+    pub fn disable_focus_events(&mut self) -> IoResult<()> {
+        self.print_escape(DISABLE_FOCUS)?;
+        self.disabled(DISABLE_FOCUS);
+        Ok(())
+    }
+
+    /// Enables bracketed paste, after which pasted text is read whole as [`Event::Paste`]
+    /// through [`StdinLock::read_event`], rather than as a key for each character.
+    pub fn enable_bracketed_paste(&mut self) -> IoResult<()> {
+        self.print_escape("\x1b[?2004h")?;
+        self.enabled(DISABLE_PASTE);
+        Ok(())
+    }
+
+    /// Disables bracketed paste.
+    pub fn disable_bracketed_paste(&mut self) -> IoResult<()> {
+        self.print_escape(DISABLE_PASTE)?;
+        self.disabled(DISABLE_PASTE);
+        Ok(())
+    }
+
+    /// Sets the title of the terminal window or tab (OSC 2). Control characters are left out,
+    /// as they would end the request early.
+    pub fn set_title(&mut self, title: &str) -> IoResult<()> {
+        let title: String = title.chars().filter(|char| !char.is_control()).collect();
+        self.print_escape(&format!("\x1b]2;{}\x07", title))
+    }
+
+    /// Saves the current title on the terminal's title stack and sets a new one, until the returned
+    /// guard is dropped and the saved title is restored. Tools nested in one another each restore
+    /// the title they found, rather than leaving theirs behind.
+    /// Writes can still be made through the guard. Terminals without a title stack keep the new title.
+    pub fn push_title(&mut self, title: &str) -> IoResult<TitleGuard<'_>> {
+        const PUSH_TITLE: &str = "\x1b[22;0t";
+
+        self.print_escape(PUSH_TITLE)?;
+        self.set_title(title)?;
+        Ok(TitleGuard { lock: self })
+    }
+
+    /// Rings the terminal bell (BEL), which terminals play as a sound, flash, or not at all, as configured.
+    pub fn bell(&mut self) -> IoResult<()> {
+        self.print("\x07")
+    }
+
+    /// Sets the volume of the bell rung by [`StdoutLock::bell`] (DECSWBV).
+    /// Terminals that do not support it ignore the request, keeping the volume they are configured with.
+    pub fn set_bell_volume(&mut self, volume: BellVolume) -> IoResult<()> {
+        self.print_escape(&format!("\x1b[{} t", volume.parameter()))
+    }
+
+    /// Sets the volume of the margin bell, rung as the cursor nears the right margin while typing (DECSMBV).
+    /// Terminals that do not support it ignore the request.
+    pub fn set_margin_bell_volume(&mut self, volume: BellVolume) -> IoResult<()> {
+        self.print_escape(&format!("\x1b[{} u", volume.parameter()))
+    }
+
+    /// Copies text to the system clipboard through the terminal (OSC 52).
+    /// Terminals that do not support or permit it ignore the request.
+    pub fn set_clipboard(&mut self, text: &str) -> IoResult<()> {
+        self.print_escape(&format!("\x1b]52;c;{}\x07", base64::encode(text.as_bytes())))
+    }
+
+    /// Asks the terminal where the cursor is (DSR), (`row`, `column`) starting from 1, waiting up to
+    /// `timeout` for the reply on `stdin`, which is read raw and unechoed meanwhile. Terminals that answer
+    /// device attribute queries end the wait as soon as they are done replying. Keys pressed while waiting
+    /// for the reply are discarded. The position is also taken as the tracked one, see
+    /// [`StdoutLock::cursor_position_hint`].
+    /// Fails with [`ErrorKind::TimedOut`] if no position is reported, as with terminals too dumb to answer.
+    pub fn cursor_position(&mut self, stdin: &mut StdinLock, timeout: Duration) -> IoResult<(usize, usize)> {
+        // The cursor position, followed by the primary device attributes
+        const QUERY: &str = "\x1b[6n\x1b[c";
+
+        let unreported = || IoError::new(ErrorKind::TimedOut, "terminal did not report the cursor position");
+        if self.settings.dumb { return Err(unreported()); }
+
+        let config = Config::set(stdin, false, &[Flag::NotCanonical, Flag::NotEcho])?;
+        self.print_escape(QUERY)?;
+
+        // Durations too long to add to the current time wait for as long as an `Instant` allows
+        let now = Instant::now();
+        let deadline = now.checked_add(timeout).unwrap_or_else(|| now + Duration::from_secs(u32::MAX.into()));
+
+        let (row, column) = read_cursor_report(config.lock, deadline)?.ok_or_else(unreported)?;
+        let position = (row.max(1) as usize, column.max(1) as usize);
+        self.track(|_, _| Some(position));
+
+        Ok(position)
+    }
+
+    /// Sets the dimensions of the terminal, (`row`, `column`).
+    /// This is meant for the master side of a pseudo-terminal, where the change is propagated
+    /// to the programs running on it; regular terminals usually ignore or reject it.
+    pub fn set_size(&self, rows: usize, columns: usize) -> IoResult<()> {
+        set_size(&self.sink, rows, columns)
+    }
+}
+
+impl Write for StdoutLock {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        let output = hold_output(&self.state, self.atomic);
+        let written = self.sink.write(buf)?;
+        drop(output);
+        self.track(|position, size| cursor::advance(position?, size, str::from_utf8(&buf[..written]).ok()?));
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.sink.flush()
+    }
+}
+
+impl AsRawFd for StdoutLock {
+    fn as_raw_fd(&self) -> RawFd {
+        self.sink.as_raw_fd()
+    }
+}
+
+/// A wrapper for the standard error lock.
+#[derive(Debug)]
+pub struct StderrLock {
+    sink: Sink,
+    settings: Settings,
+    state: Arc<State>,
+    // Whether the writes are made within `StderrLock::atomic`, which holds the output of the terminal already.
+    atomic: bool,
+}
+
+impl StderrLock {
+    /// Prints the specified string to the standard error stream.
+    pub fn print(&mut self, str: &str) -> IoResult<()> {
+        let output = hold_output(&self.state, self.atomic);
+        print_::<false>(&mut self.sink, &self.settings, str)?;
+        drop(output);
+        self.track(|position, size| cursor::advance(position?, size, str));
+        Ok(())
+    }
+
+    /// Prints the specified string to the standard error stream, followed by a newline character.
+    pub fn println(&mut self, str: &str) -> IoResult<()> {
+        let output = hold_output(&self.state, self.atomic);
+        print_::<true>(&mut self.sink, &self.settings, str)?;
+        drop(output);
+        self.track(|position, size| cursor::advance(position?, size, &[str, "\n"].concat()));
+        Ok(())
+    }
+
+    /// Whether styled output should be written to the standard error stream,
+    /// following the color choice the terminal was built with.
+    pub fn colors_enabled(&self) -> bool {
+        colors_enabled(&self.sink, self.settings.color, self.settings.dumb)
+    }
+
+    output_methods!();
+}
+
+impl Write for StderrLock {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        let output = hold_output(&self.state, self.atomic);
+        let written = self.sink.write(buf)?;
+        drop(output);
+        self.track(|position, size| cursor::advance(position?, size, str::from_utf8(&buf[..written]).ok()?));
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.sink.flush()
+    }
+}
+
+impl AsRawFd for StderrLock {
+    fn as_raw_fd(&self) -> RawFd {
+        self.sink.as_raw_fd()
+    }
+}
+
+/// The operations shared by the output locks, so that code drawing on the terminal can be written
+/// once for either stream. The locks offer the same methods directly, without the trait in scope.
+///
+/// ```rust,ignore
+/// use in_keys::streams::TerminalOutput;
+///
+/// fn draw_status(output: &mut impl TerminalOutput, status: &str) -> std::io::Result<()> {
+///     output.print("\r")?;
+///     output.clear_line_to_end()?;
+///     output.print(status)
+/// }
+/// ```
+pub trait TerminalOutput: Write {
+    /// Prints the specified string.
+    fn print(&mut self, str: &str) -> IoResult<()>;
+
+    /// Prints the specified string, followed by a newline character.
+    fn println(&mut self, str: &str) -> IoResult<()>;
+
+    /// Prints text wrapped on word boundaries, see [`StdoutLock::print_wrapped`].
+    fn print_wrapped(&mut self, text: &str, width: Option<usize>) -> IoResult<()>;
+
+    /// Whether styled output should be written, following the color choice the terminal was built with.
+    fn colors_enabled(&self) -> bool;
+
+    /// Gives where the cursor is thought to be, see [`StdoutLock::cursor_position_hint`].
+    fn cursor_position_hint(&self) -> Option<(usize, usize)>;
+
+    /// Clears the screen.
+    fn clear(&mut self) -> IoResult<()>;
+
+    /// Clears the screen from the cursor position to the end of the screen.
+    fn clear_to_end(&mut self) -> IoResult<()>;
+
+    /// Clears the screen from the beginning to the cursor position.
+    fn clear_to_beginning(&mut self) -> IoResult<()>;
+
+    /// Clears the current line from the cursor position to the end of the line.
+    fn clear_line_to_end(&mut self) -> IoResult<()>;
+
+    /// Clears the current line from the beginning to the cursor position.
+    fn clear_line_to_beginning(&mut self) -> IoResult<()>;
+
+    /// Clears the screen by scrolling its content into the scrollback.
+    fn clear_keep_scrollback(&mut self) -> IoResult<()>;
+
+    /// Erases the scrollback, leaving the screen as it is.
+    fn clear_scrollback(&mut self) -> IoResult<()>;
+
+    /// Moves the cursor to the specified row and column.
+    fn move_cursor(&mut self, rows: usize, columns: usize) -> IoResult<()>;
+
+    /// Moves the cursor up by a specified number of rows.
+    fn move_cursor_up(&mut self, rows: usize) -> IoResult<()>;
+
+    /// Moves the cursor down by a specified number of rows.
+    fn move_cursor_down(&mut self, rows: usize) -> IoResult<()>;
+
+    /// Moves the cursor forward (right) by a specified number of columns.
+    fn move_cursor_forward(&mut self, columns: usize) -> IoResult<()>;
+
+    /// Moves the cursor backward (left) by a specified number of columns.
+    fn move_cursor_backward(&mut self, columns: usize) -> IoResult<()>;
+
+    /// Moves the cursor to the specified column of the current line.
+    fn move_to_column(&mut self, column: usize) -> IoResult<()>;
+
+    /// Moves the cursor to the specified row, staying in the same column.
+    fn move_to_row(&mut self, row: usize) -> IoResult<()>;
+
+    /// Moves the cursor to the start of the line a specified number of rows down.
+    fn next_line(&mut self, rows: usize) -> IoResult<()>;
+
+    /// Moves the cursor to the start of the line a specified number of rows up.
+    fn previous_line(&mut self, rows: usize) -> IoResult<()>;
+
+    /// Hides the cursor.
+    fn hide(&mut self) -> IoResult<()>;
+
+    /// Shows the cursor.
+    fn show(&mut self) -> IoResult<()>;
+
+    /// Gives the dimensions of the terminal, (`row`, `column`).
+    fn size(&self) -> Option<(usize, usize)>;
+}
+
+// Implements `TerminalOutput` for an output lock by forwarding to its inherent methods.
+macro_rules! terminal_output {
+    ($lock:ty) => {
+        impl TerminalOutput for $lock {
+            fn print(&mut self, str: &str) -> IoResult<()> {
+                <$lock>::print(self, str)
+            }
+
+            fn println(&mut self, str: &str) -> IoResult<()> {
+                <$lock>::println(self, str)
+            }
+
+            fn print_wrapped(&mut self, text: &str, width: Option<usize>) -> IoResult<()> {
+                <$lock>::print_wrapped(self, text, width)
+            }
+
+            fn colors_enabled(&self) -> bool {
+                <$lock>::colors_enabled(self)
+            }
+
+            fn cursor_position_hint(&self) -> Option<(usize, usize)> {
+                <$lock>::cursor_position_hint(self)
+            }
+
+            fn clear(&mut self) -> IoResult<()> {
+                <$lock>::clear(self)
+            }
+
+            fn clear_to_end(&mut self) -> IoResult<()> {
+                <$lock>::clear_to_end(self)
+            }
+
+            fn clear_to_beginning(&mut self) -> IoResult<()> {
+                <$lock>::clear_to_beginning(self)
+            }
+
+            fn clear_line_to_end(&mut self) -> IoResult<()> {
+                <$lock>::clear_line_to_end(self)
+            }
+
+            fn clear_line_to_beginning(&mut self) -> IoResult<()> {
+                <$lock>::clear_line_to_beginning(self)
+            }
+
+            fn clear_keep_scrollback(&mut self) -> IoResult<()> {
+                <$lock>::clear_keep_scrollback(self)
+            }
+
+            fn clear_scrollback(&mut self) -> IoResult<()> {
+                <$lock>::clear_scrollback(self)
+            }
+
+            fn move_cursor(&mut self, rows: usize, columns: usize) -> IoResult<()> {
+                <$lock>::move_cursor(self, rows, columns)
+            }
+
+            fn move_cursor_up(&mut self, rows: usize) -> IoResult<()> {
+                <$lock>::move_cursor_up(self, rows)
+            }
+
+            fn move_cursor_down(&mut self, rows: usize) -> IoResult<()> {
+                <$lock>::move_cursor_down(self, rows)
+            }
+
+            fn move_cursor_forward(&mut self, columns: usize) -> IoResult<()> {
+                <$lock>::move_cursor_forward(self, columns)
+            }
+
+            fn move_cursor_backward(&mut self, columns: usize) -> IoResult<()> {
+                <$lock>::move_cursor_backward(self, columns)
+            }
+
+            fn move_to_column(&mut self, column: usize) -> IoResult<()> {
+                <$lock>::move_to_column(self, column)
+            }
+
+            fn move_to_row(&mut self, row: usize) -> IoResult<()> {
+                <$lock>::move_to_row(self, row)
+            }
+
+            fn next_line(&mut self, rows: usize) -> IoResult<()> {
+                <$lock>::next_line(self, rows)
+            }
+
+            fn previous_line(&mut self, rows: usize) -> IoResult<()> {
+                <$lock>::previous_line(self, rows)
+            }
+
+            fn hide(&mut self) -> IoResult<()> {
+                <$lock>::hide(self)
+            }
+
+            fn show(&mut self) -> IoResult<()> {
+                <$lock>::show(self)
+            }
+
+            fn size(&self) -> Option<(usize, usize)> {
+                <$lock>::size(self)
+            }
+        }
+    };
+}
+
+terminal_output!(StdoutLock);
+terminal_output!(StderrLock);
+
+/// A source of keys, so that code reading keys can be written once for the terminal and for fakes
+/// standing in for it in tests. A queue of keys is one such fake, giving its keys in order.
+///
+/// ```rust,ignore
+/// use std::collections::VecDeque;
+/// use in_keys::keys::Key;
+/// use in_keys::streams::KeySource;
+///
+/// fn confirm(keys: &mut impl KeySource) -> std::io::Result<bool> {
+///     Ok(keys.read_key(None)? == Some(Key::Char('y')))
+/// }
+///
+/// assert!(confirm(&mut VecDeque::from([Key::Char('y')]))?);
+/// ```
+pub trait KeySource {
+    /// Reads a key, waiting up to `timeout` for one, or for as long as it takes if [`None`].
+    /// Gives [`None`] if the timeout elapsed first.
+    fn read_key(&mut self, timeout: Option<Duration>) -> IoResult<Option<Key>>;
+}
+
+impl KeySource for StdinLock {
+    fn read_key(&mut self, timeout: Option<Duration>) -> IoResult<Option<Key>> {
+        match timeout {
+            Some(timeout) => self.read_key_or_timeout(timeout).map(Read::value),
+            None => StdinLock::read_key(self).map(Some),
+        }
+    }
+}
+
+impl KeySource for OwnedStdin {
+    fn read_key(&mut self, timeout: Option<Duration>) -> IoResult<Option<Key>> {
+        KeySource::read_key(&mut self.0, timeout)
+    }
+}
+
+/// Gives the queued keys in order. Once they run out, reads with a timeout time out, and reads
+/// without one give [`Key::Eof`], as the terminal does at the end of its input.
+impl KeySource for VecDeque<Key> {
+    fn read_key(&mut self, timeout: Option<Duration>) -> IoResult<Option<Key>> {
+        match (self.pop_front(), timeout) {
+            (None, None) => Ok(Some(Key::Eof)),
+            (key, _) => Ok(key),
+        }
+    }
+}
+
+// Modes left enabled are undone once the last terminal sharing the streams is gone.
+impl Drop for Streams {
+    fn drop(&mut self) {
+        // Errors cannot be reported from here, and leave the modes enabled at worst
+        let _ = self.restore_all();
+    }
+}
+
+impl Streams {
+    // Creates a new Streams instance with the standard input, output, and error streams.
+    pub(super) fn new() -> Self {
+        Streams::with(Settings::default(), true, false, PromptOutput::Stdout)
+    }
+
+    // Gives the Streams instance with the default settings, detecting the terminal only on the first call.
+    // The standard streams are process-wide, so every default `Terminal` can share the one instance.
+    pub(super) fn shared() -> Arc<Self> {
+        static SHARED: OnceLock<Arc<Streams>> = OnceLock::new();
+        Arc::clone(SHARED.get_or_init(|| Arc::new(Streams::new())))
+    }
+
+    // Creates a new Streams instance with the given settings.
+    // If `tty_fallback` is set and the standard input stream is not a terminal,
+    // the controlling terminal is opened to read input from instead.
+    // Prompts are drawn on the stream `prompt` names.
+    pub(super) fn with(settings: Settings, input: bool, tty_fallback: bool, prompt: PromptOutput) -> Self {
+        let stdin = match io::stdin() {
+            _ if !input => None,
+            stdin if stdin.is_terminal() => Some(Input::Stdin(stdin)),
+            _ if tty_fallback => File::options()
+                .read(true)
+                .write(true)
+                .open("/dev/tty")
+                .ok()
+                .map(Arc::new)
+                .map(Input::Tty),
+            _ => None,
+        };
+
+        Streams::stdio(settings, stdin, prompt)
+    }
+
+    // Creates a new Streams instance reading from a terminal device, such as `/dev/tty`,
+    // and writing to the standard output and error streams.
+    pub(super) fn from_input(settings: Settings, input: File, prompt: PromptOutput) -> Self {
+        Streams::stdio(settings, Some(Input::Tty(Arc::new(input))), prompt)
+    }
+
+    // Sends all regular and error output to `output`, such as `/dev/tty` or a pseudo-terminal,
+    // in place of the standard streams.
+    pub(super) fn with_output(mut self, output: File) -> Self {
+        self.output = Output::Tty(Arc::new(output));
+        self
+    }
+
+    // Creates a new Streams instance reading from `stdin`, if any, and writing to the standard streams.
+    fn stdio(settings: Settings, stdin: Option<Input>, prompt: PromptOutput) -> Self {
+        let output = Output::Stdio { stdout: io::stdout(), stderr: io::stderr() };
+        let prompt = match prompt {
+            PromptOutput::Stdout => Prompt::Stdout,
+            PromptOutput::Stderr => Prompt::Stderr,
+            PromptOutput::Tty => File::options()
+                .write(true)
+                .open("/dev/tty")
+                .map_or(Prompt::Stderr, |tty| Prompt::Tty(Arc::new(tty))),
+        };
+
+        Streams::assemble(settings, output, prompt, stdin)
+    }
+
+    // Creates a new Streams instance reading from and writing to a terminal device.
+    pub(super) fn from_tty(settings: Settings, tty: File) -> Self {
+        let tty = Arc::new(tty);
+        let output = Output::Tty(Arc::clone(&tty));
+        let stdin = Some(Input::Tty(tty));
+
+        Streams::assemble(settings, output, Prompt::Stdout, stdin)
+    }
+
+    // Creates a new Streams instance reading from one terminal device and writing to another,
+    // such as two handles to the master side of a pseudo-terminal.
+    pub(super) fn from_ttys(settings: Settings, input: File, output: File) -> Self {
+        let output = Output::Tty(Arc::new(output));
+        let stdin = Some(Input::Tty(Arc::new(input)));
+
+        Streams::assemble(settings, output, Prompt::Stdout, stdin)
+    }
+
+    // Creates a new Streams instance from its parts, which every other way of creating one goes through.
+    fn assemble(mut settings: Settings, output: Output, prompt: Prompt, stdin: Option<Input>) -> Self {
+        // Escape sequences are not understood by dumb terminals, or when no terminal type is given
+        settings.dumb = match env::var_os("TERM") {
+            Some(term) => term == "dumb",
+            None => true,
+        };
+
+        Streams { output, prompt, stdin, settings, state: Arc::default() }
+    }
+
+    // Locks the standard output stream, providing a controlled interface for writing.
+    pub(super) fn lock_stdout(&self) -> StdoutLock {
+        let sink = match &self.output {
+            Output::Stdio { stdout, .. } => Sink::Stdout(stdout.lock()),
+            Output::Tty(file) => Sink::Tty(Arc::clone(file)),
+        };
+
+        StdoutLock { sink, settings: self.settings, state: Arc::clone(&self.state), atomic: false }
+    }
+
+    // Locks the stream prompts are drawn on, providing the same interface as the standard output stream.
+    pub(super) fn lock_prompt(&self) -> StdoutLock {
+        let sink = match (&self.output, &self.prompt) {
+            (Output::Stdio { stderr, .. }, Prompt::Stderr) => Sink::Stderr(stderr.lock()),
+            (_, Prompt::Tty(file)) => Sink::Tty(Arc::clone(file)),
+            _ => return self.lock_stdout(),
+        };
+
+        StdoutLock { sink, settings: self.settings, state: Arc::clone(&self.state), atomic: false }
+    }
+
+    // Locks the standard error stream, providing a controlled interface for writing.
+    pub(super) fn lock_stderr(&self) -> StderrLock {
+        let sink = match &self.output {
+            Output::Stdio { stderr, .. } => Sink::Stderr(stderr.lock()),
+            Output::Tty(file) => Sink::Tty(Arc::clone(file)),
+        };
+
+        StderrLock { sink, settings: self.settings, state: Arc::clone(&self.state), atomic: false }
+    }
+
+    // Whether there is a terminal to read input from.
+    pub(super) fn has_input(&self) -> bool {
+        self.stdin.is_some()
+    }
+
+    // Attempts to lock the input stream if it is associated with a user-attended terminal,
+    // waiting for any other lock on it to be released.
+    pub(super) fn lock_stdin(&self) -> Option<StdinLock> {
+        let source = match self.stdin.as_ref()? {
+            Input::Stdin(stdin) => Source::Stdin(stdin.lock()),
+            Input::Tty(file) => Source::Tty(Arc::clone(file)),
+        };
+
+        // The standard library's lock is taken first, as locks taken without it never wait on it
+        let claim = Claim::take(&self.state, None);
+        Some(StdinLock { source, settings: self.settings, state: Arc::clone(&self.state), capture: None, _claim: claim })
+    }
+
+    // Locks the input stream over a duplicate of its descriptor, bypassing the standard library's lock,
+    // unless another lock on it is held.
+    pub(super) fn try_lock_stdin(&self) -> Option<StdinLock> {
+        self.lock_stdin_until(Instant::now())
+    }
+
+    // Locks the input stream as `try_lock_stdin` does, waiting up to `timeout` for another lock on it to be released.
+    pub(super) fn lock_stdin_timeout(&self, timeout: Duration) -> Option<StdinLock> {
+        // Durations too long to add to the current time wait for as long as an `Instant` allows
+        let now = Instant::now();
+        self.lock_stdin_until(now.checked_add(timeout).unwrap_or_else(|| now + Duration::from_secs(u32::MAX.into())))
+    }
+
+    // Locks the input stream over a duplicate of its descriptor, waiting until `deadline` for another lock on it to be released.
+    fn lock_stdin_until(&self, deadline: Instant) -> Option<StdinLock> {
+        let input = self.stdin.as_ref()?;
+        let claim = Claim::take(&self.state, Some(deadline))?;
+
+        let file = match input {
+            Input::Stdin(stdin) => Arc::new(stdin.as_fd().try_clone_to_owned().map(File::from).ok()?),
+            Input::Tty(file) => Arc::clone(file),
+        };
+
+        let source = Source::Tty(file);
+        Some(StdinLock { source, settings: self.settings, state: Arc::clone(&self.state), capture: None, _claim: Some(claim) })
+    }
+
+    // Gives the descriptor of the output stream, without locking it.
+    fn output_fd(&self) -> RawFd {
+        match &self.output {
+            Output::Stdio { stdout, .. } => stdout.as_raw_fd(),
+            Output::Tty(file) => file.as_raw_fd(),
+        }
+    }
+
+    // Has every terminal restored if the program is ended by a signal.
+    pub(super) fn restore_on_signals(&self) -> IoResult<()> {
+        cleanup::restore_on_signals()
+    }
+
+    // Registers a callback to be run with the new size of the terminal whenever it is resized.
+    pub(super) fn on_resize(self: &Arc<Self>, callback: Box<dyn FnMut(u16, u16) + Send>) -> IoResult<()> {
+        resize::register(self, callback)
+    }
+
+    // Starts delivering resizes as events to every reader of the terminal, waking those waiting for one.
+    pub(super) fn enable_resize_events(self: &Arc<Self>) -> IoResult<()> {
+        // Enabling them again changes nothing
+        if self.state.resizes.set(open_pipe()?).is_err() { return Ok(()); }
+
+        let state = Arc::clone(&self.state);
+        self.on_resize(Box::new(move |rows, columns| {
+            state.push_pending(Event::Resize(rows, columns));
+            if let Some((_, wake)) = state.resizes.get() {
+                // A full pipe already wakes readers
+                let _ = (&*wake).write(&[0]);
+            }
+        }))
+    }
+
+    // Undoes the modes enabled through any of the output streams, latest first.
+    pub(super) fn restore_all(&self) -> IoResult<()> {
+        self.lock_stdout().restore_all()?;
+        self.lock_stderr().restore_all()?;
+        self.lock_prompt().restore_all()
+    }
+
+    // Duplicates the descriptor of the input stream into an owned handle.
+    pub(super) fn owned_stdin(&self) -> IoResult<OwnedStdin> {
+        let file = match self.stdin.as_ref().ok_or_else(detached)? {
+            Input::Stdin(stdin) => stdin.as_fd().try_clone_to_owned().map(File::from)?,
+            Input::Tty(file) => file.try_clone()?,
+        };
+
+        let source = Source::Tty(Arc::new(file));
+        Ok(OwnedStdin(StdinLock { source, settings: self.settings, state: Arc::clone(&self.state), capture: None, _claim: None }))
     }
 }
 
@@ -0,0 +1,118 @@
+use std::{
+    io::{Error as IoError, Result as IoResult},
+    mem,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    ptr,
+};
+
+use crate::builder::PollBackend;
+
+// A way of waiting for descriptors to become ready.
+trait Poller {
+    // Waits up to `timeout` milliseconds, or for as long as it takes if negative, for any of `fds` to have
+    // the events asked for, filling in the events that happened as `poll` does. Gives how many are ready.
+    fn wait(&self, fds: &mut [libc::pollfd], timeout: i32) -> IoResult<usize>;
+}
+
+// Waits with `poll(2)`.
+struct Poll;
+// Waits with `select(2)`, which cannot tell hang-ups apart from input.
+struct Select;
+// Waits with an `epoll` instance made for each wait.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+struct Epoll;
+
+impl Poller for Poll {
+    fn wait(&self, fds: &mut [libc::pollfd], timeout: i32) -> IoResult<usize> {
+        // Safety: Count for `fds` is properly managed.
+        match unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) } {
+            ready if ready < 0 => Err(IoError::last_os_error()),
+            ready => Ok(ready as usize),
+        }
+    }
+}
+
+impl Poller for Select {
+    fn wait(&self, fds: &mut [libc::pollfd], timeout: i32) -> IoResult<usize> {
+        // The sets have room for a fixed number of descriptors
+        if fds.iter().any(|fd| !(0..libc::FD_SETSIZE as i32).contains(&fd.fd)) {
+            return Err(IoError::from_raw_os_error(libc::EINVAL));
+        }
+
+        // Safety: The sets are cleared before use and only hold descriptors within their size.
+        unsafe {
+            let mut read: libc::fd_set = mem::zeroed();
+            let mut write: libc::fd_set = mem::zeroed();
+            libc::FD_ZERO(&mut read);
+            libc::FD_ZERO(&mut write);
+
+            for fd in fds.iter() {
+                if fd.events & libc::POLLIN != 0 { libc::FD_SET(fd.fd, &mut read); }
+                if fd.events & libc::POLLOUT != 0 { libc::FD_SET(fd.fd, &mut write); }
+            }
+
+            let mut time = libc::timeval { tv_sec: (timeout / 1000).into(), tv_usec: (timeout % 1000 * 1000).into() };
+            let time: *mut libc::timeval = if timeout < 0 { ptr::null_mut() } else { &mut time };
+            let count = fds.iter().map(|fd| fd.fd + 1).max().unwrap_or(0);
+
+            if libc::select(count, &mut read, &mut write, ptr::null_mut(), time) < 0 {
+                return Err(IoError::last_os_error());
+            }
+
+            for fd in fds.iter_mut() {
+                fd.revents = 0;
+                if fd.events & libc::POLLIN != 0 && libc::FD_ISSET(fd.fd, &read) { fd.revents |= libc::POLLIN; }
+                if fd.events & libc::POLLOUT != 0 && libc::FD_ISSET(fd.fd, &write) { fd.revents |= libc::POLLOUT; }
+            }
+        }
+
+        Ok(fds.iter().filter(|fd| fd.revents != 0).count())
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl Poller for Epoll {
+    fn wait(&self, fds: &mut [libc::pollfd], timeout: i32) -> IoResult<usize> {
+        // Safety: The instance is owned and closed once done, and the events have room for every descriptor.
+        unsafe {
+            let epoll = libc::epoll_create1(libc::EPOLL_CLOEXEC);
+            if epoll < 0 { return Err(IoError::last_os_error()); }
+            let epoll = OwnedFd::from_raw_fd(epoll);
+
+            // The event bits of `epoll` are the ones of `poll` on Linux
+            for fd in fds.iter() {
+                let mut event = libc::epoll_event { events: fd.events as u32, u64: fd.fd as u64 };
+                if libc::epoll_ctl(epoll.as_raw_fd(), libc::EPOLL_CTL_ADD, fd.fd, &mut event) < 0 {
+                    // A descriptor listed twice is watched once
+                    let error = IoError::last_os_error();
+                    if error.raw_os_error() != Some(libc::EEXIST) { return Err(error); }
+                }
+            }
+
+            let mut events = vec![libc::epoll_event { events: 0, u64: 0 }; fds.len().max(1)];
+            let ready = libc::epoll_wait(epoll.as_raw_fd(), events.as_mut_ptr(), events.len() as i32, timeout);
+            if ready < 0 { return Err(IoError::last_os_error()); }
+
+            fds.iter_mut().for_each(|fd| fd.revents = 0);
+            for event in &events[..ready as usize] {
+                for fd in fds.iter_mut().filter(|fd| fd.fd as u64 == event.u64) {
+                    fd.revents = event.events as libc::c_short;
+                }
+            }
+        }
+
+        Ok(fds.iter().filter(|fd| fd.revents != 0).count())
+    }
+}
+
+// Waits for `fds` as `poll` does, with the chosen backend.
+pub(super) fn wait(backend: PollBackend, fds: &mut [libc::pollfd], timeout: i32) -> IoResult<usize> {
+    let poller: &dyn Poller = match backend {
+        PollBackend::Poll => &Poll,
+        PollBackend::Select => &Select,
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        PollBackend::Epoll => &Epoll,
+    };
+
+    poller.wait(fds, timeout)
+}
@@ -0,0 +1,95 @@
+use std::{
+    fs::File,
+    io::{ErrorKind, Read, Result as IoResult, Write},
+    mem,
+    os::fd::{AsRawFd, RawFd},
+    sync::{Mutex, OnceLock, PoisonError},
+    task::Waker,
+    thread,
+};
+
+use crate::builder::PollBackend;
+use crate::streams::{poller, unix::open_pipe};
+
+// The futures waiting for input, along with the descriptors any of which being ready wakes them.
+static WAITING: Mutex<Vec<(Vec<RawFd>, Waker)>> = Mutex::new(Vec::new());
+// The writing end of the pipe interrupting the thread's wait whenever a future starts waiting,
+// or the error of starting the thread, which only happens once.
+static THREAD: OnceLock<Result<File, i32>> = OnceLock::new();
+
+// Starts the thread waking the futures waiting for input, waiting with `backend`.
+fn start(backend: PollBackend) -> IoResult<File> {
+    let (reader, writer) = open_pipe()?;
+    thread::Builder::new().name("in-keys-reactor".into()).spawn(move || wait(reader, backend))?;
+
+    Ok(writer)
+}
+
+// Waits for any of the descriptors futures are waiting on to become ready, waking those futures.
+fn wait(mut reader: File, mut backend: PollBackend) {
+    let mut buffer = [0_u8; 64];
+
+    loop {
+        let waiting: Vec<RawFd> = WAITING
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .flat_map(|(fds, _)| fds.iter().copied())
+            .collect();
+
+        // The pipe comes first, so a future starting to wait is picked up right away
+        let mut fds: Vec<libc::pollfd> = [reader.as_raw_fd()]
+            .into_iter()
+            .chain(waiting)
+            .map(|fd| libc::pollfd { fd, events: libc::POLLIN, revents: 0 })
+            .collect();
+
+        match poller::wait(backend, &mut fds, -1) {
+            Ok(_) => {},
+            Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+            // The set cannot be waited on, such as for a descriptor closed under a dropped future,
+            // so every future is woken to find out on its next poll and waited on anew from then.
+            // Descriptors only `poll` takes, such as ones past the size of `select` sets, are left to it
+            Err(_) => {
+                backend = PollBackend::Poll;
+                let waiting = mem::take(&mut *WAITING.lock().unwrap_or_else(PoisonError::into_inner));
+                waiting.into_iter().for_each(|(_, waker)| waker.wake());
+                continue;
+            },
+        }
+
+        while reader.read(&mut buffer).is_ok_and(|read| read > 0) {}
+
+        // Hang-ups and errors wake futures too, for them to find out about on their next poll
+        let ready: Vec<RawFd> = fds[1..].iter().filter(|fd| fd.revents != 0).map(|fd| fd.fd).collect();
+        if ready.is_empty() { continue; }
+
+        let mut waiting = WAITING.lock().unwrap_or_else(PoisonError::into_inner);
+        waiting.retain(|(fds, waker)| match fds.iter().any(|fd| ready.contains(fd)) {
+            true => {
+                waker.wake_by_ref();
+                false
+            },
+            false => true,
+        });
+    }
+}
+
+// Has `waker` woken once any of `fds` is ready to be read, or right away if the thread waiting for
+// them cannot be started, leaving the future to be polled over and over instead.
+// The thread waits with the `backend` of the first future to start it.
+pub(super) fn wake_when_ready(fds: Vec<RawFd>, waker: &Waker, backend: PollBackend) {
+    let writer = match THREAD.get_or_init(|| start(backend).map_err(|error| error.raw_os_error().unwrap_or(libc::EINVAL))) {
+        Ok(writer) => writer,
+        Err(_) => return waker.wake_by_ref(),
+    };
+
+    let mut waiting = WAITING.lock().unwrap_or_else(PoisonError::into_inner);
+    // A future polled again before being woken is only waited on once
+    waiting.retain(|(other_fds, other)| !(other_fds == &fds && other.will_wake(waker)));
+    waiting.push((fds, waker.clone()));
+    drop(waiting);
+
+    // A full pipe already interrupts the wait
+    let _ = (&*writer).write(&[0]);
+}
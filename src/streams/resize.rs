@@ -0,0 +1,115 @@
+use std::{
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    slice,
+    sync::{Arc, Mutex, OnceLock, PoisonError, Weak, atomic::{AtomicI32, AtomicUsize, Ordering}},
+    thread,
+};
+
+use crate::builder::PollBackend;
+use crate::streams::{poller, unix::{size, with_errno_kept}, Streams};
+
+// A callback along with the streams whose size it is given, held weakly so registering a callback
+// does not keep them open. Callbacks are dropped along with their streams.
+type Callback = (Weak<Streams>, Box<dyn FnMut(u16, u16) + Send>);
+
+// The callbacks to run whenever the terminal is resized.
+static CALLBACKS: Mutex<Vec<Callback>> = Mutex::new(Vec::new());
+// The result of installing the signal handler, which only happens once.
+static INSTALLED: OnceLock<Result<(), i32>> = OnceLock::new();
+// The writing end of the pipe the signal handler wakes the callback thread through.
+static PIPE: AtomicI32 = AtomicI32::new(-1);
+// The handler that was installed before ours, called in turn so it keeps working.
+static PREVIOUS: AtomicUsize = AtomicUsize::new(libc::SIG_DFL);
+
+// Handles `SIGWINCH` by waking the callback thread. Only async-signal-safe calls are made.
+extern "C" fn handle(signal: libc::c_int) {
+    // Safety: Writing to a pipe is async-signal-safe.
+    with_errno_kept(|| unsafe { libc::write(PIPE.load(Ordering::Relaxed), [0_u8].as_ptr().cast(), 1); });
+
+    match PREVIOUS.load(Ordering::Relaxed) {
+        libc::SIG_DFL | libc::SIG_IGN => {},
+        // Safety: The previous handler was installed as a plain handler, not with `SA_SIGINFO`.
+        previous => unsafe { std::mem::transmute::<usize, extern "C" fn(libc::c_int)>(previous)(signal) },
+    }
+}
+
+// Creates the pipe, starts the thread running the callbacks, waiting with `backend`, and installs the signal handler.
+fn install(backend: PollBackend) -> IoResult<()> {
+    let mut fds = [0; 2];
+    // Safety: `fds` has room for both ends of the pipe.
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC | libc::O_NONBLOCK) } < 0 {
+        return Err(IoError::last_os_error());
+    }
+
+    let [reader, writer] = fds;
+    PIPE.store(writer, Ordering::Relaxed);
+
+    thread::Builder::new().name("in-keys-resize".into()).spawn(move || wait(reader, backend))?;
+
+    // Safety: The handler only makes async-signal-safe calls, and `action` is fully initialized.
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+
+        let mut previous: libc::sigaction = std::mem::zeroed();
+        if libc::sigaction(libc::SIGWINCH, &action, &mut previous) < 0 {
+            return Err(IoError::last_os_error());
+        }
+
+        if previous.sa_flags & libc::SA_SIGINFO == 0 {
+            PREVIOUS.store(previous.sa_sigaction, Ordering::Relaxed);
+        }
+    }
+
+    Ok(())
+}
+
+// Runs the callbacks each time the signal handler writes to the pipe.
+fn wait(reader: i32, mut backend: PollBackend) {
+    let mut fds = libc::pollfd { fd: reader, events: libc::POLLIN, revents: 0 };
+    let mut buffer = [0_u8; 64];
+
+    loop {
+        match poller::wait(backend, slice::from_mut(&mut fds), -1) {
+            Ok(_) => {},
+            Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+            // A pipe other backends cannot wait on, such as one past the size of `select` sets, is left to `poll`
+            Err(_) if backend != PollBackend::Poll => {
+                backend = PollBackend::Poll;
+                continue;
+            },
+            // Resizes can no longer be waited for, which retrying would not change
+            Err(_) => return,
+        }
+
+        // Several resizes in a row are handled once
+        // Safety: Valid `fd` and buffer, the pipe never being closed.
+        while unsafe { libc::read(reader, buffer.as_mut_ptr().cast(), buffer.len()) } > 0 {}
+
+        let mut callbacks = CALLBACKS.lock().unwrap_or_else(PoisonError::into_inner);
+        callbacks.retain_mut(|(streams, callback)| {
+            // The descriptor stays open while the streams holding it are kept alive
+            let Some(streams) = streams.upgrade() else { return false };
+            if let Some((rows, columns)) = size(&streams.output_fd()) {
+                callback(rows.min(u16::MAX as usize) as u16, columns.min(u16::MAX as usize) as u16);
+            }
+
+            true
+        });
+    }
+}
+
+// Registers a callback to be run with the new size of `streams` whenever the terminal is resized.
+// The thread running the callbacks waits with the backend of the first streams to register one.
+pub(super) fn register(streams: &Arc<Streams>, callback: Box<dyn FnMut(u16, u16) + Send>) -> IoResult<()> {
+    INSTALLED
+        .get_or_init(|| install(streams.settings.poll_backend).map_err(|error| error.raw_os_error().unwrap_or(libc::EINVAL)))
+        .map_err(IoError::from_raw_os_error)?;
+
+    let mut callbacks = CALLBACKS.lock().unwrap_or_else(PoisonError::into_inner);
+    callbacks.retain(|(streams, _)| streams.strong_count() > 0);
+    callbacks.push((Arc::downgrade(streams), callback));
+    Ok(())
+}
@@ -23,15 +23,22 @@
 // SOFTWARE.
 
 use std::{
+    fs::File,
     mem::MaybeUninit,
-    io::{StdoutLock, StdinLock, BufRead},
-    os::fd::AsRawFd,
-    io::{Error as IoError, ErrorKind, Result as IoResult},
+    slice,
+    os::fd::{AsRawFd, FromRawFd, RawFd},
+    io::{Error as IoError, ErrorKind, Read, Result as IoResult, Write},
+    sync::{Mutex, PoisonError, TryLockError, atomic::{AtomicU64, Ordering}},
+    time::{Duration, Instant},
 };
 
 use crate::{
-    keys::Key,
-    streams::config::Flag,
+    builder::{CtrlC, Settings},
+    events::{Event, MouseEvent, MouseMode},
+    keys::{Key, KeyEvent, KeyKind, Modifiers},
+    proxy::Intercept,
+    sequence::{parse_sequence, ControlSequence},
+    streams::{cleanup, config::Flag, poller, terminal_closed, StdinLock},
 };
 
 // Constant representing a successful system call result.
@@ -45,8 +52,25 @@ fn io_error(c_call: impl FnOnce() -> libc::c_int) -> IoResult<()> {
     }
 }
 
+// Runs `handle`, from within a signal handler, keeping `errno` as it was for the code the signal interrupted.
+pub(super) fn with_errno_kept(handle: impl FnOnce()) {
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "emscripten"))]
+    use libc::__errno_location as errno;
+    #[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd", target_os = "dragonfly"))]
+    use libc::__error as errno;
+    #[cfg(any(target_os = "netbsd", target_os = "openbsd"))]
+    use libc::__errno as errno;
+
+    // Reading the error does not allocate, as it only holds the number
+    let saved = IoError::last_os_error().raw_os_error().unwrap_or(0);
+    handle();
+
+    // Safety: The location of `errno` is valid for the current thread.
+    unsafe { *errno() = saved };
+}
+
 // Attains the window size of the terminal, in (`row`, `column`) notation.
-pub fn size(lock: &StdoutLock) -> Option<(usize, usize)> {
+pub fn size(lock: &impl AsRawFd) -> Option<(usize, usize)> {
     // Safety: `ioctl` is appropriately used.
     unsafe {
         let mut size: libc::winsize = std::mem::zeroed();
@@ -57,191 +81,1174 @@ pub fn size(lock: &StdoutLock) -> Option<(usize, usize)> {
     }
 }
 
+// Attains the size of a single cell of the terminal in pixels, in (`height`, `width`) notation.
+// Gives `None` if the terminal does not report its size in pixels.
+fn cell_size(lock: &impl AsRawFd) -> Option<(usize, usize)> {
+    // Safety: `ioctl` is appropriately used.
+    unsafe {
+        let mut size: libc::winsize = std::mem::zeroed();
+
+        #[allow(clippy::useless_conversion)]
+        libc::ioctl(lock.as_raw_fd(), libc::TIOCGWINSZ.into(), &mut size);
+        (size.ws_row > 0 && size.ws_col > 0 && size.ws_ypixel > 0 && size.ws_xpixel > 0).then(|| (
+            (size.ws_ypixel / size.ws_row) as usize,
+            (size.ws_xpixel / size.ws_col) as usize,
+        ))
+    }
+}
+
+// Sets the window size of the terminal, in (`row`, `column`) notation.
+// On the master side of a pseudo-terminal, this notifies the foreground process group with `SIGWINCH`.
+pub fn set_size(lock: &impl AsRawFd, rows: usize, columns: usize) -> IoResult<()> {
+    // Window sizes beyond the `u16` range cannot be represented
+    let invalid = || IoError::new(ErrorKind::InvalidInput, "window size out of range");
+    let size = libc::winsize {
+        ws_row: rows.try_into().map_err(|_| invalid())?,
+        ws_col: columns.try_into().map_err(|_| invalid())?,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+
+    // Safety: `ioctl` is appropriately used.
+    #[allow(clippy::useless_conversion)]
+    io_error(|| unsafe { libc::ioctl(lock.as_raw_fd(), libc::TIOCSWINSZ.into(), &size) })
+}
+
+// Opens a new pseudo-terminal, giving its (`master`, `slave`) sides. Neither is inherited by
+// programs spawned afterwards, unless passed to them explicitly.
+pub(crate) fn open_pty() -> IoResult<(File, File)> {
+    // Safety: The descriptor is checked before being owned, and the name buffer outlives its use.
+    unsafe {
+        let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC);
+        if master < 0 { return Err(IoError::last_os_error()); }
+        let master = File::from_raw_fd(master);
+
+        io_error(|| libc::grantpt(master.as_raw_fd()))?;
+        io_error(|| libc::unlockpt(master.as_raw_fd()))?;
+
+        // Find the path of the slave side, such as `/dev/pts/3`
+        let mut name = [0 as libc::c_char; 128];
+        io_error(|| libc::ptsname_r(master.as_raw_fd(), name.as_mut_ptr(), name.len()))?;
+
+        let slave = libc::open(name.as_ptr(), libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC);
+        if slave < 0 { return Err(IoError::last_os_error()); }
+
+        Ok((master, File::from_raw_fd(slave)))
+    }
+}
+
+// Opens a pipe, giving its (`reader`, `writer`) ends. Neither end blocks, nor is inherited by programs spawned afterwards.
+pub(crate) fn open_pipe() -> IoResult<(File, File)> {
+    let mut fds = [0; 2];
+    // Safety: `fds` has room for both ends of the pipe, which are owned once created.
+    unsafe {
+        io_error(|| libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC | libc::O_NONBLOCK))?;
+        Ok((File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])))
+    }
+}
+
+// Sets the input and output baud rate of a serial terminal device.
+// The receiver is enabled and modem control lines are ignored, as is usual for serial consoles.
+pub(crate) fn set_speed(device: &File, baud: u32) -> IoResult<()> {
+    // Map the rate onto the `termios` speed constants
+    let speed = match baud {
+        50 => libc::B50,
+        75 => libc::B75,
+        110 => libc::B110,
+        134 => libc::B134,
+        150 => libc::B150,
+        200 => libc::B200,
+        300 => libc::B300,
+        600 => libc::B600,
+        1200 => libc::B1200,
+        1800 => libc::B1800,
+        2400 => libc::B2400,
+        4800 => libc::B4800,
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        230400 => libc::B230400,
+        460800 => libc::B460800,
+        500000 => libc::B500000,
+        576000 => libc::B576000,
+        921600 => libc::B921600,
+        1000000 => libc::B1000000,
+        1152000 => libc::B1152000,
+        1500000 => libc::B1500000,
+        2000000 => libc::B2000000,
+        2500000 => libc::B2500000,
+        3000000 => libc::B3000000,
+        3500000 => libc::B3500000,
+        4000000 => libc::B4000000,
+        _ => return Err(IoError::new(ErrorKind::InvalidInput, "unsupported baud rate")),
+    };
+
+    // Safety: `termios` is properly handled
+    unsafe {
+        let mut termios = MaybeUninit::uninit();
+        io_error(|| libc::tcgetattr(device.as_raw_fd(), termios.as_mut_ptr()))?;
+
+        let mut termios = termios.assume_init();
+        io_error(|| libc::cfsetspeed(&mut termios, speed))?;
+        termios.c_cflag |= libc::CLOCAL | libc::CREAD;
+
+        io_error(|| libc::tcsetattr(device.as_raw_fd(), libc::TCSANOW, &termios))
+    }
+}
+
 // Polls the standard input stream for available input.
 // `timeout` is the time, in milliseconds, to wait for input. 0 is non-blocking and negative is forever blocking.
 // The returned `bool` indicating whether there is input available [`true`] or not [`false`].
-fn poll_input(lock: &StdinLock, timeout: i32) -> IoResult<bool> {
-    // Safety: Count for `fds` is properly managed.
-    unsafe {
-        let mut fds = libc::pollfd {
-            fd: lock.as_raw_fd(),  // Standard input file descriptor
-            events: libc::POLLIN,  // Interested in read events
-            revents: 0,            // Placeholder for returned events
-        };
+pub(super) fn poll_input(lock: &StdinLock, timeout: i32) -> IoResult<bool> {
+    // Bytes read ahead are available right away
+    if lock.state.has_unread() { return Ok(true); }
+
+    let mut fds = libc::pollfd {
+        fd: lock.as_raw_fd(),  // Standard input file descriptor
+        events: libc::POLLIN,  // Interested in read events
+        revents: 0,            // Placeholder for returned events
+    };
+
+    // Wait with the backend the terminal was built with
+    let start = Instant::now();
+    poller::wait(lock.settings.poll_backend, slice::from_mut(&mut fds), timeout)?;
+
+    // Check if POLLIN event occurred and return result
+    let ready = fds.revents & libc::POLLIN == libc::POLLIN;
+    lock.state.stats.record(start.elapsed(), ready);
 
-        // Call the `poll` system call, using a closure to pass the pointer to `fds`.
-        // The `min` function is used to ensure a successful result (>= 0) is always 0.
-        io_error(|| libc::poll(&mut fds as *mut _, 1, timeout).min(SUCCESS))?;
+    if let Some(error) = poll_error(fds.revents) { return Err(error); }
+    Ok(ready)
+}
+
+// Gives the error `poll` found on the input, if it has no input left to read: the terminal having
+// hung up, or the descriptor not being open, which would otherwise be reported as ready forever.
+fn poll_error(revents: libc::c_short) -> Option<IoError> {
+    match revents {
+        revents if revents & libc::POLLNVAL != 0 => Some(IoError::from_raw_os_error(libc::EBADF)),
+        revents if revents & libc::POLLIN != 0 => None,
+        revents if revents & (libc::POLLHUP | libc::POLLERR) != 0 => Some(terminal_closed()),
+        _ => None,
+    }
+}
+
+// Gives the error for a read that found no bytes, which is the terminal going away if it has hung up,
+// as reads in raw mode find nothing rather than fail once it has.
+fn end_of_input(lock: &StdinLock) -> IoError {
+    let mut fds = libc::pollfd { fd: lock.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+    let _ = poller::wait(lock.settings.poll_backend, slice::from_mut(&mut fds), 0);
 
-        // Check if POLLIN event occurred and return result
-        Ok(fds.revents & libc::POLLIN == libc::POLLIN)
+    match fds.revents & libc::POLLHUP {
+        0 => IoError::from(ErrorKind::UnexpectedEof),
+        _ => terminal_closed(),
+    }
+}
+
+// Tells the terminal going away apart from other failures of a call on it.
+fn hangup(error: IoError) -> IoError {
+    match error.raw_os_error() {
+        Some(libc::EIO | libc::ENXIO) => terminal_closed(),
+        _ => error,
     }
 }
 
 // Reads a fixed-size byte array from standard input, specified by a const-generic.
-// `_lock` refers to the `StdinLock` for correctness, and `timeout` is the timeout in milliseconds.
-// 0 is non-blocking and negative is forever blocking.
-// If input is available, an `IoResult` containing an `Option` of a byte array with size `N` is returned.
-// If no input is available within the specified timeout, `Ok(None)` is returned.
+// `timeout` is the time, in milliseconds, to wait for the first byte. 0 is non-blocking and negative is forever blocking.
+// If all `N` bytes are read, they are returned; if no input is available within the timeout,
+// or it stops short of `N` bytes, `Ok(None)` is returned, dropping whatever was read.
 fn read_bytes<const N: usize>(lock: &mut StdinLock, timeout: i32) -> IoResult<Option<[u8; N]>> {
+    Ok(read_up_to::<N>(lock, timeout)?.and_then(|(buffer, read)| (read == N).then_some(buffer)))
+}
+
+// Reads up to `N` bytes from standard input, giving them along with how many were read, the rest of the
+// array being zeroes. The kernel can hand over fewer bytes than asked for, such as when a sequence is split
+// between writes, so reads are repeated for as long as the rest arrives within the escape timeout.
+// `timeout` is the time, in milliseconds, to wait for the first byte, with `Ok(None)` returned if none arrives.
+fn read_up_to<const N: usize>(lock: &mut StdinLock, timeout: i32) -> IoResult<Option<([u8; N], usize)>> {
     // Check if input is available, return None if not
     if !poll_input(lock, timeout)? { return Ok(None); }
 
-    // Special case for zero-sized array, return filled array of zeros
-    if N == 0 { return Ok(Some([0; N])); }
-
     // Create a buffer to hold the read bytes
     let mut buffer = [0; N];
+    let mut filled = 0;
 
-    // Use unsafe Rust to call the `read` system call, populating the buffer
-    // Safety: Valid `fd` and buffer.
-    let read = unsafe { libc::read(lock.as_raw_fd(), buffer.as_mut_ptr().cast(), N) };
+    while filled < N {
+        // Only the first read is known to have input waiting for it
+        if filled > 0 && !poll_input(lock, lock.settings.escape_timeout_millis())? { break; }
+
+        // Bytes read ahead come first
+        let unread = lock.state.take_unread(N - filled);
+        buffer[filled..filled + unread.len()].copy_from_slice(&unread);
+
+        // Use unsafe Rust to call the `read` system call, populating the rest of the buffer
+        // Safety: Valid `fd`, and the buffer has room for `N - filled` more bytes.
+        let read = match unread.len() {
+            0 => unsafe { libc::read(lock.as_raw_fd(), buffer[filled..].as_mut_ptr().cast(), N - filled) },
+            read => read as isize,
+        };
+
+        match read {
+            ..0 => return Err(hangup(IoError::last_os_error())), // Return the error if the read failed
+            0 => return Err(end_of_input(lock)), // Return UnexpectedEof, or TerminalClosed after a hang-up, if no bytes were read
+            read => {
+                // Keep the bytes read if they are being captured
+                if let Some(capture) = &mut lock.capture {
+                    capture.extend_from_slice(&buffer[filled..filled + read as usize]);
+                }
 
-    // Match on the result of the read and the buffer contents
-    match (read, buffer) {
-        (0, _) => Err(IoError::from(ErrorKind::UnexpectedEof)), // Return UnexpectedEof if no bytes were read
-        (_, buffer) if buffer[0] == b'\x03' => Err(IoError::from(ErrorKind::Interrupted)), // Return Interrupted if Ctrl+C was pressed
-        (_, buffer) => Ok(Some(buffer)), // Return the read bytes
+                filled += read as usize;
+            },
+        }
     }
+
+    Ok(Some((buffer, filled)))
 }
 
 // This function processes the input received from the user.
-fn process_key(lock: &mut StdinLock, timeout: i32) -> IoResult<Option<Key>> {
-    // Try to read one byte from the input
-    match read_bytes::<1>(lock, timeout)? {
-        // If an escape character (0x1b) is received and there's more input available
-        Some([b'\x1b']) if poll_input(lock, 0)? => {
-            // Match on the next two bytes to determine special key combinations
-            let key = match read_bytes::<2>(lock, 0)? {
-                Some([b'[', b'A']) => return Ok(Some(Key::ArrowUp)),
-                Some([b'[', b'B']) => return Ok(Some(Key::ArrowDown)),
-                Some([b'[', b'C']) => return Ok(Some(Key::ArrowRight)),
-                Some([b'[', b'D']) => return Ok(Some(Key::ArrowLeft)),
-                Some([b'[', b'H']) => return Ok(Some(Key::Home)),
-                Some([b'[', b'F']) => return Ok(Some(Key::End)),
-                Some([b'[', b'Z']) => return Ok(Some(Key::BackTab)),
-                Some([b'[', b'1']) => Ok(Some(Key::Home)),
-                Some([b'[', b'2']) => Ok(Some(Key::Insert)),
-                Some([b'[', b'3']) => Ok(Some(Key::Del)),
-                Some([b'[', b'4']) => Ok(Some(Key::End)),
-                Some([b'[', b'5']) => Ok(Some(Key::PageUp)),
-                Some([b'[', b'6']) => Ok(Some(Key::PageDown)),
-                Some([b'[', b'7']) => Ok(Some(Key::Home)),
-                Some([b'[', b'8']) => Ok(Some(Key::End)),
-                _ => return Ok(Some(Key::Unknown)),
-            };
+fn process_event(lock: &mut StdinLock, timeout: i32) -> IoResult<Option<Event>> {
+    // Try to read one byte from the input, the end of input being read as a key of its own
+    let read = match read_bytes::<1>(lock, timeout) {
+        Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(Some(Key::Eof.into())),
+        read => read?,
+    };
 
-            // Check for a tilde (~) character indicating the end of an escape sequence
-            match read_bytes::<1>(lock, 0)? {
-                Some([b'~']) => key,
-                _ => Ok(Some(Key::Unknown)),
-            }
+    match read {
+        // If an escape character (0x1b) is received and there's more input available
+        Some([b'\x1b']) if poll_input(lock, lock.settings.escape_timeout_millis())? => {
+            process_sequence(lock).map(Some)
         },
         // If only an escape character (0x1b) is received
-        Some([b'\x1b']) => Ok(Some(Key::Escape)),
+        Some([b'\x1b']) => Ok(Some(Key::Escape.into())),
+        // If Ctrl+C is received and is not to be read as a key
+        Some([b'\x03']) if lock.settings.ctrl_c != CtrlC::Key => Err(IoError::from(ErrorKind::Interrupted)),
+        // If a byte starting a registered sequence is received
+        Some([byte]) if lock.state.starts_sequence(byte) => {
+            process_registered(lock, byte).map(Some)
+        },
         // If a byte other than an escape character is received
-        Some([byte]) => match byte {
-            // Handle UTF-8 multi-byte sequences
-            byte if byte & 224_u8 == 192_u8 => {
-                let Some([second]) = read_bytes::<1>(lock, 0)? else {
-                    return Ok(Some(Key::Unknown));
-                };
-
-                Ok(Some((&[byte, second][..]).into()))
-            },
-            byte if byte & 240_u8 == 224_u8 => {
-                let Some([second, third]) = read_bytes::<2>(lock, 0)? else {
-                    return Ok(Some(Key::Unknown));
-                };
+        Some([byte]) => process_byte(lock, byte).map(Event::from).map(Some),
+        // If no input is received
+        None => Ok(None),
+    }
+}
+
+// Keys sent as `ESC [ x` or `ESC O x`, by their final byte `x`, also sent as `ESC [ 1 ; m x`
+// when pressed along with modifiers.
+const FINAL_KEYS: &[(u8, Key)] = &[
+    (b'A', Key::ArrowUp),
+    (b'B', Key::ArrowDown),
+    (b'C', Key::ArrowRight),
+    (b'D', Key::ArrowLeft),
+    (b'H', Key::Home),
+    (b'F', Key::End),
+    (b'Z', Key::BackTab),
+    (b'P', Key::F(1)),
+    (b'Q', Key::F(2)),
+    (b'R', Key::F(3)),
+    (b'S', Key::F(4)),
+];
+
+// Keys sent as `ESC [ n ~`, by their parameter `n`.
+const TILDE_KEYS: &[(u16, Key)] = &[
+    (1, Key::Home),
+    (2, Key::Insert),
+    (3, Key::Del),
+    (4, Key::End),
+    (5, Key::PageUp),
+    (6, Key::PageDown),
+    (7, Key::Home),
+    (8, Key::End),
+    (11, Key::F(1)),
+    (12, Key::F(2)),
+    (13, Key::F(3)),
+    (14, Key::F(4)),
+    (15, Key::F(5)),
+    (17, Key::F(6)),
+    (18, Key::F(7)),
+    (19, Key::F(8)),
+    (20, Key::F(9)),
+    (21, Key::F(10)),
+    (23, Key::F(11)),
+    (24, Key::F(12)),
+];
+
+// Function keys F1 to F5 on the Linux console, sent as `ESC [ [ x`, by their final byte `x`.
+const CONSOLE_KEYS: &[(u8, Key)] = &[
+    (b'A', Key::F(1)),
+    (b'B', Key::F(2)),
+    (b'C', Key::F(3)),
+    (b'D', Key::F(4)),
+    (b'E', Key::F(5)),
+];
+
+// Looks `code` up in one of the tables above.
+fn lookup<T: PartialEq>(table: &[(T, Key)], code: T) -> Key {
+    table.iter().find(|(other, _)| *other == code).map_or(Key::Unknown, |(_, key)| *key)
+}
+
+// Reads on from `byte` while the bytes read could still make up one of the registered sequences,
+// giving the key event of the one they make up. Otherwise, the first byte is given as the key it is
+// on its own, and the keys of the others are kept aside for the next reads.
+fn process_registered(lock: &mut StdinLock, byte: u8) -> IoResult<Event> {
+    let registered = lock.state.sequences();
+    let mut bytes = vec![byte];
+
+    loop {
+        if let Some((_, event)) = registered.iter().find(|(sequence, _)| *sequence == bytes) {
+            return Ok(Event::Key(*event));
+        }
+
+        let longer = registered.iter().any(|(sequence, _)| sequence.len() > bytes.len() && sequence.starts_with(&bytes));
+        if !longer { break; }
+
+        let timeout = lock.settings.escape_timeout_millis();
+        let Some([next]) = read_bytes::<1>(lock, timeout)? else { break };
+        bytes.push(next);
+    }
 
-                Ok(Some((&[byte, second, third][..]).into()))
+    if bytes.len() == 1 { return process_byte(lock, byte).map(Event::from); }
+
+    // Escape sequences among the bytes are cut short, so their bytes are given as keys of their own
+    let text = String::from_utf8_lossy(&bytes);
+    let mut keys = text.chars().map(|char| match char {
+        '\x1b' => Key::Escape,
+        char if char.is_ascii() => ascii_key(&lock.settings, char as u8),
+        char => Key::Char(char),
+    });
+
+    let first = keys.next().unwrap_or(Key::Unknown);
+    keys.for_each(|key| lock.state.push_pending(key.into()));
+    Ok(first.into())
+}
+
+// Processes the escape sequence following an escape character, looking it up among the registered
+// sequences first. Those the built-in ones do not know of are read up to their end.
+fn process_sequence(lock: &mut StdinLock) -> IoResult<Event> {
+    if !lock.state.starts_sequence(b'\x1b') { return process_escape(lock); }
+    let registered = lock.state.sequences();
+
+    // The bytes read are captured for the lookup, and handed to any capture already in progress
+    let outer = lock.capture.replace(vec![b'\x1b']);
+    let event = match_sequence(lock, &registered);
+    let bytes = std::mem::replace(&mut lock.capture, outer).unwrap_or_default();
+    if let Some(outer) = &mut lock.capture { outer.extend_from_slice(&bytes[1..]); }
+
+    event
+}
+
+// Decodes the escape sequence being captured, reading on while it is unknown and could still become
+// one of the `registered` sequences.
+fn match_sequence(lock: &mut StdinLock, registered: &[(Vec<u8>, KeyEvent)]) -> IoResult<Event> {
+    let event = process_escape(lock)?;
+    let known = !matches!(event, Event::Key(KeyEvent { key: Key::Unknown, .. }));
+
+    loop {
+        let bytes = lock.capture.as_deref().unwrap_or_default();
+        if let Some((_, registered)) = registered.iter().find(|(sequence, _)| sequence == bytes) {
+            return Ok(Event::Key(*registered));
+        }
+
+        let longer = registered.iter().any(|(sequence, _)| sequence.len() > bytes.len() && sequence.starts_with(bytes));
+        if known || !longer { return Ok(event); }
+
+        let timeout = lock.settings.escape_timeout_millis();
+        if read_bytes::<1>(lock, timeout)?.is_none() { return Ok(event); }
+    }
+}
+
+// This function processes the escape sequence following an escape character.
+fn process_escape(lock: &mut StdinLock) -> IoResult<Event> {
+    match read_bytes::<1>(lock, 0)? {
+        Some([b'[']) => match read_bytes::<1>(lock, 0)? {
+            Some([b'<']) => process_mouse(lock),
+            Some([b'I']) => Ok(Event::FocusGained),
+            Some([b'O']) => Ok(Event::FocusLost),
+            Some([digit @ b'0'..=b'9']) => process_csi(lock, digit),
+            Some([b'[']) => match read_bytes::<1>(lock, 0)? {
+                Some([letter]) => Ok(lookup(CONSOLE_KEYS, letter).into()),
+                None => Ok(Key::Unknown.into()),
             },
-            byte if byte & 248u8 == 240u8 => {
-                let Some([second, third, fourth]) = read_bytes::<3>(lock, 0)? else {
-                    return Ok(Some(Key::Unknown));
-                };
+            Some([letter]) => Ok(lookup(FINAL_KEYS, letter).into()),
+            None => Ok(alt(Key::Char('['))),
+        },
+        // Keys sent in application cursor mode, `ESC O A` for example, and function keys F1 to F4
+        Some([b'O']) => match read_bytes::<1>(lock, 0)? {
+            Some([letter]) => Ok(lookup(FINAL_KEYS, letter).into()),
+            None => Ok(alt(Key::Char('O'))),
+        },
+        // Keys pressed along with Alt are sent prefixed with an escape character
+        Some([byte]) if byte != b'\x1b' => process_byte(lock, byte).map(alt),
+        _ => Ok(Key::Unknown.into()),
+    }
+}
+
+// Gives the event of `key` pressed along with Alt.
+fn alt(key: Key) -> Event {
+    Event::Key(KeyEvent { modifiers: Modifiers { alt: true, ..Modifiers::default() }, ..key.into() })
+}
 
-                Ok(Some((&[byte, second, third, fourth][..]).into()))
+// Processes a control sequence with numeric parameters, `ESC [ 1 ; 5 C` (Ctrl+Right) for example,
+// starting from its first parameter byte. The second parameter, if any, holds the modifiers.
+fn process_csi(lock: &mut StdinLock, first: u8) -> IoResult<Event> {
+    // Longer sequences are not keys, and are given up on
+    const LIMIT: usize = 16;
+
+    // Gather the sequence up to its final byte
+    let mut sequence = vec![b'\x1b', b'[', first];
+    loop {
+        match read_bytes::<1>(lock, 0)? {
+            Some([byte]) if sequence.len() < LIMIT => {
+                sequence.push(byte);
+                if (0x40..=0x7e).contains(&byte) { break; }
             },
-            // Handle special control characters
-            b'\n' | b'\r' => Ok(Some(Key::Enter)),
-            b'\x7f' => Ok(Some(Key::Backspace)),
-            b'\t' => Ok(Some(Key::Tab)),
-            b'\x01' => Ok(Some(Key::Home)),
-            b'\x05' => Ok(Some(Key::End)),
-            b'\x08' => Ok(Some(Key::Backspace)),
-            // Handle regular printable characters
-            byte => Ok(Some(Key::Char(byte as char))),
+            _ => return Ok(Key::Unknown.into()),
+        }
+    }
+
+    let ControlSequence::Csi { private: None, parameters, last, .. } = parse_sequence(&sequence) else {
+        return Ok(Key::Unknown.into());
+    };
+
+    if let (b'~', [200]) = (last, &parameters[..]) { return read_paste(lock); }
+
+    let key = match (last, parameters.first().copied().unwrap_or_default()) {
+        (b'~', number) => lookup(TILDE_KEYS, number),
+        (_, 1) => lookup(FINAL_KEYS, last),
+        _ => Key::Unknown,
+    };
+
+    let modifiers = parameters.get(1).copied().map(Modifiers::from_parameter).unwrap_or_default();
+    Ok(Event::Key(KeyEvent { modifiers, ..key.into() }))
+}
+
+// Reads text pasted while bracketed paste is enabled, following its `ESC [ 200 ~` introducer, up to the
+// `ESC [ 201 ~` ending it. A paste cut short, with the input going quiet for a while, gives what arrived.
+fn read_paste(lock: &mut StdinLock) -> IoResult<Event> {
+    const END: &[u8] = b"\x1b[201~";
+    // Pastes arrive in one go, so a pause this long means the end was lost
+    const TIMEOUT: i32 = 1000;
+
+    let mut text = Vec::new();
+    while !text.ends_with(END) {
+        let Some([byte]) = read_bytes::<1>(lock, TIMEOUT)? else { break };
+        text.push(byte);
+    }
+
+    if text.ends_with(END) { text.truncate(text.len() - END.len()); }
+    Ok(Event::Paste(String::from_utf8_lossy(&text).into_owned()))
+}
+
+// This function processes an SGR mouse report, following its `ESC [ <` introducer.
+fn process_mouse(lock: &mut StdinLock) -> IoResult<Event> {
+    // Reports are short; anything longer is treated as garbage
+    const MAX_REPORT: usize = 32;
+    let mut body = Vec::with_capacity(MAX_REPORT);
+
+    // Collect bytes up to and including the final `M` or `m`
+    while body.len() < MAX_REPORT {
+        let Some([byte]) = read_bytes::<1>(lock, 0)? else { break };
+        body.push(byte);
+
+        if byte == b'M' || byte == b'm' { break; }
+    }
+
+    let mode = lock.state.mouse_mode();
+    let cell = match mode {
+        MouseMode::Pixels => cell_size(lock),
+        MouseMode::Cells => None,
+    };
+
+    Ok(MouseEvent::from_sgr(&body, mode, cell).map_or(Key::Unknown.into(), Event::Mouse))
+}
+
+// This function processes a byte other than an escape character.
+fn process_byte(lock: &mut StdinLock, byte: u8) -> IoResult<Key> {
+    match byte {
+        // Handle UTF-8 multi-byte sequences
+        byte if byte & 224_u8 == 192_u8 => {
+            let Some([second]) = read_bytes::<1>(lock, 0)? else {
+                return Ok(Key::Unknown);
+            };
+
+            Ok((&[byte, second][..]).into())
         },
-        // If no input is received
-        None => Ok(None),
+        byte if byte & 240_u8 == 224_u8 => {
+            let Some([second, third]) = read_bytes::<2>(lock, 0)? else {
+                return Ok(Key::Unknown);
+            };
+
+            Ok((&[byte, second, third][..]).into())
+        },
+        byte if byte & 248u8 == 240u8 => {
+            let Some([second, third, fourth]) = read_bytes::<3>(lock, 0)? else {
+                return Ok(Key::Unknown);
+            };
+
+            Ok((&[byte, second, third, fourth][..]).into())
+        },
+        byte => Ok(ascii_key(&lock.settings, byte)),
+    }
+}
+
+// Gives the key of a single-byte character.
+fn ascii_key(settings: &Settings, byte: u8) -> Key {
+    match byte {
+        // Handle special control characters
+        b'\n' | b'\r' => Key::Enter,
+        b'\x7f' => Key::Backspace,
+        b'\t' => Key::Tab,
+        b'\x01' if !settings.ctrl_home_end => Key::Home,
+        b'\x05' if !settings.ctrl_home_end => Key::End,
+        b'\x08' => Key::Backspace,
+        b'\x04' => Key::Eof,
+        // Ctrl with a letter clears the upper bits of the letter
+        b'\x01'..=b'\x1a' => Key::Ctrl((b'a' + byte - 1) as char),
+        // Handle regular printable characters
+        byte => Key::Char(byte as char),
     }
 }
 
 // This function reads a single key from the terminal input.
+// Events other than keys, such as stray mouse reports, are given as `Key::Unknown`.
 pub(super) fn read_key(lock: &mut StdinLock, timeout: i32) -> IoResult<Option<Key>> {
-    process_key(lock, timeout)
+    // Events left over from an earlier read come first
+    let event = match lock.state.pop_pending() {
+        Some(event) => Some(event),
+        None => loop {
+            match process_event(lock, timeout)? {
+                Some(event) if is_bounce(lock, &event) => continue,
+                event => break event,
+            }
+        },
+    };
+
+    if let Some(event) = &event { lock.state.record(event); }
+    Ok(event.map(|event| match event {
+        Event::Key(event) => event.key,
+        _ => Key::Unknown,
+    }))
 }
 
-// This function reads a line of characters from the terminal input.
-pub(super) fn read_string(lock: &mut StdinLock, timeout: i32) -> IoResult<Option<String>> {
-    if poll_input(lock, timeout)? {
-        let mut buffer = String::new();
-        lock.read_line(&mut buffer)?;
+// This function reads a single event from the terminal input.
+pub(super) fn read_event(lock: &mut StdinLock, timeout: i32) -> IoResult<Option<Event>> {
+    // Events left over from an earlier read come first, as do resizes
+    let event = loop {
+        if let Some(event) = lock.state.pop_pending() { break Some(event); }
 
-        Ok(Some(buffer))
+        if wait_event(lock, timeout)? {
+            break match process_event(lock, 0)? {
+                Some(event) if is_bounce(lock, &event) => continue,
+                Some(event) if lock.settings.coalesce_navigation => Some(coalesce(lock, event)?),
+                event => event,
+            };
+        }
+
+        // Woken by a resize, which another reader may have taken first
+        if timeout >= 0 { break lock.state.pop_pending(); }
+    };
+
+    let event = match (event, lock.settings.repeat_threshold) {
+        (Some(Event::Key(key)), Some(threshold)) => Some(Event::Key(detect_repeat(lock, key, threshold))),
+        (event, _) => event,
+    };
+
+    if let Some(event) = &event { lock.state.record(event); }
+    Ok(event)
+}
+
+// Waits up to `timeout` milliseconds for input, like `poll_input`, but also wakes up once a resize
+// event is kept aside, if resize events are enabled. Gives whether input is available.
+fn wait_event(lock: &StdinLock, timeout: i32) -> IoResult<bool> {
+    let Some((wake, _)) = lock.state.resizes.get() else { return poll_input(lock, timeout) };
+    if lock.state.has_unread() { return Ok(true); }
+    let mut fds = [
+        libc::pollfd { fd: lock.as_raw_fd(), events: libc::POLLIN, revents: 0 },
+        libc::pollfd { fd: wake.as_raw_fd(), events: libc::POLLIN, revents: 0 },
+    ];
+
+    let start = Instant::now();
+    match poller::wait(lock.settings.poll_backend, &mut fds, timeout) {
+        // The resize signal itself can interrupt the wait, which is then a wake-up like any other
+        Err(error) if error.kind() == ErrorKind::Interrupted => {},
+        result => { result?; },
+    }
+
+    let ready = fds[0].revents & libc::POLLIN == libc::POLLIN;
+    lock.state.stats.record(start.elapsed(), ready);
+    if let Some(error) = poll_error(fds[0].revents) { return Err(error); }
+
+    // Several resizes in a row wake once
+    let mut buffer = [0_u8; 64];
+    while (&*wake).read(&mut buffer).is_ok_and(|read| read > 0) {}
+
+    Ok(ready)
+}
+
+// This function merges the identical navigation keys already queued after `event` into it.
+// The first differing event is kept aside for the next read.
+fn coalesce(lock: &mut StdinLock, mut event: Event) -> IoResult<Event> {
+    let Event::Key(KeyEvent { key, repeat, modifiers, .. }) = &mut event else { return Ok(event) };
+    let scrolls = matches!(
+        key,
+        Key::ArrowUp | Key::ArrowDown | Key::ArrowLeft | Key::ArrowRight | Key::PageUp | Key::PageDown
+    );
+
+    // Only consume what is already queued, never waiting for more
+    while scrolls && *repeat < u16::MAX && poll_input(lock, 0)? {
+        match process_event(lock, 0)? {
+            Some(Event::Key(next)) if (next.key, next.modifiers) == (*key, *modifiers) => {
+                *repeat = repeat.saturating_add(next.repeat)
+            },
+            Some(next) => {
+                lock.state.push_pending(next);
+                break;
+            },
+            None => break,
+        }
+    }
+
+    Ok(event)
+}
+
+// Whether `event` is a key event identical to the one kept last, less than the debounce window ago,
+// and should be dropped. Events kept are remembered for the next ones to be compared against.
+fn is_bounce(lock: &StdinLock, event: &Event) -> bool {
+    let (Some(window), Event::Key(event)) = (lock.settings.debounce, event) else { return false };
+    let now = Instant::now();
+    let mut last = lock.state.last_kept.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let same = |kept: &KeyEvent| kept.key == event.key && kept.modifiers == event.modifiers;
+    if matches!(&*last, Some((kept, at)) if same(kept) && now.duration_since(*at) < window) { return true; }
+
+    *last = Some((*event, now));
+    false
+}
+
+// Marks a key event as a repeat if the same key was last read less than `threshold` ago,
+// as happens when a key is held down on terminals that only report presses.
+fn detect_repeat(lock: &mut StdinLock, mut event: KeyEvent, threshold: Duration) -> KeyEvent {
+    let now = Instant::now();
+    let mut last = lock.state.last_press.lock().unwrap_or_else(PoisonError::into_inner);
+
+    if matches!(*last, Some((key, at)) if key == event.key && now.duration_since(at) < threshold) {
+        event.kind = KeyKind::Repeat;
+    }
+
+    *last = Some((event.key, now));
+    event
+}
+
+// This function reads whatever bytes are available in a single `read`, such as one key's sequence.
+pub(super) fn read_available(lock: &mut StdinLock, timeout: i32) -> IoResult<Option<Vec<u8>>> {
+    if !poll_input(lock, timeout)? { return Ok(None); }
+
+    // Bytes read ahead come first
+    let unread = lock.state.take_unread(64);
+    if !unread.is_empty() { return Ok(Some(unread)); }
+
+    let mut buffer = [0_u8; 64];
+    // Safety: Valid `fd` and buffer.
+    let read = unsafe { libc::read(lock.as_raw_fd(), buffer.as_mut_ptr().cast(), buffer.len()) };
+
+    match read {
+        read if read < 0 => Err(hangup(IoError::last_os_error())),
+        0 => Err(end_of_input(lock)),
+        read => Ok(Some(buffer[..read as usize].to_vec())),
+    }
+}
+
+// Reads the reply to an OSC 52 clipboard query, `ESC ] 52 ; selection ; data` ended by BEL or ST,
+// giving the base64 `data`. Input before the reply is discarded. `Ok(None)` is returned if no
+// reply arrives before `deadline`, and an error if the reply grows beyond `limit` bytes.
+pub(super) fn read_clipboard(lock: &mut StdinLock, deadline: Instant, limit: usize) -> IoResult<Option<Vec<u8>>> {
+    const START: &[u8] = b"\x1b]52;";
+
+    let mut reply = Vec::new();
+    loop {
+        // Give up once the deadline passes, clamping the remaining time to what `poll` accepts
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let timeout = remaining.as_millis().min(i32::MAX as u128) as i32;
+        match read_available(lock, timeout)? {
+            Some(bytes) => reply.extend(bytes),
+            None => return Ok(None),
+        }
+
+        // Drop whatever came before the start of the reply
+        match reply.windows(START.len()).position(|window| window == START) {
+            Some(start) => { reply.drain(..start); },
+            None => {
+                reply.drain(..reply.len().saturating_sub(START.len() - 1));
+                continue;
+            },
+        }
+
+        if reply.len() > limit {
+            return Err(IoError::new(ErrorKind::InvalidData, "clipboard reply exceeds the size limit"));
+        }
+
+        // The reply ends with BEL or with ST (`ESC \`)
+        if let ControlSequence::Osc(payload) = parse_sequence(&reply) {
+            // Skip the command and selection, which are followed by the data
+            let data = payload.splitn(3, |&byte| byte == b';').nth(2).unwrap_or_default();
+            return Ok(Some(data.to_vec()));
+        }
+    }
+}
+
+// Reads the replies to queries written ahead of a primary device attributes query, handing each
+// control sequence to `reply`. Input around the replies is discarded. Stops once the device attributes
+// reply arrives, as terminals reply in order, or once `deadline` passes, giving whether the replies ended.
+fn read_replies(lock: &mut StdinLock, deadline: Instant, mut reply: impl FnMut(ControlSequence)) -> IoResult<bool> {
+    let mut input = Vec::new();
+
+    loop {
+        // Give up once the deadline passes, clamping the remaining time to what `poll` accepts
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let timeout = remaining.as_millis().min(i32::MAX as u128) as i32;
+        match read_available(lock, timeout)? {
+            Some(bytes) => input.extend(bytes),
+            None => return Ok(false),
+        }
+
+        // Go through the sequences read so far, keeping an incomplete one for the next read
+        while let Some(start) = input.iter().position(|&byte| byte == b'\x1b') {
+            input.drain(..start);
+
+            match parse_sequence(&input) {
+                ControlSequence::Incomplete => break,
+                ControlSequence::Csi { private: Some(b'?'), last: b'c', .. } => return Ok(true),
+                sequence => reply(sequence),
+            }
+
+            input.remove(0);
+        }
+
+        if input.first() != Some(&b'\x1b') { input.clear(); }
+    }
+}
+
+// Reads the replies to window reports (XTWINOPS) asked for ahead of a primary device attributes
+// query, giving the three parameters of each.
+pub(super) fn read_window_reports(lock: &mut StdinLock, deadline: Instant) -> IoResult<Vec<[u16; 3]>> {
+    let mut reports = Vec::new();
+    read_replies(lock, deadline, |sequence| match sequence {
+        ControlSequence::Csi { private: None, parameters, last: b't', .. } if parameters.len() == 3 => {
+            reports.push([parameters[0], parameters[1], parameters[2]]);
+        },
+        _ => {},
+    })?;
+
+    Ok(reports)
+}
+
+// Reads the reply to a cursor position report (CPR) asked for ahead of a primary device attributes
+// query, `ESC [ row ; column R`, giving the position if it arrives.
+pub(super) fn read_cursor_report(lock: &mut StdinLock, deadline: Instant) -> IoResult<Option<(u16, u16)>> {
+    let mut position = None;
+    read_replies(lock, deadline, |sequence| match sequence {
+        ControlSequence::Csi { private: None, parameters, last: b'R', .. } if parameters.len() == 2 => {
+            position = Some((parameters[0], parameters[1]));
+        },
+        _ => {},
+    })?;
+
+    Ok(position)
+}
+
+// Waits until either input stream has something to read or has been closed on the other side,
+// giving whether each one is ready.
+fn poll_pair(first: &StdinLock, second: &StdinLock) -> IoResult<(bool, bool)> {
+    let mut fds = [first, second].map(|lock| libc::pollfd { fd: lock.as_raw_fd(), events: libc::POLLIN, revents: 0 });
+
+    poller::wait(first.settings.poll_backend, &mut fds, -1)?;
+
+    // Closed descriptors would be reported forever, with nothing to read from them
+    if fds.iter().any(|fd| fd.revents & libc::POLLNVAL != 0) {
+        return Err(IoError::from_raw_os_error(libc::EBADF));
+    }
+
+    let ready = |fd: &libc::pollfd| fd.revents & (libc::POLLIN | libc::POLLHUP | libc::POLLERR) != 0;
+    Ok((ready(&fds[0]), ready(&fds[1])))
+}
+
+// Copies what is written on the child's side of a pseudo-terminal, read from `child`, to `output`,
+// and passes the events read from `input` on to `child_input` as `observe` decides.
+// Ends once the child's side is closed or `observe` stops the relay.
+pub(super) fn relay(
+    input: &mut StdinLock,
+    child: &mut StdinLock,
+    output: &mut impl Write,
+    child_input: &mut impl Write,
+    mut observe: impl FnMut(&Event, &[u8]) -> Intercept,
+) -> IoResult<()> {
+    let mut buffer = [0_u8; 4096];
+
+    loop {
+        let (typed, written) = match poll_pair(input, child) {
+            // Retry waits interrupted by a signal, such as a resize
+            Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+            result => result?,
+        };
+
+        if written {
+            match child.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(read) => {
+                    output.write_all(&buffer[..read])?;
+                    output.flush()?;
+                },
+                // Reading the master side fails with EIO once the child's side is closed
+                Err(error) if error.raw_os_error() == Some(libc::EIO) => return Ok(()),
+                Err(error) if error.kind() == ErrorKind::Interrupted => {},
+                Err(error) => return Err(error),
+            }
+        }
+
+        if typed {
+            input.capture = Some(Vec::new());
+            let event = process_event(input, 0);
+            let bytes = input.capture.take().unwrap_or_default();
+            let Some(event) = event? else { continue };
+
+            match observe(&event, &bytes) {
+                Intercept::Forward => child_input.write_all(&bytes)?,
+                Intercept::Replace(bytes) => child_input.write_all(&bytes)?,
+                Intercept::Drop => {},
+                Intercept::Stop => return Ok(()),
+            }
+
+            child_input.flush()?;
+        }
+    }
+}
+
+// Attains the current `termios` settings of the terminal.
+pub(crate) fn attributes(lock: &impl AsRawFd) -> IoResult<libc::termios> {
+    // Safety: `termios` is properly handled
+    unsafe {
+        let mut termios = MaybeUninit::uninit();
+        io_error(|| libc::tcgetattr(lock.as_raw_fd(), termios.as_mut_ptr()))?;
+
+        Ok(termios.assume_init())
+    }
+}
+
+// This function reads bytes from the terminal input up to and including a newline, or until EOF.
+// In canonical mode the kernel hands over at most one line per `read`, so nothing past it is consumed.
+fn read_line(lock: &mut StdinLock) -> IoResult<Vec<u8>> {
+    // Bytes read ahead come first, and may hold the whole line
+    let mut buffer = lock.state.take_unread_line();
+    if buffer.ends_with(b"\n") { return Ok(buffer); }
+
+    let mut chunk = [0_u8; 4096];
+
+    let result = loop {
+        // Safety: Valid `fd` and buffer.
+        let read = unsafe { libc::read(lock.as_raw_fd(), chunk.as_mut_ptr().cast(), chunk.len()) };
+
+        match read {
+            read if read < 0 => match IoError::last_os_error() {
+                // Retry reads interrupted by a signal
+                error if error.kind() == ErrorKind::Interrupted => continue,
+                error => {
+                    wipe(&mut buffer);
+                    break Err(error);
+                },
+            },
+            // End of file, give what has been read so far
+            0 => break Ok(buffer),
+            read => {
+                let chunk = &chunk[..read as usize];
+                extend(&mut buffer, chunk);
+
+                if chunk.ends_with(b"\n") { break Ok(buffer); }
+            },
+        }
+    };
+
+    wipe(&mut chunk);
+    result
+}
+
+// Reads a line in non-canonical mode, which has no limit on its length, keeping up to `limit` bytes of it.
+// Gives the bytes kept and whether any were left out. Bytes read past the end of the line are kept aside.
+// The end of input, or Ctrl+D pressed on its own, ends the line early, failing only if none of it was read.
+pub(super) fn read_long_line(lock: &mut StdinLock, limit: Option<usize>) -> IoResult<(Vec<u8>, bool)> {
+    let mut line = Vec::new();
+    let mut truncated = false;
+
+    loop {
+        let read = read_up_to::<4096>(lock, -1);
+        let ended = match &read {
+            Err(error) => error.kind() == ErrorKind::UnexpectedEof,
+            Ok(Some((chunk, 1))) => chunk[0] == b'\x04',
+            Ok(_) => false,
+        };
+
+        if ended {
+            return match line.is_empty() && !truncated {
+                true => Err(IoError::from(ErrorKind::UnexpectedEof)),
+                false => Ok((line, truncated)),
+            };
+        }
+
+        let Some((chunk, read)) = read? else { continue };
+        let chunk = &chunk[..read];
+
+        // Ctrl+C pressed on its own interrupts the line unless it is to be read as a key,
+        // while pasted text arrives in larger reads and keeps any such byte as it is
+        if chunk == b"\x03" && lock.settings.ctrl_c != CtrlC::Key {
+            return Err(IoError::from(ErrorKind::Interrupted));
+        }
+
+        let end = chunk.iter().position(|byte| *byte == b'\n').map(|index| index + 1);
+        let part = &chunk[..end.unwrap_or(read)];
+
+        let room = limit.map_or(usize::MAX, |limit| limit.saturating_sub(line.len()));
+        truncated |= part.len() > room;
+        extend(&mut line, &part[..part.len().min(room)]);
+
+        if let Some(end) = end {
+            lock.state.push_unread(&chunk[end..]);
+            return Ok((line, truncated));
+        }
+    }
+}
+
+// Appends bytes to a buffer. With the `zeroize` feature, a buffer that has to grow is
+// wiped before being freed, so no copies of what was typed are left behind.
+fn extend(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    #[cfg(feature = "zeroize")]
+    if buffer.capacity() - buffer.len() < bytes.len() {
+        let mut grown = Vec::with_capacity((buffer.len() + bytes.len()).max(buffer.capacity() * 2));
+        grown.extend_from_slice(buffer);
+        wipe(buffer);
+        *buffer = grown;
+    }
+
+    buffer.extend_from_slice(bytes);
+}
+
+// Overwrites a buffer with zeroes with the `zeroize` feature, and does nothing otherwise.
+#[cfg(feature = "zeroize")]
+fn wipe(buffer: &mut impl zeroize::Zeroize) {
+    buffer.zeroize();
+}
+
+#[cfg(not(feature = "zeroize"))]
+fn wipe<T>(_buffer: &mut T) {}
+
+// This function reads a line of bytes from the terminal input, making no assumption on their encoding.
+pub(super) fn read_line_bytes(lock: &mut StdinLock, timeout: i32) -> IoResult<Option<Vec<u8>>> {
+    if poll_input(lock, timeout)? {
+        read_line(lock).map(Some)
     } else {
         Ok(None)
     }
 }
 
-pub(crate) struct Config<'a> {
-    pub(super) lock: &'a mut StdinLock<'static>,
+// This function reads a line of characters from the terminal input.
+pub(super) fn read_string(lock: &mut StdinLock, timeout: i32) -> IoResult<Option<String>> {
+    let Some(buffer) = read_line_bytes(lock, timeout)? else { return Ok(None) };
+    let buffer = String::from_utf8(buffer).map_err(|error| {
+        wipe(&mut error.into_bytes());
+        IoError::new(ErrorKind::InvalidData, "stream did not contain valid UTF-8")
+    })?;
+
+    Ok(Some(buffer))
+}
+
+// This function drains the unterminated line that canonical mode is holding back.
+// Turning canonical processing off makes the kernel release the partial line to `read`,
+// after which the original settings are put back in place.
+pub(super) fn read_pending(lock: &mut StdinLock) -> IoResult<String> {
+    // Safety: `termios` is properly handled and the buffer is valid for its length.
+    unsafe {
+        // Fetch the current settings to restore afterwards
+        let mut termios = MaybeUninit::uninit();
+        io_error(|| libc::tcgetattr(lock.as_raw_fd(), termios.as_mut_ptr()))?;
+        let original = termios.assume_init();
+
+        // Non-canonical and non-blocking: `read` returns what is pending and nothing more
+        let mut pending = original;
+        pending.c_lflag &= !libc::ICANON;
+        pending.c_cc[libc::VMIN] = 0;
+        pending.c_cc[libc::VTIME] = 0;
+        io_error(|| libc::tcsetattr(lock.as_raw_fd(), libc::TCSANOW, &pending))?;
+
+        // Bytes read ahead come first
+        let mut buffer = lock.state.take_unread(usize::MAX);
+        let mut chunk = [0_u8; 256];
+
+        // Read until the kernel has nothing left to give
+        let result = loop {
+            match libc::read(lock.as_raw_fd(), chunk.as_mut_ptr().cast(), chunk.len()) {
+                read if read < 0 => break Err(IoError::last_os_error()),
+                0 => break Ok(()),
+                read => buffer.extend_from_slice(&chunk[..read as usize]),
+            }
+        };
+
+        // Restore the original settings before reporting any read error
+        io_error(|| libc::tcsetattr(lock.as_raw_fd(), libc::TCSANOW, &original))?;
+        result?;
+
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
+
+// The terminals with `Config` guards active on them, each with its settings from before the first
+// guard and the flags of every active guard, in the order they were set. Guards compose through it,
+// so one dropped while another set later is still active does not undo the other's flags.
+static GUARDED: Mutex<Vec<Guarded>> = Mutex::new(Vec::new());
+
+// Identifies each `Config` guard.
+static NEXT_GUARD: AtomicU64 = AtomicU64::new(0);
+
+struct Guarded {
+    // The device and inode of the terminal, shared by every descriptor duplicated from it.
+    device: (libc::dev_t, libc::ino_t),
+    // The descriptor the first guard was set through, for restoring the settings from the panic hook.
+    fd: RawFd,
     original: libc::termios,
+    guards: Vec<(u64, Vec<Flag>)>,
+}
+
+impl Guarded {
+    // Gives the settings with the flags of every active guard applied, later guards taking precedence.
+    fn settings(&self) -> libc::termios {
+        let mut termios = self.original;
+
+        for flag in self.guards.iter().flat_map(|(_, flags)| flags) {
+            match flag {
+                Flag::Canonical => termios.c_lflag |= libc::ICANON,
+                Flag::Echo => termios.c_lflag |= libc::ECHO,
+                Flag::NotCanonical => termios.c_lflag &= !libc::ICANON,
+                Flag::NotEcho => termios.c_lflag &= !libc::ECHO,
+                Flag::Passthrough => {
+                    termios.c_lflag &= !(libc::ISIG | libc::IEXTEN);
+                    termios.c_iflag &= !(libc::IXON | libc::ICRNL);
+                },
+                // A control character set to `_POSIX_VDISABLE` (0 on Linux) matches no key
+                Flag::NoInterrupt => termios.c_cc[libc::VINTR] = 0,
+            }
+        }
+
+        termios
+    }
+}
+
+// Whether two settings differ in what the guards change, so setting one over the other needs a system call.
+fn differ(first: &libc::termios, second: &libc::termios) -> bool {
+    (first.c_lflag, first.c_iflag, first.c_cc[libc::VINTR]) != (second.c_lflag, second.c_iflag, second.c_cc[libc::VINTR])
+}
+
+// Puts the terminals with guards active on them back to their settings from before the first guard,
+// for the panic hook. The guards still apply their settings if they are dropped afterwards.
+pub(super) fn restore_settings() {
+    // The panic may have happened while holding the lock
+    let guarded = match GUARDED.try_lock() {
+        Ok(guarded) => guarded,
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        Err(TryLockError::WouldBlock) => return,
+    };
+
+    for terminal in guarded.iter() {
+        // Safety: Valid settings; a descriptor closed since fails harmlessly.
+        unsafe { libc::tcsetattr(terminal.fd, libc::TCSANOW, &terminal.original) };
+    }
+}
+
+// Identifies the terminal behind a descriptor by its device and inode.
+fn device(lock: &impl AsRawFd) -> (libc::dev_t, libc::ino_t) {
+    // Safety: `stat` is properly handled
+    unsafe {
+        let mut stat = MaybeUninit::<libc::stat>::zeroed();
+        // A failure leaves the zeroed identity, which is merely shared by other failures
+        libc::fstat(lock.as_raw_fd(), stat.as_mut_ptr());
+        let stat = stat.assume_init();
+
+        (stat.st_dev, stat.st_ino)
+    }
+}
+
+pub(crate) struct Config<'a> {
+    pub(super) lock: &'a mut StdinLock,
+    device: (libc::dev_t, libc::ino_t),
+    id: u64,
     flush: bool,
 }
 
 impl<'a> Config<'a> {
-    pub(super) fn set(lock: &'a mut StdinLock<'static>, flush: bool, flags: &[Flag]) -> Self {
-        // Safety: `termios` is properly handled
-        unsafe {
-            // Initialize termios struct
-            let mut termios = MaybeUninit::uninit();
-            // Theoretically, the call will never fail.
-            io_error(|| libc::tcgetattr(lock.as_raw_fd(), termios.as_mut_ptr())).unwrap();
-
-            // Get the initialized termios struct
-            let mut termios = termios.assume_init();
-            // Store the original settings for later restoration
-            let original = termios;
-
-            // Set flags
-            for flag in flags {
-                match flag {
-                    Flag::Canonical => termios.c_lflag |= libc::ICANON,
-                    Flag::Echo => termios.c_lflag |= libc::ECHO,
-                    Flag::NotCanonical => termios.c_lflag &= !libc::ICANON,
-                    Flag::NotEcho => termios.c_lflag &= !libc::ECHO,
-                }
-            }
+    pub(super) fn set(lock: &'a mut StdinLock, flush: bool, flags: &[Flag]) -> IoResult<Self> {
+        // Dumb terminals are kept line-buffered and echoing, as raw input cannot be relied upon
+        let raw = flags.iter().any(|flag| matches!(flag, Flag::NotCanonical));
+        let flags = match (lock.settings.dumb, raw, lock.settings.ctrl_c) {
+            (true, true, _) => &[][..],
+            // Ctrl+C reaches raw reads unless it is to be sent as a signal
+            (false, true, CtrlC::Error | CtrlC::Key) => &[flags, &[Flag::NoInterrupt]].concat(),
+            _ => flags,
+        };
+
+        let device = device(lock);
+        let id = NEXT_GUARD.fetch_add(1, Ordering::Relaxed);
+        cleanup::install();
+        let mut guarded = GUARDED.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // The settings are only saved for the first guard on the terminal
+        let index = match guarded.iter().position(|terminal| terminal.device == device) {
+            Some(index) => index,
+            None => {
+                let original = attributes(lock).map_err(hangup)?;
+                guarded.push(Guarded { device, fd: lock.as_raw_fd(), original, guards: Vec::new() });
+                guarded.len() - 1
+            },
+        };
+
+        let previous = guarded[index].settings();
+        guarded[index].guards.push((id, flags.to_vec()));
+        let termios = guarded[index].settings();
 
-            // Apply the modified termios settings
-            let action = if flush { libc::TCSAFLUSH } else { libc::TCSADRAIN };
-            // This particular call will also, theoretically, never fail.
-            io_error(|| libc::tcsetattr(lock.as_raw_fd(), action, &termios)).unwrap();
-            Config { lock, original, flush }
+        // Apply the modified termios settings, unless they are already in place and no input is to be discarded
+        let action = if flush { libc::TCSAFLUSH } else { libc::TCSADRAIN };
+        let applied = match flush || differ(&previous, &termios) {
+            // Safety: `termios` is properly handled
+            true => io_error(|| unsafe { libc::tcsetattr(lock.as_raw_fd(), action, &termios) }),
+            false => Ok(()),
+        };
+
+        if let Err(error) = applied {
+            // The guard never took effect, so it is forgotten along with the saved settings if it was the only one
+            guarded[index].guards.pop();
+            if guarded[index].guards.is_empty() { guarded.swap_remove(index); }
+            return Err(hangup(error));
         }
+
+        Ok(Config { lock, device, id, flush })
     }
 }
 
 impl<'a> Drop for Config<'a> {
     fn drop(&mut self) {
+        let mut guarded = GUARDED.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(index) = guarded.iter().position(|terminal| terminal.device == self.device) else { return };
+
+        // Restore the settings from before the first guard once the last one is dropped,
+        // or else the settings of the guards still active
+        let previous = guarded[index].settings();
+        guarded[index].guards.retain(|&(id, _)| id != self.id);
+        let termios = match guarded[index].guards.is_empty() {
+            true => guarded.swap_remove(index).original,
+            false => guarded[index].settings(),
+        };
+
+        if !self.flush && !differ(&previous, &termios) { return; }
+
+        let action = if self.flush { libc::TCSAFLUSH } else { libc::TCSANOW };
+        // Errors cannot be reported from here, and only happen once the terminal is gone
         // Safety: `termios` is properly handled
-        unsafe {
-            // Restore the original termios settings
-            let action = if self.flush { libc::TCSAFLUSH } else { libc::TCSANOW };
-            // Call will theoretically never fail.
-            io_error(|| libc::tcsetattr(self.lock.as_raw_fd(), action, &self.original)).unwrap();
-        }
+        let _ = io_error(|| unsafe { libc::tcsetattr(self.lock.as_raw_fd(), action, &termios) });
     }
 }
@@ -0,0 +1,292 @@
+//! Colors and text attributes for styled output.
+//!
+//! ```rust,ignore
+//! use in_keys::Terminal;
+//! use in_keys::style::{Color, Style, RESET};
+//!
+//! let terminal = Terminal::new();
+//! let warning = Style::new().foreground(Color::Yellow).bold();
+//!
+//! let mut stdout = terminal.lock_stdout();
+//! if stdout.colors_enabled() {
+//!     stdout.print(&format!("{}warning{}: disk almost full", warning.render(), RESET))?;
+//! }
+//!
+//! // Or through the lock, which only writes the sequences if styled output is enabled
+//! stdout.set_style(&warning)?;
+//! stdout.print("warning")?;
+//! stdout.reset_style()?;
+//! stdout.println(": disk almost full")?;
+//! ```
+
+/// The escape sequence that resets every color and attribute.
+pub const RESET: &str = "\x1b[0m";
+
+/// A terminal color, from the 16 the terminal defines, its 256-color palette, or any RGB value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    /// Black (palette index 0)
+    Black,
+    /// Red (palette index 1)
+    Red,
+    /// Green (palette index 2)
+    Green,
+    /// Yellow (palette index 3)
+    Yellow,
+    /// Blue (palette index 4)
+    Blue,
+    /// Magenta (palette index 5)
+    Magenta,
+    /// Cyan (palette index 6)
+    Cyan,
+    /// White (palette index 7)
+    White,
+    /// Bright black, or gray (palette index 8)
+    BrightBlack,
+    /// Bright red (palette index 9)
+    BrightRed,
+    /// Bright green (palette index 10)
+    BrightGreen,
+    /// Bright yellow (palette index 11)
+    BrightYellow,
+    /// Bright blue (palette index 12)
+    BrightBlue,
+    /// Bright magenta (palette index 13)
+    BrightMagenta,
+    /// Bright cyan (palette index 14)
+    BrightCyan,
+    /// Bright white (palette index 15)
+    BrightWhite,
+    /// A color of the 256-color palette
+    Ansi256(u8),
+    /// A truecolor value, (`red`, `green`, `blue`)
+    Rgb(u8, u8, u8),
+}
+
+// The 16 colors the terminal defines, in palette order.
+const NAMED: [Color; 16] = [
+    Color::Black, Color::Red, Color::Green, Color::Yellow,
+    Color::Blue, Color::Magenta, Color::Cyan, Color::White,
+    Color::BrightBlack, Color::BrightRed, Color::BrightGreen, Color::BrightYellow,
+    Color::BrightBlue, Color::BrightMagenta, Color::BrightCyan, Color::BrightWhite,
+];
+
+impl Color {
+    // Gives the SGR parameters selecting the color, with `base` being 30 for the foreground
+    // and 40 for the background.
+    fn sgr(&self, base: u8) -> String {
+        match *self {
+            Color::Ansi256(index) => format!("{};5;{}", base + 8, index),
+            Color::Rgb(red, green, blue) => format!("{};2;{};{};{}", base + 8, red, green, blue),
+            named => match named.index().unwrap_or_default() {
+                index @ 0..=7 => (base + index).to_string(),
+                index => (base + 60 + index - 8).to_string(),
+            },
+        }
+    }
+
+    // Gives the named color with the given palette index, from 0 to 15.
+    #[cfg_attr(not(feature = "anstyle"), allow(dead_code))]
+    fn named(index: u8) -> Option<Color> {
+        NAMED.get(index as usize).copied()
+    }
+
+    // Gives the palette index of a named color.
+    fn index(&self) -> Option<u8> {
+        NAMED.iter().position(|color| color == self).map(|index| index as u8)
+    }
+}
+
+/// A set of colors and text attributes, built up with its methods.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Style {
+    pub(crate) foreground: Option<Color>,
+    pub(crate) background: Option<Color>,
+    pub(crate) bold: bool,
+    pub(crate) dim: bool,
+    pub(crate) italic: bool,
+    pub(crate) underline: bool,
+    pub(crate) blink: bool,
+    pub(crate) reverse: bool,
+    pub(crate) hidden: bool,
+    pub(crate) strikethrough: bool,
+}
+
+impl Style {
+    /// Creates a style with no colors or attributes.
+    pub fn new() -> Self {
+        Style::default()
+    }
+
+    /// Sets the color of the text.
+    pub fn foreground(mut self, color: Color) -> Self {
+        self.foreground = Some(color);
+        self
+    }
+
+    /// Sets the color behind the text.
+    pub fn background(mut self, color: Color) -> Self {
+        self.background = Some(color);
+        self
+    }
+
+    /// Makes the text bold.
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    /// Makes the text dim.
+    pub fn dim(mut self) -> Self {
+        self.dim = true;
+        self
+    }
+
+    /// Makes the text italic.
+    pub fn italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+
+    /// Underlines the text.
+    pub fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    /// Makes the text blink.
+    pub fn blink(mut self) -> Self {
+        self.blink = true;
+        self
+    }
+
+    /// Swaps the colors of the text and what is behind it.
+    pub fn reverse(mut self) -> Self {
+        self.reverse = true;
+        self
+    }
+
+    /// Hides the text.
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    /// Strikes the text through.
+    pub fn strikethrough(mut self) -> Self {
+        self.strikethrough = true;
+        self
+    }
+
+    /// Whether the style has no colors or attributes.
+    pub fn is_plain(&self) -> bool {
+        *self == Style::default()
+    }
+
+    /// Gives the escape sequence applying the style, which is empty if the style is plain.
+    /// The colors and attributes stay applied until [`RESET`] is written.
+    pub fn render(&self) -> String {
+        let attributes = [
+            (self.bold, "1"),
+            (self.dim, "2"),
+            (self.italic, "3"),
+            (self.underline, "4"),
+            (self.blink, "5"),
+            (self.reverse, "7"),
+            (self.hidden, "8"),
+            (self.strikethrough, "9"),
+        ];
+
+        let parameters: Vec<String> = attributes
+            .into_iter()
+            .filter(|&(set, _)| set)
+            .map(|(_, code)| code.to_string())
+            .chain(self.foreground.map(|color| color.sgr(30)))
+            .chain(self.background.map(|color| color.sgr(40)))
+            .collect();
+
+        match parameters.is_empty() {
+            true => String::new(),
+            false => format!("\x1b[{}m", parameters.join(";")),
+        }
+    }
+}
+
+#[cfg(feature = "anstyle")]
+impl From<Color> for anstyle::Color {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Ansi256(index) => anstyle::Color::Ansi256(anstyle::Ansi256Color(index)),
+            Color::Rgb(red, green, blue) => anstyle::Color::Rgb(anstyle::RgbColor(red, green, blue)),
+            named => {
+                // The named colors are the first 16 of the palette, which `anstyle` maps back to its own
+                let index = named.index().unwrap_or_default();
+                anstyle::Color::Ansi(anstyle::Ansi256Color(index).into_ansi().unwrap_or(anstyle::AnsiColor::Black))
+            },
+        }
+    }
+}
+
+#[cfg(feature = "anstyle")]
+impl From<anstyle::Color> for Color {
+    fn from(color: anstyle::Color) -> Self {
+        match color {
+            anstyle::Color::Ansi(ansi) => Color::named(anstyle::Ansi256Color::from_ansi(ansi).index()).unwrap_or(Color::Black),
+            anstyle::Color::Ansi256(anstyle::Ansi256Color(index)) => Color::Ansi256(index),
+            anstyle::Color::Rgb(anstyle::RgbColor(red, green, blue)) => Color::Rgb(red, green, blue),
+        }
+    }
+}
+
+#[cfg(feature = "anstyle")]
+impl From<Style> for anstyle::Style {
+    fn from(style: Style) -> Self {
+        use anstyle::Effects;
+
+        let effects = [
+            (style.bold, Effects::BOLD),
+            (style.dim, Effects::DIMMED),
+            (style.italic, Effects::ITALIC),
+            (style.underline, Effects::UNDERLINE),
+            (style.blink, Effects::BLINK),
+            (style.reverse, Effects::INVERT),
+            (style.hidden, Effects::HIDDEN),
+            (style.strikethrough, Effects::STRIKETHROUGH),
+        ];
+
+        let effects = effects
+            .into_iter()
+            .fold(Effects::new(), |effects, (set, effect)| effects.set(effect, set));
+
+        anstyle::Style::new()
+            .fg_color(style.foreground.map(Into::into))
+            .bg_color(style.background.map(Into::into))
+            .effects(effects)
+    }
+}
+
+#[cfg(feature = "anstyle")]
+impl From<anstyle::Style> for Style {
+    /// Converts the style, turning every kind of underline into a plain one.
+    /// Underline colors have no counterpart and are dropped.
+    fn from(style: anstyle::Style) -> Self {
+        use anstyle::Effects;
+
+        let effects = style.get_effects();
+        let underlines = Effects::UNDERLINE | Effects::DOUBLE_UNDERLINE | Effects::CURLY_UNDERLINE
+            | Effects::DOTTED_UNDERLINE | Effects::DASHED_UNDERLINE;
+
+        Style {
+            foreground: style.get_fg_color().map(Into::into),
+            background: style.get_bg_color().map(Into::into),
+            bold: effects.contains(Effects::BOLD),
+            dim: effects.contains(Effects::DIMMED),
+            italic: effects.contains(Effects::ITALIC),
+            underline: effects.iter().any(|effect| underlines.contains(effect)),
+            blink: effects.contains(Effects::BLINK),
+            reverse: effects.contains(Effects::INVERT),
+            hidden: effects.contains(Effects::HIDDEN),
+            strikethrough: effects.contains(Effects::STRIKETHROUGH),
+        }
+    }
+}
@@ -0,0 +1,611 @@
+//! Small interactive widgets built on top of the input and output streams.
+//!
+//! ```rust,ignore
+//! use in_keys::Terminal;
+//! use in_keys::widgets::{Confirm, LineEditor, Locale};
+//!
+//! let terminal = Terminal::new();
+//! let german = Locale {
+//!     accept: vec!['j'],
+//!     reject: vec!['n'],
+//!     hint: ["[J/n]".into(), "[j/N]".into(), "[j/n]".into()],
+//!     accept_label: "ja".into(),
+//!     reject_label: "nein".into(),
+//! };
+//!
+//! let delete = Confirm::new("Datei löschen?").default(true).locale(german).ask(&terminal)?;
+//! let password = LineEditor::new("Passwort: ").mask('*').read(&terminal)?;
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Error as IoError, ErrorKind, Result as IoResult, Write};
+use std::ops::Range;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::Terminal;
+use crate::events::Event;
+use crate::keys::{Key, KeyEvent};
+use crate::streams::{detached, StdinLock, StdoutLock};
+use crate::width::{grapheme_len, grapheme_width, graphemes, visible_width};
+
+/// The keys and strings a prompt uses, allowing prompts to be localized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    /// The keys that answer yes, compared case-insensitively.
+    pub accept: Vec<char>,
+    /// The keys that answer no, compared case-insensitively.
+    pub reject: Vec<char>,
+    /// The hints shown after the prompt when the default is yes, no, or absent, respectively.
+    pub hint: [String; 3],
+    /// The text echoed when yes is chosen.
+    pub accept_label: String,
+    /// The text echoed when no is chosen.
+    pub reject_label: String,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale {
+            accept: vec!['y'],
+            reject: vec!['n'],
+            hint: ["[Y/n]".into(), "[y/N]".into(), "[y/n]".into()],
+            accept_label: "yes".into(),
+            reject_label: "no".into(),
+        }
+    }
+}
+
+impl Locale {
+    // Whether `char` is one of the `keys`, ignoring case.
+    fn matches(keys: &[char], char: char) -> bool {
+        keys.iter().any(|key| key.to_lowercase().eq(char.to_lowercase()))
+    }
+}
+
+/// A yes or no question, answered with a single key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Confirm {
+    prompt: String,
+    default: Option<bool>,
+    locale: Locale,
+}
+
+impl Confirm {
+    /// Creates a question with the given prompt and no default answer.
+    pub fn new(prompt: &str) -> Self {
+        Confirm { prompt: prompt.to_string(), default: None, locale: Locale::default() }
+    }
+
+    /// Sets the answer given when Enter is pressed.
+    pub fn default(mut self, default: bool) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    /// Sets the keys and strings used, see [`Locale`].
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Asks the question, waiting until it is answered.
+    /// Keys other than the accepting and rejecting ones are ignored.
+    pub fn ask(&self, terminal: &Terminal) -> IoResult<bool> {
+        let mut stdin = terminal
+            .lock_stdin()
+            .ok_or_else(detached)?;
+
+        self.show(terminal)?;
+        let answer = loop {
+            if let Some(answer) = self.answer(stdin.read_key()?)? { break answer }
+        };
+
+        self.finish(terminal, answer)
+    }
+
+    /// Asks the question asynchronously, resolving once it is answered, like [`Confirm::ask`].
+    /// Keys are read with [`StdinLock::read_key_future`], so any executor can drive it.
+    #[cfg(feature = "async")]
+    pub async fn ask_async(&self, terminal: &Terminal) -> IoResult<bool> {
+        let mut stdin = terminal
+            .lock_stdin()
+            .ok_or_else(detached)?;
+
+        self.show(terminal)?;
+        let answer = loop {
+            if let Some(answer) = self.answer(stdin.read_key_future().await?)? { break answer }
+        };
+
+        self.finish(terminal, answer)
+    }
+
+    // Prints the prompt along with the hint for the default answer.
+    fn show(&self, terminal: &Terminal) -> IoResult<()> {
+        let hint = match self.default {
+            Some(true) => &self.locale.hint[0],
+            Some(false) => &self.locale.hint[1],
+            None => &self.locale.hint[2],
+        };
+        terminal.lock_prompt().print(&format!("{} {} ", self.prompt, hint))
+    }
+
+    // Gives the answer `key` stands for, if any, failing at the end of input, where no answer can come.
+    fn answer(&self, key: Key) -> IoResult<Option<bool>> {
+        match key {
+            Key::Char(char) if Locale::matches(&self.locale.accept, char) => Ok(Some(true)),
+            Key::Char(char) if Locale::matches(&self.locale.reject, char) => Ok(Some(false)),
+            Key::Enter => Ok(self.default),
+            Key::Eof => Err(IoError::from(ErrorKind::UnexpectedEof)),
+            _ => Ok(None),
+        }
+    }
+
+    // Echoes the label of the answer given, ending the prompt's line.
+    fn finish(&self, terminal: &Terminal, answer: bool) -> IoResult<bool> {
+        let label = match answer {
+            true => &self.locale.accept_label,
+            false => &self.locale.reject_label,
+        };
+        terminal.lock_prompt().println(label)?;
+
+        Ok(answer)
+    }
+}
+
+/// A line of text typed at a prompt and edited in place, with the arrows, Home, End, Backspace and Delete.
+/// Edits work on whole user-perceived characters, so Backspace removes an emoji along with its
+/// modifiers, or a Hangul syllable typed as separate jamo, at once. The line is expected to fit on one row.
+///
+/// Lines entered earlier can be recalled with the up and down arrows, see [`History`].
+/// Alt+B and Alt+F move by words, and Ctrl+W deletes the word before the cursor, see [`WordRule`].
+/// Tab completes the text around the cursor, once given a [`Completer`].
+/// Other threads can print while a line is being read through [`LineEditor::suspend`],
+/// or through an [`ExternalPrinter`].
+#[derive(Debug)]
+pub struct LineEditor {
+    view: View,
+    line: Arc<Mutex<Line>>,
+    history: Mutex<History>,
+    keep_edits: bool,
+    words: WordRule,
+    completer: Option<Box<dyn Completer>>,
+}
+
+/// The completions of the text around the cursor, as given by a [`Completer`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Completion {
+    /// The byte range of the line replaced by a candidate, which can cover text before and after the
+    /// cursor, so completing can correct what was typed, such as its case, rather than only append to it.
+    pub range: Range<usize>,
+    /// The texts the range can be replaced with.
+    pub candidates: Vec<String>,
+}
+
+impl Completion {
+    // Gives the prefix the candidates share, being the candidate itself if there is only one.
+    pub(crate) fn shared_prefix(&self) -> &str {
+        match &self.candidates[..] {
+            [] => "",
+            [first, rest @ ..] => rest.iter().fold(first.as_str(), |prefix, candidate| {
+                let common = prefix.char_indices().zip(candidate.chars()).find(|((_, a), b)| a != b);
+                common.map_or(&prefix[..prefix.len().min(candidate.len())], |((index, _), _)| &prefix[..index])
+            }),
+        }
+    }
+}
+
+/// Completes the line of a [`LineEditor`], or one read with [`StdinLock::read_string_with_completion`], when Tab is pressed.
+///
+/// A single candidate replaces the range it is given for. Several candidates replace it with the
+/// prefix they share, if any, and are listed above the prompt when that changes nothing.
+/// Closures taking the line and the byte index of the cursor are completers.
+pub trait Completer: Send + Sync {
+    /// Gives the completions of `line` with the cursor at the byte index `cursor`.
+    fn complete(&self, line: &str, cursor: usize) -> Completion;
+}
+
+impl<F: Fn(&str, usize) -> Completion + Send + Sync> Completer for F {
+    fn complete(&self, line: &str, cursor: usize) -> Completion {
+        self(line, cursor)
+    }
+}
+
+impl fmt::Debug for dyn Completer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Completer(..)")
+    }
+}
+
+/// What makes up a word for the word operations of a [`LineEditor`], Alt+B, Alt+F and Ctrl+W.
+/// Words are runs of word characters, and whatever lies between them is skipped over along with the word.
+#[derive(Clone, Default)]
+pub enum WordRule {
+    /// Words are separated by whitespace, as with readline's Ctrl+W.
+    #[default]
+    Whitespace,
+    /// Words are shell tokens, separated by whitespace and by the operators `|`, `&`, `;`, `<`, `>`, `(` and `)`.
+    ShellToken,
+    /// Words are path components, separated by whitespace and by `/`, so Ctrl+W in a path
+    /// deletes its last component rather than the whole path.
+    PathComponent,
+    /// Words are made of the characters the predicate accepts.
+    Custom(Arc<dyn Fn(char) -> bool + Send + Sync>),
+}
+
+impl fmt::Debug for WordRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WordRule::Whitespace => f.write_str("Whitespace"),
+            WordRule::ShellToken => f.write_str("ShellToken"),
+            WordRule::PathComponent => f.write_str("PathComponent"),
+            WordRule::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+impl WordRule {
+    // Whether the grapheme cluster `grapheme` is part of a word, judged by its first character.
+    fn is_word(&self, grapheme: &str) -> bool {
+        let Some(char) = grapheme.chars().next() else { return false };
+
+        match self {
+            WordRule::Whitespace => !char.is_whitespace(),
+            WordRule::ShellToken => !char.is_whitespace() && !"|&;<>()".contains(char),
+            WordRule::PathComponent => !char.is_whitespace() && char != '/',
+            WordRule::Custom(predicate) => predicate(char),
+        }
+    }
+
+    // Gives the byte index of the start of the word before `index`, skipping what lies between.
+    fn start_before(&self, text: &str, index: usize) -> usize {
+        let mut start = index;
+
+        while let Some(previous) = previous_boundary(text, start).filter(|&previous| !self.is_word(&text[previous..start])) {
+            start = previous;
+        }
+        while let Some(previous) = previous_boundary(text, start).filter(|&previous| self.is_word(&text[previous..start])) {
+            start = previous;
+        }
+
+        start
+    }
+
+    // Gives the byte index of the end of the word after `index`, skipping what lies between.
+    fn end_after(&self, text: &str, index: usize) -> usize {
+        let mut end = index;
+        let mut graphemes = graphemes(&text[index..]).peekable();
+
+        while let Some(grapheme) = graphemes.next_if(|grapheme| !self.is_word(grapheme)) {
+            end += grapheme.len();
+        }
+        while let Some(grapheme) = graphemes.next_if(|grapheme| self.is_word(grapheme)) {
+            end += grapheme.len();
+        }
+
+        end
+    }
+}
+
+/// The lines entered in a [`LineEditor`] or read with [`StdinLock::read_string_with_history`], oldest first.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct History {
+    entries: Vec<String>,
+}
+
+impl History {
+    /// Creates an empty history.
+    pub fn new() -> Self {
+        History::default()
+    }
+
+    /// Adds a line to the end of the history, unless it is empty or the same as the last one.
+    pub fn push(&mut self, line: &str) {
+        if !line.is_empty() && self.entries.last().map(String::as_str) != Some(line) {
+            self.entries.push(line.to_string());
+        }
+    }
+
+    /// Gives the lines, oldest first.
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+}
+
+// How a `LineEditor` shows its line.
+#[derive(Debug, Clone)]
+struct View {
+    prompt: String,
+    mask: Option<char>,
+}
+
+// The line being read by a `LineEditor`.
+#[derive(Debug, Default)]
+struct Line {
+    text: String,
+    // The byte index of the cursor in the text, always between two grapheme clusters
+    cursor: usize,
+    // The terminal the line is being read from, while it is
+    terminal: Option<Terminal>,
+}
+
+/// A guard keeping the prompt of a [`LineEditor`] cleared, so other output can be printed in its place.
+/// The prompt and the text typed so far are drawn again below that output when the guard is dropped.
+pub struct SuspendGuard<'a> {
+    view: &'a View,
+    line: MutexGuard<'a, Line>,
+}
+
+impl Drop for SuspendGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(terminal) = &self.line.terminal {
+            // Errors cannot be reported from here, and only leave the prompt undrawn until the next key
+            let _ = self.view.render(&mut terminal.lock_prompt(), &self.line);
+        }
+    }
+}
+
+impl LineEditor {
+    /// Creates an editor showing the given prompt before the line.
+    pub fn new(prompt: &str) -> Self {
+        let view = View { prompt: prompt.to_string(), mask: None };
+        LineEditor { view, line: Arc::default(), history: Mutex::default(), keep_edits: true, words: WordRule::default(), completer: None }
+    }
+
+    /// Completes the line with `completer` when Tab is pressed.
+    pub fn completer(self, completer: impl Completer + 'static) -> Self {
+        LineEditor { completer: Some(Box::new(completer)), ..self }
+    }
+
+    /// Sets what makes up a word for Alt+B, Alt+F and Ctrl+W. The default is [`WordRule::Whitespace`].
+    pub fn words(self, words: WordRule) -> Self {
+        LineEditor { words, ..self }
+    }
+
+    /// Starts from the given history, such as one saved from an earlier session.
+    pub fn history(self, history: History) -> Self {
+        LineEditor { history: Mutex::new(history), ..self }
+    }
+
+    /// Sets whether edits made to a recalled line are kept when moving on to another one, so they
+    /// are found again on coming back to it, as readline does. The default is to keep them.
+    /// Either way, the edits only last until the line is entered, and the history itself is left as it was.
+    pub fn keep_edits(mut self, keep_edits: bool) -> Self {
+        self.keep_edits = keep_edits;
+        self
+    }
+
+    /// Gives the lines entered so far, including those of the history the editor started from.
+    pub fn current_history(&self) -> History {
+        self.history.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Shows `mask` in place of each character typed, such as for passwords.
+    pub fn mask(mut self, mask: char) -> Self {
+        self.view.mask = Some(mask);
+        self
+    }
+
+    /// Reads a line, waiting until Enter is pressed, and gives it without the newline.
+    /// The line is added to the history, unless it is masked.
+    pub fn read(&self, terminal: &Terminal) -> IoResult<String> {
+        let mut stdin = terminal
+            .lock_stdin()
+            .ok_or_else(detached)?;
+        // Keys are echoed by redrawing the line rather than by the terminal
+        let mut stdin = stdin.echo_off()?;
+
+        *self.line() = Line { terminal: Some(terminal.clone()), ..Line::default() };
+        let result = self.edit(&mut stdin, terminal);
+        self.line().terminal = None;
+
+        result
+    }
+
+    /// Clears the prompt of the line being read, from another thread, until the returned guard is
+    /// dropped, so log lines or other output can be printed without garbling it. Output printed
+    /// meanwhile should end with a newline, as the prompt is drawn again on the row the cursor is left on.
+    /// Keys typed meanwhile are handled once the guard is dropped.
+    pub fn suspend(&self) -> IoResult<SuspendGuard<'_>> {
+        suspend(&self.view, &self.line)
+    }
+
+    /// Creates a handle printing lines above the prompt, which can be sent to other threads.
+    /// Lines are printed on the prompt stream of `terminal` while no line is being read.
+    pub fn printer(&self, terminal: &Terminal) -> ExternalPrinter {
+        ExternalPrinter { view: self.view.clone(), line: Arc::clone(&self.line), terminal: terminal.clone() }
+    }
+
+    // Locks the line being read.
+    fn line(&self) -> MutexGuard<'_, Line> {
+        self.line.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Edits the line with the keys read until Enter is pressed.
+    fn edit(&self, stdin: &mut StdinLock, terminal: &Terminal) -> IoResult<String> {
+        let entries = self.current_history().entries;
+        // The entry being shown, with the new line being one past the last entry
+        let mut index = entries.len();
+        // Edits made to the entries during this read, kept apart from the history
+        let mut edits = HashMap::new();
+
+        // The line is locked before the output, in the order `suspend` takes them
+        let line = self.line();
+        self.view.render(&mut terminal.lock_prompt(), &line)?;
+        drop(line);
+
+        loop {
+            let (key, modifiers) = match stdin.read_event()? {
+                Event::Key(KeyEvent { key, modifiers, .. }) => (key, modifiers),
+                // Pasted line breaks are left out, as the line is only entered with Enter
+                Event::Paste(pasted) => {
+                    let mut line = self.line();
+                    let Line { text, cursor, .. } = &mut *line;
+                    let pasted: String = pasted.chars().filter(|char| !char.is_control()).collect();
+
+                    text.insert_str(*cursor, &pasted);
+                    *cursor += pasted.len();
+                    self.view.render(&mut terminal.lock_prompt(), &line)?;
+                    continue;
+                },
+                _ => continue,
+            };
+            let mut line = self.line();
+            let Line { text, cursor, .. } = &mut *line;
+
+            match key {
+                // The line cannot be ended any other way once the input has
+                Key::Eof => return Err(IoError::from(ErrorKind::UnexpectedEof)),
+                Key::Char('b') if modifiers.alt => *cursor = self.words.start_before(text, *cursor),
+                Key::Char('f') if modifiers.alt => *cursor = self.words.end_after(text, *cursor),
+                // Ctrl+W
+                Key::Ctrl('w') => {
+                    let start = self.words.start_before(text, *cursor);
+                    text.replace_range(start..*cursor, "");
+                    *cursor = start;
+                },
+                Key::Enter => {
+                    terminal.lock_prompt().println("")?;
+                    if self.view.mask.is_none() {
+                        self.history.lock().unwrap_or_else(PoisonError::into_inner).push(text);
+                    }
+
+                    return Ok(std::mem::take(text));
+                },
+                Key::ArrowUp | Key::ArrowDown => {
+                    let next = match key {
+                        Key::ArrowUp => index.checked_sub(1),
+                        _ => (index < entries.len()).then_some(index + 1),
+                    };
+                    let Some(next) = next else { continue };
+
+                    // The new line is always kept, as it is in no entry
+                    if self.keep_edits || index == entries.len() {
+                        edits.insert(index, std::mem::take(text));
+                    }
+
+                    index = next;
+                    *text = edits.get(&index).or(entries.get(index)).cloned().unwrap_or_default();
+                    *cursor = text.len();
+                },
+                Key::Tab => match &self.completer {
+                    Some(completer) => complete(completer.complete(text, *cursor), text, cursor, terminal)?,
+                    None => continue,
+                },
+                Key::Char(char) if !char.is_control() => {
+                    text.insert(*cursor, char);
+                    *cursor += char.len_utf8();
+                },
+                Key::Backspace => if let Some(start) = previous_boundary(text, *cursor) {
+                    text.replace_range(start..*cursor, "");
+                    *cursor = start;
+                },
+                Key::Del => {
+                    let end = *cursor + grapheme_len(&text[*cursor..]);
+                    text.replace_range(*cursor..end, "");
+                },
+                Key::ArrowLeft => *cursor = previous_boundary(text, *cursor).unwrap_or(*cursor),
+                Key::ArrowRight => *cursor += grapheme_len(&text[*cursor..]),
+                Key::Home | Key::Ctrl('a') => *cursor = 0,
+                Key::End | Key::Ctrl('e') => *cursor = text.len(),
+                _ => continue,
+            }
+
+            self.view.render(&mut terminal.lock_prompt(), &line)?;
+        }
+    }
+}
+
+impl View {
+
+    // Gives the number of columns `text` takes up when shown, masked or not.
+    fn columns(&self, text: &str) -> usize {
+        match self.mask {
+            Some(mask) => graphemes(text).count() * visible_width(&mask.to_string()),
+            None => graphemes(text).map(grapheme_width).sum(),
+        }
+    }
+
+    // Redraws the prompt and the line, placing the cursor where it is in the line.
+    fn render(&self, output: &mut StdoutLock, line: &Line) -> IoResult<()> {
+        let shown = match self.mask {
+            Some(mask) => graphemes(&line.text).map(|_| mask).collect(),
+            None => line.text.clone(),
+        };
+
+        output.atomic(|output| {
+            output.print("\r")?;
+            output.clear_line_to_end()?;
+            output.print(&self.prompt)?;
+            output.print(&shown)?;
+            output.move_to_column(visible_width(&self.prompt) + self.columns(&line.text[..line.cursor]) + 1)?;
+            output.flush()
+        })
+    }
+}
+
+/// A handle printing lines above the prompt of a [`LineEditor`], such as log lines from worker threads.
+/// Created with [`LineEditor::printer`], it can be cloned and sent to other threads.
+#[derive(Debug, Clone)]
+pub struct ExternalPrinter {
+    view: View,
+    line: Arc<Mutex<Line>>,
+    terminal: Terminal,
+}
+
+impl ExternalPrinter {
+    /// Prints a line above the prompt, drawing the prompt and the text typed so far again below it.
+    /// Lines printed from several threads at once are printed one after another, never mixed with the prompt.
+    pub fn println(&self, text: &str) -> IoResult<()> {
+        let guard = suspend(&self.view, &self.line)?;
+        let terminal = guard.line.terminal.as_ref().unwrap_or(&self.terminal);
+        terminal.lock_prompt().println(text)
+    }
+}
+
+// Clears the prompt of the line being read, if any, until the returned guard is dropped.
+fn suspend<'a>(view: &'a View, line: &'a Mutex<Line>) -> IoResult<SuspendGuard<'a>> {
+    let line = line.lock().unwrap_or_else(PoisonError::into_inner);
+
+    if let Some(terminal) = &line.terminal {
+        let mut output = terminal.lock_prompt();
+        output.print("\r")?;
+        output.clear_line_to_end()?;
+        output.flush()?;
+    }
+
+    Ok(SuspendGuard { view, line })
+}
+
+// Applies `completion` to the line, listing its candidates above the prompt when it cannot be applied further.
+fn complete(completion: Completion, text: &mut String, cursor: &mut usize, terminal: &Terminal) -> IoResult<()> {
+    // Ranges not on character boundaries of the line are ignored
+    if completion.candidates.is_empty() || text.get(completion.range.clone()).is_none() { return Ok(()) }
+
+    let Completion { range, candidates } = &completion;
+    let replacement = completion.shared_prefix();
+
+    if candidates.len() > 1 && (replacement.is_empty() || replacement == &text[range.clone()]) {
+        let mut output = terminal.lock_prompt();
+        output.print("\r")?;
+        output.clear_line_to_end()?;
+        return output.println(&candidates.join("  "));
+    }
+
+    text.replace_range(range.clone(), replacement);
+    *cursor = range.start + replacement.len();
+    Ok(())
+}
+
+// Gives the byte index of the start of the grapheme cluster before `index`, if any.
+fn previous_boundary(text: &str, index: usize) -> Option<usize> {
+    let mut start = 0;
+    let mut previous = None;
+
+    while start < index {
+        previous = Some(start);
+        start += grapheme_len(&text[start..]);
+    }
+
+    previous
+}
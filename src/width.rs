@@ -0,0 +1,374 @@
+//! Measuring how many columns text takes up on a terminal.
+//!
+//! ```rust,ignore
+//! use in_keys::width::visible_width;
+//!
+//! let styled = "\x1b[1mBold\x1b[0m";
+//! assert_eq!(visible_width(styled), 4);
+//! ```
+
+use std::str::Chars;
+
+// Ranges of characters that take no columns: combining marks, zero-width spaces and joiners,
+// and variation selectors.
+const ZERO: &[(u32, u32)] = &[
+    (0x0300, 0x036F),
+    (0x0483, 0x0489),
+    (0x0591, 0x05BD),
+    (0x0610, 0x061A),
+    (0x064B, 0x065F),
+    (0x0E31, 0x0E31),
+    (0x0E34, 0x0E3A),
+    (0x0E47, 0x0E4E),
+    (0x1AB0, 0x1AFF),
+    (0x1DC0, 0x1DFF),
+    (0x200B, 0x200F),
+    (0x2060, 0x2064),
+    (0x20D0, 0x20FF),
+    (0xFE00, 0xFE0F),
+    (0xFE20, 0xFE2F),
+    (0xFEFF, 0xFEFF),
+    (0xE0100, 0xE01EF),
+];
+
+// Ranges of characters that take two columns: East Asian wide and fullwidth characters, and emoji.
+const WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115F),
+    (0x231A, 0x231B),
+    (0x2329, 0x232A),
+    (0x23E9, 0x23EC),
+    (0x25FD, 0x25FE),
+    (0x2614, 0x2615),
+    (0x26AA, 0x26AB),
+    (0x26BD, 0x26BE),
+    (0x26F5, 0x26F5),
+    (0x26FA, 0x26FA),
+    (0x2705, 0x2705),
+    (0x270A, 0x270B),
+    (0x274C, 0x274C),
+    (0x2753, 0x2755),
+    (0x2795, 0x2797),
+    (0x2B1B, 0x2B1C),
+    (0x2E80, 0x303E),
+    (0x3041, 0x33FF),
+    (0x3400, 0x4DBF),
+    (0x4E00, 0x9FFF),
+    (0xA000, 0xA4CF),
+    (0xA960, 0xA97F),
+    (0xAC00, 0xD7A3),
+    (0xF900, 0xFAFF),
+    (0xFE10, 0xFE19),
+    (0xFE30, 0xFE6F),
+    (0xFF00, 0xFF60),
+    (0xFFE0, 0xFFE6),
+    (0x16FE0, 0x16FE4),
+    (0x17000, 0x18CFF),
+    (0x1B000, 0x1B2FF),
+    (0x1F004, 0x1F004),
+    (0x1F0CF, 0x1F0CF),
+    (0x1F18E, 0x1F18E),
+    (0x1F191, 0x1F19A),
+    (0x1F200, 0x1F251),
+    (0x1F300, 0x1F64F),
+    (0x1F680, 0x1F6FF),
+    (0x1F7E0, 0x1F7EB),
+    (0x1F90C, 0x1F9FF),
+    (0x1FA70, 0x1FAFF),
+    (0x20000, 0x2FFFD),
+    (0x30000, 0x3FFFD),
+];
+
+// Whether `char` lies in one of the sorted `ranges`.
+fn within(ranges: &[(u32, u32)], char: char) -> bool {
+    let code = char as u32;
+    ranges
+        .binary_search_by(|&(start, end)| match () {
+            _ if end < code => std::cmp::Ordering::Less,
+            _ if start > code => std::cmp::Ordering::Greater,
+            _ => std::cmp::Ordering::Equal,
+        })
+        .is_ok()
+}
+
+/// Gives the number of columns a character takes up on a terminal.
+/// Wide characters, such as CJK ideographs and most emoji, take two; combining marks and
+/// control characters take none.
+pub fn char_width(char: char) -> usize {
+    match char {
+        char if char.is_control() => 0,
+        char if within(ZERO, char) => 0,
+        char if within(WIDE, char) => 2,
+        _ => 1,
+    }
+}
+
+/// Gives the number of columns a string takes up on a terminal, skipping ANSI escape sequences
+/// such as the ones styling text, so already styled strings can be aligned and padded.
+/// Control characters, including newlines, take no columns.
+pub fn visible_width(str: &str) -> usize {
+    let mut chars = str.chars();
+    let mut width = 0;
+
+    while let Some(char) = chars.next() {
+        match char {
+            '\x1b' => { skip_escape(&mut chars); },
+            char => width += char_width(char),
+        }
+    }
+
+    width
+}
+
+/// Gives the column the cursor reaches when `text` is written from column `start`, counting from 1,
+/// on a line wide enough to hold it. Wide characters move the cursor by two columns, tabs move it
+/// to the next multiple of eight columns, and escape sequences do not move it.
+pub fn column_after(text: &str, start: usize) -> usize {
+    let mut chars = text.chars();
+    let mut column = start.max(1);
+
+    while let Some(char) = chars.next() {
+        match char {
+            '\x1b' => { skip_escape(&mut chars); },
+            '\t' => column = (column - 1) / 8 * 8 + 9,
+            char => column += char_width(char),
+        }
+    }
+
+    column
+}
+
+// Whether a character is a regional indicator, two of which make up a flag.
+fn is_regional(char: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&char)
+}
+
+// The kinds of Hangul jamo, which join into syllables.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Jamo {
+    // A leading consonant
+    Leading,
+    // A vowel, or a precomposed syllable without a trailing consonant
+    Vowel,
+    // A trailing consonant, or a precomposed syllable with one
+    Trailing,
+}
+
+// Gives the kind of Hangul jamo a character is, if any.
+fn jamo(char: char) -> Option<Jamo> {
+    match char as u32 {
+        0x1100..=0x115F | 0xA960..=0xA97C => Some(Jamo::Leading),
+        0x1160..=0x11A7 | 0xD7B0..=0xD7C6 => Some(Jamo::Vowel),
+        0x11A8..=0x11FF | 0xD7CB..=0xD7FB => Some(Jamo::Trailing),
+        code @ 0xAC00..=0xD7A3 if (code - 0xAC00) % 28 == 0 => Some(Jamo::Vowel),
+        0xAC00..=0xD7A3 => Some(Jamo::Trailing),
+        _ => None,
+    }
+}
+
+// Gives the length in bytes of the grapheme cluster, or user-perceived character, at the start of
+// `str`: a character along with the combining marks, variation selectors and emoji modifiers after it,
+// characters joined by zero-width joiners, pairs of regional indicators, and Hangul syllables
+// spelled out in jamo. A simplification of the Unicode rules covering the clusters met in practice.
+pub(crate) fn grapheme_len(str: &str) -> usize {
+    let mut chars = str.char_indices().peekable();
+    let Some((_, mut previous)) = chars.next() else { return 0 };
+    let mut regional = is_regional(previous) as usize;
+
+    while let Some(&(index, char)) = chars.peek() {
+        let joins = match (jamo(previous), jamo(char)) {
+            // Carriage returns and line feeds stay together
+            _ if previous == '\r' => char == '\n',
+            (Some(Jamo::Leading), Some(_)) => true,
+            (Some(Jamo::Vowel), Some(Jamo::Vowel | Jamo::Trailing)) => true,
+            (Some(Jamo::Trailing), Some(Jamo::Trailing)) => true,
+            _ if previous == '\u{200D}' => true,
+            // Flags are made of two regional indicators, so a third starts a new flag
+            _ if is_regional(char) => regional % 2 == 1,
+            _ => !char.is_control() && (within(ZERO, char) || matches!(char as u32, 0x1F3FB..=0x1F3FF | 0xE0020..=0xE007F)),
+        };
+
+        if !joins { return index; }
+
+        regional += is_regional(char) as usize;
+        previous = char;
+        chars.next();
+    }
+
+    str.len()
+}
+
+// Splits a string into its grapheme clusters, as `grapheme_len` finds them.
+pub(crate) fn graphemes(str: &str) -> impl Iterator<Item = &str> {
+    let mut rest = str;
+    std::iter::from_fn(move || {
+        let (grapheme, tail) = rest.split_at(grapheme_len(rest));
+        rest = tail;
+        (!grapheme.is_empty()).then_some(grapheme)
+    })
+}
+
+// Gives the number of columns a grapheme cluster takes up, which is that of its widest character,
+// or two for flags and characters shown as emoji through a variation selector.
+pub(crate) fn grapheme_width(grapheme: &str) -> usize {
+    match grapheme {
+        _ if grapheme.chars().filter(|&char| is_regional(char)).count() == 2 => 2,
+        _ if grapheme.contains('\u{FE0F}') => 2,
+        _ => grapheme.chars().map(char_width).max().unwrap_or_default(),
+    }
+}
+
+// The kinds of escape sequences.
+pub(crate) enum Escape {
+    // A control sequence (`ESC [`), with its final character
+    Control(Option<char>),
+    // An operating system command (`ESC ]`), such as setting the title or a hyperlink
+    Command,
+    // Any other escape sequence, taking a single character after the escape
+    Other,
+}
+
+// Skips the escape sequence following an escape character, giving its kind.
+pub(crate) fn skip_escape(chars: &mut Chars) -> Escape {
+    match chars.next() {
+        // Control sequences end with a character from `@` to `~`
+        Some('[') => Escape::Control(chars.find(|char| ('@'..='~').contains(char))),
+        // Operating system commands end with BEL or ST (`ESC \`)
+        Some(']') => {
+            while let Some(char) = chars.next() {
+                match char {
+                    '\x07' => break,
+                    '\x1b' => { chars.next(); break },
+                    _ => {},
+                }
+            }
+
+            Escape::Command
+        },
+        _ => Escape::Other,
+    }
+}
+
+// Wraps text on word boundaries so no line takes up more than `width` columns, keeping existing
+// newlines. Lines after the first of each paragraph start with `indent` spaces.
+// Words too long to fit on a line of their own are broken up.
+pub(crate) fn wrap(text: &str, width: usize, indent: usize) -> Vec<String> {
+    let width = width.max(1);
+    // An indent leaving no room for text is dropped
+    let indent = if indent < width { indent } else { 0 };
+    let mut lines = Vec::new();
+
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut used = 0;
+        // Whether the line holds any words yet, as opposed to only the indent
+        let mut started = false;
+
+        for word in paragraph.split_whitespace() {
+            let mut word = word;
+            let mut word_width = visible_width(word);
+
+            // Move to a new line if the word does not fit after the ones already on this one
+            if started && used + 1 + word_width > width {
+                lines.push(std::mem::replace(&mut line, " ".repeat(indent)));
+                (used, started) = (indent, false);
+            }
+
+            // Break up words that do not fit on a line of their own
+            while used + word_width > width {
+                let (head, tail) = split_at_width(word, width - used);
+                // A character wider than the line is left sticking out of it, rather than followed by an empty line
+                if tail.is_empty() { break; }
+
+                line.push_str(head);
+                lines.push(std::mem::replace(&mut line, " ".repeat(indent)));
+                used = indent;
+                (word, word_width) = (tail, visible_width(tail));
+            }
+
+            if started {
+                line.push(' ');
+                used += 1;
+            }
+
+            line.push_str(word);
+            used += word_width;
+            started = true;
+        }
+
+        lines.push(line);
+    }
+
+    lines
+}
+
+// Splits a string after as many characters as fit in `width` columns, taking at least one.
+fn split_at_width(str: &str, width: usize) -> (&str, &str) {
+    let mut used = 0;
+    for (index, char) in str.char_indices() {
+        let width_of = char_width(char);
+        if used + width_of > width && index > 0 {
+            return str.split_at(index);
+        }
+
+        used += width_of;
+    }
+
+    (str, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn character_widths() {
+        assert_eq!(char_width('a'), 1);
+        assert_eq!(char_width('中'), 2);
+        assert_eq!(char_width('😀'), 2);
+        assert_eq!(char_width('\u{301}'), 0);
+        assert_eq!(char_width('\u{200B}'), 0);
+        assert_eq!(char_width('\n'), 0);
+    }
+
+    #[test]
+    fn visible_widths_skip_escape_sequences() {
+        assert_eq!(visible_width("\x1b[31m中a\x1b[0m"), 3);
+        assert_eq!(visible_width("\x1b]8;;https://example.com\x07link\x1b]8;;\x1b\\"), 4);
+        assert_eq!(visible_width("e\u{301}"), 1);
+    }
+
+    #[test]
+    fn columns_after_text() {
+        assert_eq!(column_after("abc", 1), 4);
+        assert_eq!(column_after("中", 3), 5);
+        assert_eq!(column_after("\t", 1), 9);
+        assert_eq!(column_after("ab\t", 1), 9);
+        assert_eq!(column_after("\x1b[1mab", 0), 3);
+    }
+
+    #[test]
+    fn grapheme_clusters() {
+        assert_eq!(graphemes("e\u{301}x").collect::<Vec<_>>(), ["e\u{301}", "x"]);
+        assert_eq!(graphemes("🇩🇪🇫🇷").collect::<Vec<_>>(), ["🇩🇪", "🇫🇷"]);
+        assert_eq!(graphemes("👩\u{200D}💻!").collect::<Vec<_>>(), ["👩\u{200D}💻", "!"]);
+        assert_eq!(graphemes("\r\n").count(), 1);
+        assert_eq!(grapheme_len("\u{1100}\u{1161}\u{11A8}a"), 9);
+        assert_eq!(grapheme_width("🇩🇪"), 2);
+        assert_eq!(grapheme_width("\u{2764}\u{FE0F}"), 2);
+    }
+
+    #[test]
+    fn wraps_on_word_boundaries() {
+        assert_eq!(wrap("hello world", 5, 0), ["hello", "world"]);
+        assert_eq!(wrap("aa bb cc", 5, 2), ["aa bb", "  cc"]);
+        assert_eq!(wrap("one\n\ntwo", 10, 0), ["one", "", "two"]);
+    }
+
+    #[test]
+    fn breaks_up_long_and_wide_words() {
+        assert_eq!(wrap("abcdefgh", 3, 0), ["abc", "def", "gh"]);
+        assert_eq!(wrap("中中中", 4, 0), ["中中", "中"]);
+        // A wide character is kept whole even where it cannot fit
+        assert_eq!(wrap("中", 1, 0), ["中"]);
+    }
+}